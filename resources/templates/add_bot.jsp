@@ -69,6 +69,15 @@
     <div class="bg-light p-5 rounded">
         <h2>Start New Bot</h2>
         <form class="form" action="#" method="POST" th:action="@{/bots/add/post}" th:object="${newBot}">
+            <div class="form-row">
+                <div class="form-group col-md-12">
+                    <label for="strategy">Strategy</label>
+                    <select id="strategy" class="form-control" name="strategy">
+                        <option th:each="kind : ${strategyKinds}" th:value="${kind.name}"
+                                th:text="${kind.name + ' - ' + kind.description}"></option>
+                    </select>
+                </div>
+            </div>
             <div class="form-row">
                 <div class="form-group col-md-6">
                     <label for="inputAddress">Market ID</label>
@@ -81,11 +90,11 @@
             </div>
             <div class="form-row">
                 <div class="form-group col-md-4">
-                    <label for="amountBid">Bid Amount</label>
+                    <label for="amountBid">Bid Amount (quote)</label>
                     <input type="text" class="form-control" id="amountBid" th:field="*{amountBid}">
                 </div>
                 <div class="form-group col-md-4">
-                    <label for="amountAsk">Ask Amount</label>
+                    <label for="amountAsk">Ask Amount (base)</label>
                     <input type="text" class="form-control" id="amountAsk" th:field="*{amountAsk}">
                 </div>
                 <div class="form-group col-md-4">
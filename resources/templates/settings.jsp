@@ -69,20 +69,150 @@
     <div class="bg-light p-5 rounded">
         <h2>Settings</h2>
         <div class="input-group mb-3">
-            <form class="form-signin" method="POST" action="${pageContext.request.contextPath}/settings/save">
-                <div class="input-group-prepend">
-                    <span class="input-group-text" id="rpc-server-text">RPC Server</span>
+            <form class="form-signin" method="POST" action="/settings/save">
+                <p>Saved to <span th:text="${configPath}"></span>. RPC changes apply after a restart, bot defaults apply to new bots.</p>
+                <div class="input-group mb-2">
+                    <span class="input-group-text" id="rpc-server-text">RPC Servers</span>
+                    <input class="form-control" type="text" required aria-describedby="rpc-server-text" name="rpc_urls"
+                           th:value="${#strings.listJoin(config.rpc.urls, ',')}">
+                </div>
+                <div class="input-group mb-2">
+                    <span class="input-group-text" id="ws-url-text">Websocket</span>
+                    <input class="form-control" type="text" aria-describedby="ws-url-text" name="ws_url"
+                           th:value="${config.rpc.wsUrl}" placeholder="derived from the first RPC server">
+                </div>
+                <div class="input-group mb-2">
+                    <span class="input-group-text" id="rps-text">Requests/s per endpoint</span>
+                    <input class="form-control" type="number" step="any" min="0" required aria-describedby="rps-text"
+                           name="requests_per_second" th:value="${config.rpc.requestsPerSecond}">
+                </div>
+                <h5>Bot Defaults</h5>
+                <div class="input-group mb-2">
+                    <span class="input-group-text" id="price-strategy-text">Pricing</span>
+                    <select class="form-select" aria-describedby="price-strategy-text" name="price_strategy">
+                        <option value="jupiter" th:selected="${config.bot.priceStrategy == 'jupiter'}">Jupiter</option>
+                        <option value="orderbook" th:selected="${config.bot.priceStrategy == 'orderbook'}">Order Book</option>
+                    </select>
+                </div>
+                <div class="input-group mb-2">
+                    <span class="input-group-text" id="bps-spread-text">Spread (bps)</span>
+                    <input class="form-control" type="number" step="any" min="0" required aria-describedby="bps-spread-text"
+                           name="bps_spread" th:value="${config.bot.bpsSpread}">
+                </div>
+                <div class="input-group mb-2">
+                    <span class="input-group-text" id="amount-bid-text">Bid Size (quote)</span>
+                    <input class="form-control" type="number" step="any" min="0" required aria-describedby="amount-bid-text"
+                           name="amount_bid" th:value="${config.bot.amountBid}">
+                    <span class="input-group-text" id="amount-ask-text">Ask Size (base)</span>
+                    <input class="form-control" type="number" step="any" min="0" required aria-describedby="amount-ask-text"
+                           name="amount_ask" th:value="${config.bot.amountAsk}">
+                </div>
+                <div class="input-group mb-2">
+                    <span class="input-group-text" id="loop-interval-text">Loop Interval (ms)</span>
+                    <input class="form-control" type="number" min="1" required aria-describedby="loop-interval-text"
+                           name="loop_interval_ms" th:value="${config.bot.loopIntervalMs}">
                 </div>
-                <label>
-                    <input class="form-control" type="text" required aria-describedby="rpc-server-text" name="rpc">
-                </label>
                 <button class="btn btn-primary btn-block" type="submit">Save</button>
             </form>
+            <table class="table">
+                <thead>
+                <tr>
+                    <th scope="col">Endpoint</th>
+                    <th scope="col">Status</th>
+                    <th scope="col">Slot</th>
+                    <th scope="col">Slot Lag</th>
+                    <th scope="col">Latency (ms)</th>
+                    <th scope="col">Error Rate</th>
+                    <th scope="col">Last Error</th>
+                </tr>
+                </thead>
+                <tbody>
+                <tr th:each="endpoint : ${rpcEndpoints}">
+                    <td th:text="${endpoint.url}"></td>
+                    <td th:text="${endpoint.healthy} ? 'Healthy' : 'Unhealthy'"></td>
+                    <td th:text="${endpoint.slot}"></td>
+                    <td th:text="${endpoint.slotLag}"></td>
+                    <td th:text="${endpoint.latencyMs}"></td>
+                    <td th:text="${#numbers.formatPercent(endpoint.errorRate, 1, 1)}"></td>
+                    <td th:text="${endpoint.lastError}"></td>
+                </tr>
+                </tbody>
+            </table>
+            <table class="table table-sm">
+                <thead>
+                <tr>
+                    <th scope="col">RPC Method</th>
+                    <th scope="col">Requests</th>
+                    <th scope="col">Retries</th>
+                    <th scope="col">Failures</th>
+                </tr>
+                </thead>
+                <tbody>
+                <tr th:each="method : ${rpcMethods}">
+                    <td th:text="${method.method}"></td>
+                    <td th:text="${method.requests}"></td>
+                    <td th:text="${method.retries}"></td>
+                    <td th:text="${method.failures}"></td>
+                </tr>
+                </tbody>
+            </table>
             <hr>
             Current account: <span th:text="${tradingAccountPubkey}"></span>
+            <div th:if="${remoteSigner}">Signing through external signer: <span th:text="${remoteSigner}"></span></div>
+            <hr>
+            <h4>Keystore</h4>
+            <form th:if="${!keystoreUnlocked}" method="POST" action="/keystore/unlock">
+                <label>
+                    <input class="form-control" type="password" required name="passphrase" placeholder="Passphrase">
+                </label>
+                <button class="btn btn-primary" type="submit">Unlock</button>
+            </form>
+            <form th:if="${keystoreUnlocked}" method="POST" action="/keystore/lock">
+                <button class="btn btn-secondary" type="submit">Lock</button>
+            </form>
+            <table class="table">
+                <thead>
+                <tr>
+                    <th scope="col">Label</th>
+                    <th scope="col">Public Key</th>
+                    <th scope="col">Actions</th>
+                </tr>
+                </thead>
+                <tbody>
+                <tr th:each="key : ${keys}">
+                    <td th:text="${key.label}"></td>
+                    <td th:text="${key.pubkey}"></td>
+                    <td>
+                        <form method="POST" action="/keys/remove">
+                            <input type="hidden" name="pubkey" th:value="${key.pubkey}"/>
+                            <button class="btn btn-danger btn-sm" type="submit" th:disabled="${!keystoreUnlocked}">Remove</button>
+                        </form>
+                    </td>
+                </tr>
+                </tbody>
+            </table>
             <form method="POST" action="/privateKeyUpload" enctype="multipart/form-data">
+                <input type="text" name="label" placeholder="Label"/>
                 <input type="file" name="file"/><br/><br/>
-                <input type="submit" value="Upload Private Key"/>
+                <input type="submit" value="Upload Private Key" th:disabled="${!keystoreUnlocked}"/>
+            </form>
+        </div>
+    </div>
+    <div class="row">
+        <div class="col">
+            <h4>Kill switch</h4>
+            <p>Stops every bot and cancels all of their resting orders, using the pre-signed cancels first. The server keeps running.</p>
+            <form method="POST" action="/kill" onsubmit="return confirm('Stop every bot and cancel all orders?');">
+                <button class="btn btn-danger" type="submit">Kill switch</button>
+            </form>
+        </div>
+    </div>
+    <div class="row">
+        <div class="col">
+            <h4>Shutdown</h4>
+            <p>Stops every bot, cancels its orders and settles its funds, then stops the server.</p>
+            <form method="POST" action="/shutdown" onsubmit="return confirm('Shut down Arcana?');">
+                <button class="btn btn-danger" type="submit">Shut down</button>
             </form>
         </div>
     </div>
@@ -73,9 +73,9 @@
         <hr>
         Market ID: <span th:text="${botMarketId}"></span>
         <hr>
-        Bps Spread: <span th:text="${botBpsSpread}"></span>
+        Spread: Bid <span th:text="${botBidSpreadBps}"></span> bps, Ask <span th:text="${botAskSpreadBps}"></span> bps
         <hr>
-        Quote Sizes: Bid <span th:text="${botAmountBid}"></span>, Ask <span th:text="${botAmountAsk}"></span>
+        Sizes: Bid <span th:text="${botAmountBid}"></span> quote, Ask <span th:text="${botAmountAsk}"></span> base
         <hr>
         OOA: <span th:text="${botOoa}"></span>, Base Wallet: <span th:text="${botBaseWallet}"></span>, Quote Wallet:
         <span th:text="${botQuoteWallet}"></span>
@@ -84,6 +84,39 @@
         <hr>
         Last Ask order: <span th:text="${lastAskOrder}"></span>
     </div>
+    <div class="row mt-3">
+        <div class="col">
+            <h4>Quote parameters</h4>
+            <p>Applied on the bot's next tick. Empty fields keep their current value.</p>
+            <form method="POST" th:action="@{/bots/{uuid}/params(uuid=${botUuid})}">
+                <div class="mb-2">
+                    <label class="form-label">Spread (bps, both sides)</label>
+                    <input class="form-control" type="number" step="any" name="bps_spread">
+                </div>
+                <div class="mb-2">
+                    <label class="form-label">Bid spread (bps)</label>
+                    <input class="form-control" type="number" step="any" name="bid_spread_bps" th:placeholder="${botBidSpreadBps}">
+                </div>
+                <div class="mb-2">
+                    <label class="form-label">Ask spread (bps)</label>
+                    <input class="form-control" type="number" step="any" name="ask_spread_bps" th:placeholder="${botAskSpreadBps}">
+                </div>
+                <div class="mb-2">
+                    <label class="form-label">Bid size (quote)</label>
+                    <input class="form-control" type="number" step="any" name="amount_bid" th:placeholder="${botAmountBid}">
+                </div>
+                <div class="mb-2">
+                    <label class="form-label">Ask size (base)</label>
+                    <input class="form-control" type="number" step="any" name="amount_ask" th:placeholder="${botAmountAsk}">
+                </div>
+                <div class="mb-2">
+                    <label class="form-label">Min midpoint change</label>
+                    <input class="form-control" type="number" step="any" name="min_midpoint_change" th:placeholder="${botMinMidpointChange}">
+                </div>
+                <button class="btn btn-primary" type="submit">Update</button>
+            </form>
+        </div>
+    </div>
 </main>
 
 
@@ -1,49 +1,590 @@
 //use rocket::request::FromData;
 //use rocket::response::NamedFile;
-use solana_client::rpc_client::RpcClient;
-
-pub(crate) fn rpc_client() -> RpcClient {
-    let rpc_client = RpcClient::new("https://try-rpc.mainnet.solana.blockdaemon.tech");
-    //let rpc_client = RpcClient::new("http://localhost:8899".to_string()); //todo Replace with your Solana RPC URL
-
-    rpc_client
-}
-
-struct SerumManager {
-    rpc_client: RpcClient,
-}
-
-impl SerumManager {
-    // Implement your SerumManager methods here
-}
-//
-// #[derive(Serialize)]
-// struct Context {
-//     title: &'static str,
-//     // Add other context data here
-// }
-//
-// #[get("/")]
-// fn index() -> Template {
-//     let context = Context { title: "Arcana" };
-//     Template::render("index", &context)
-// }
-
-// fn main() {
-//     let config = Config::build(Environment::Development)
-//         .address("127.0.0.1")
-//         .port(8000)
-//         .finalize()
-//         .unwrap();
-//
-//     rocket::custom(config)
-//         .attach(AdHoc::on_attach("Database Configuration", |rocket| {
-//             Ok(rocket.manage(SerumManager {
-//                 rpc_client: RpcClient,
-//             }))
-//         }))
-//         .attach(Template::fairing())
-//         .mount("/", StaticFiles::from("static"))
-//         .mount("/", routes![index])
-//         .launch();
-// }
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use crate::rpc::retry::RetryPolicy;
+use crate::rpc::rpc_pool::RpcPool;
+use crate::transactions::priority_fees::PriorityFeePolicy;
+
+const DEFAULT_CONFIG_PATH: &str = "arcana.toml";
+const DEFAULT_RPC_URL: &str = "https://try-rpc.mainnet.solana.blockdaemon.tech";
+const PRICE_STRATEGIES: [&str; 2] = ["jupiter", "orderbook"];
+
+//the effective config, i.e. the file with env overrides applied
+static CONFIG: OnceLock<RwLock<ArcanaConfig>> = OnceLock::new();
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArcanaConfig {
+    pub server: ServerConfig,
+    pub rpc: RpcConfig,
+    pub keys: KeysConfig,
+    pub storage: StorageConfig,
+    pub pricing: PricingConfig,
+    pub compute_budget: PriorityFeePolicy,
+    pub bot: BotDefaults,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub address: String,
+    pub port: u16,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RpcConfig {
+    pub urls: Vec<String>,
+    //websocket endpoint for account subscriptions, by default the first rpc url with a ws scheme
+    pub ws_url: Option<String>,
+    //per endpoint, most public and free tier endpoints allow somewhere around 10
+    pub requests_per_second: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysConfig {
+    pub keystore_path: String,
+    //unix:/path/to/socket or http://host:port of an arcana-signer, when set the keystore is never used for signing
+    pub signer: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    //sqlite file holding the bots and their last known order state, created if missing
+    pub database_path: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PricingConfig {
+    pub jupiter_price_url: String,
+}
+
+//what a new bot starts with, everything here can be changed per bot afterwards
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotDefaults {
+    pub market_id: String,
+    //jupiter or orderbook
+    pub price_strategy: String,
+    //either side of the reference price, bid_spread_bps and ask_spread_bps override it for one side
+    pub bps_spread: f64,
+    pub bid_spread_bps: Option<f64>,
+    pub ask_spread_bps: Option<f64>,
+    //quote notional, the base quantity follows from the bid price
+    pub amount_bid: f64,
+    //base units
+    pub amount_ask: f64,
+    pub loop_interval_ms: u64,
+    //fraction the quote has to move by before it is replaced
+    pub min_midpoint_change: f64,
+    //failures in a row before the bot is marked failed and its orders cancelled, a run longer than
+    //restart_max_delay_ms resets the count
+    pub max_restarts: u32,
+    pub restart_base_delay_ms: u64,
+    pub restart_max_delay_ms: u64,
+    //how long every bot together gets to cancel and settle when the server shuts down
+    pub shutdown_timeout_ms: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            //the ui can move funds and shut the server down, it only listens on other interfaces when told to
+            address: "127.0.0.1".to_string(),
+            port: 8080,
+        }
+    }
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            urls: vec![DEFAULT_RPC_URL.to_string()],
+            ws_url: None,
+            requests_per_second: 10.0,
+        }
+    }
+}
+
+impl Default for KeysConfig {
+    fn default() -> Self {
+        Self {
+            keystore_path: "arcana_keystore.json".to_string(),
+            signer: None,
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            database_path: "arcana.db".to_string(),
+        }
+    }
+}
+
+impl Default for PricingConfig {
+    fn default() -> Self {
+        Self {
+            jupiter_price_url: "https://price.jup.ag/v4/price".to_string(),
+        }
+    }
+}
+
+impl Default for BotDefaults {
+    fn default() -> Self {
+        Self {
+            market_id: "9Lyhks5bQQxb9EyyX55NtgKQzpM4WK7JCmeaWuQ5MoXD".to_string(),
+            price_strategy: "jupiter".to_string(),
+            bps_spread: 10.0,
+            bid_spread_bps: None,
+            ask_spread_bps: None,
+            amount_bid: 10.0,
+            amount_ask: 0.1,
+            loop_interval_ms: 5000,
+            min_midpoint_change: 0.0010,
+            max_restarts: 5,
+            restart_base_delay_ms: 1000,
+            restart_max_delay_ms: 60000,
+            shutdown_timeout_ms: 30000,
+        }
+    }
+}
+
+impl ArcanaConfig {
+    //a missing file is not an error, everything has a default
+    pub fn read_file(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        toml::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    //write to a sibling file first so a crash mid-write can't leave a truncated config behind
+    pub fn write_file(&self, path: &Path) -> Result<(), String> {
+        let contents = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, contents)
+            .and_then(|_| fs::rename(&tmp_path, path))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    //ARCANA_RPC_URLS is comma separated, e.g. http://localhost:8899,https://api.mainnet-beta.solana.com
+    pub fn apply_env_overrides(&mut self) -> Result<(), String> {
+        self.apply_overrides(env)
+    }
+
+    //var gives the value of a variable that is set and not blank
+    fn apply_overrides(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        if let Some(address) = var("ARCANA_ADDRESS") {
+            self.server.address = address;
+        }
+        if let Some(port) = var("ARCANA_PORT") {
+            self.server.port = port.parse().map_err(|_| format!("ARCANA_PORT: {} is not a port", port))?;
+        }
+        if let Some(urls) = var("ARCANA_RPC_URLS") {
+            self.rpc.urls = urls.split(',')
+                .map(|url| url.trim().to_string())
+                .filter(|url| !url.is_empty())
+                .collect();
+        }
+        if let Some(ws_url) = var("ARCANA_WS_URL") {
+            self.rpc.ws_url = Some(ws_url);
+        }
+        if let Some(requests_per_second) = var("ARCANA_RPC_REQUESTS_PER_SECOND") {
+            self.rpc.requests_per_second = requests_per_second.parse()
+                .map_err(|_| format!("ARCANA_RPC_REQUESTS_PER_SECOND: {} is not a number", requests_per_second))?;
+        }
+        if let Some(keystore_path) = var("ARCANA_KEYSTORE_PATH") {
+            self.keys.keystore_path = keystore_path;
+        }
+        if let Some(signer) = var("ARCANA_SIGNER") {
+            self.keys.signer = Some(signer);
+        }
+        if let Some(database_path) = var("ARCANA_DATABASE_PATH") {
+            self.storage.database_path = database_path;
+        }
+        Ok(())
+    }
+
+    //every problem at once, so a bad config takes one edit to fix rather than one restart per field
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        if self.server.address.parse::<IpAddr>().is_err() {
+            problems.push(format!("server.address: {} is not an ip address", self.server.address));
+        }
+        if self.server.port == 0 {
+            problems.push("server.port: must not be 0".to_string());
+        }
+
+        if self.rpc.urls.is_empty() {
+            problems.push("rpc.urls: at least one rpc url is needed".to_string());
+        }
+        for url in &self.rpc.urls {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                problems.push(format!("rpc.urls: {} is not an http(s) url", url));
+            }
+        }
+        if let Some(ws_url) = &self.rpc.ws_url {
+            if !ws_url.starts_with("ws://") && !ws_url.starts_with("wss://") {
+                problems.push(format!("rpc.ws_url: {} is not a ws(s) url", ws_url));
+            }
+        }
+        if !above(self.rpc.requests_per_second, 0.0) {
+            problems.push("rpc.requests_per_second: must be above 0".to_string());
+        }
+
+        if self.keys.keystore_path.trim().is_empty() {
+            problems.push("keys.keystore_path: must not be empty".to_string());
+        }
+        if let Some(signer) = &self.keys.signer {
+            if !signer.starts_with("unix:") && !signer.starts_with("http://") && !signer.starts_with("https://") {
+                problems.push(format!("keys.signer: {} is neither unix:<path> nor an http(s) url", signer));
+            }
+        }
+
+        if self.storage.database_path.trim().is_empty() {
+            problems.push("storage.database_path: must not be empty".to_string());
+        }
+
+        if !self.pricing.jupiter_price_url.starts_with("http://") && !self.pricing.jupiter_price_url.starts_with("https://") {
+            problems.push(format!("pricing.jupiter_price_url: {} is not an http(s) url", self.pricing.jupiter_price_url));
+        }
+
+        let fees = &self.compute_budget;
+        if !(0.0..=100.0).contains(&fees.percentile) {
+            problems.push("compute_budget.percentile: must be between 0 and 100".to_string());
+        }
+        if fees.min_micro_lamports > fees.max_micro_lamports {
+            problems.push("compute_budget.min_micro_lamports: must not be above max_micro_lamports".to_string());
+        }
+        if !at_least(fees.compute_unit_margin, 1.0) {
+            problems.push("compute_budget.compute_unit_margin: must be at least 1".to_string());
+        }
+        if fees.fallback_compute_unit_limit == 0 {
+            problems.push("compute_budget.fallback_compute_unit_limit: must be above 0".to_string());
+        }
+
+        let bot = &self.bot;
+        if bot.market_id.parse::<Pubkey>().is_err() {
+            problems.push(format!("bot.market_id: {} is not a pubkey", bot.market_id));
+        }
+        if !PRICE_STRATEGIES.iter().any(|strategy| strategy.eq_ignore_ascii_case(&bot.price_strategy)) {
+            problems.push(format!("bot.price_strategy: {} is not one of {}", bot.price_strategy, PRICE_STRATEGIES.join(", ")));
+        }
+        for (name, bps) in [("bps_spread", Some(bot.bps_spread)), ("bid_spread_bps", bot.bid_spread_bps), ("ask_spread_bps", bot.ask_spread_bps)] {
+            if let Some(bps) = bps {
+                if !at_least(bps, 0.0) || !above(10_000.0, bps) {
+                    problems.push(format!("bot.{}: must be at least 0 and below 10000", name));
+                }
+            }
+        }
+        if !above(bot.amount_bid, 0.0) || !above(bot.amount_ask, 0.0) {
+            problems.push("bot.amount_bid, bot.amount_ask: must be above 0".to_string());
+        }
+        if bot.loop_interval_ms == 0 {
+            problems.push("bot.loop_interval_ms: must be above 0".to_string());
+        }
+        if !at_least(bot.min_midpoint_change, 0.0) {
+            problems.push("bot.min_midpoint_change: must not be negative".to_string());
+        }
+        if bot.restart_base_delay_ms == 0 || bot.restart_base_delay_ms > bot.restart_max_delay_ms {
+            problems.push("bot.restart_base_delay_ms: must be above 0 and at most restart_max_delay_ms".to_string());
+        }
+        if bot.shutdown_timeout_ms == 0 {
+            problems.push("bot.shutdown_timeout_ms: must be above 0".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("invalid configuration:\n  {}", problems.join("\n  ")))
+        }
+    }
+}
+
+//NaN fails both, toml happily parses nan
+fn above(value: f64, min: f64) -> bool {
+    value > min
+}
+
+fn at_least(value: f64, min: f64) -> bool {
+    value >= min
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+pub(crate) fn config_path() -> PathBuf {
+    PathBuf::from(env("ARCANA_CONFIG").unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string()))
+}
+
+//reads ARCANA_CONFIG (or arcana.toml), applies env overrides and validates, called once at startup
+pub(crate) fn load() -> Result<ArcanaConfig, String> {
+    let path = config_path();
+    let mut config = ArcanaConfig::read_file(&path)?;
+    config.apply_env_overrides()?;
+    config.validate()?;
+    *CONFIG.get_or_init(|| RwLock::new(ArcanaConfig::default())).write().unwrap() = config.clone();
+    Ok(config)
+}
+
+pub(crate) fn config() -> ArcanaConfig {
+    CONFIG.get_or_init(|| RwLock::new(ArcanaConfig::default())).read().unwrap().clone()
+}
+
+//changes the config file, not the env overrides, so a value set through the environment still wins
+//after saving. Things that are read once at startup (server, rpc pool, keys) need a restart
+pub(crate) fn save<F: FnOnce(&mut ArcanaConfig)>(change: F) -> Result<ArcanaConfig, String> {
+    let path = config_path();
+    let config = save_file(&path, change)?;
+    println!("Saved configuration to {}", path.display());
+    *CONFIG.get_or_init(|| RwLock::new(ArcanaConfig::default())).write().unwrap() = config.clone();
+    Ok(config)
+}
+
+//the file is only written when both it and the effective config it leads to are valid
+fn save_file<F: FnOnce(&mut ArcanaConfig)>(path: &Path, change: F) -> Result<ArcanaConfig, String> {
+    let mut file_config = ArcanaConfig::read_file(path)?;
+    change(&mut file_config);
+    file_config.validate()?;
+
+    let mut config = file_config.clone();
+    config.apply_env_overrides()?;
+    config.validate()?;
+
+    file_config.write_file(path)?;
+    Ok(config)
+}
+
+pub(crate) fn rpc_urls() -> Vec<String> {
+    config().rpc.urls
+}
+
+fn ws_url() -> String {
+    if let Some(url) = config().rpc.ws_url {
+        return url;
+    }
+    let rpc_url = rpc_urls().remove(0);
+    if let Some(rest) = rpc_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = rpc_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        rpc_url
+    }
+}
+
+pub(crate) fn rpc_pool() -> RpcPool {
+    RpcPool::new(rpc_urls(), config().rpc.requests_per_second, RetryPolicy::new())
+}
+
+pub(crate) fn priority_fee_policy() -> PriorityFeePolicy {
+    config().compute_budget
+}
+
+pub(crate) fn keystore_path() -> String {
+    config().keys.keystore_path
+}
+
+//lets a headless deployment unlock the keystore at startup instead of through the settings page.
+//Only ever read from the environment so it never ends up in the config file
+pub(crate) fn keystore_passphrase() -> Option<String> {
+    std::env::var("ARCANA_KEYSTORE_PASSPHRASE").ok()
+}
+
+pub(crate) fn signer_endpoint() -> Option<String> {
+    config().keys.signer
+}
+
+pub(crate) fn database_path() -> String {
+    config().storage.database_path
+}
+
+pub(crate) fn bot_defaults() -> BotDefaults {
+    config().bot
+}
+
+//what every bot gets from the config besides its own params, read again for each bot so saved settings apply to
+//the next one started
+#[derive(Clone, Debug)]
+pub struct BotSettings {
+    pub ws_url: String,
+    pub loop_interval: Duration,
+    //how the bot manager backs off between restarts of a failing bot
+    pub restart_policy: RetryPolicy,
+}
+
+pub(crate) fn bot_settings() -> BotSettings {
+    let bot = config().bot;
+    BotSettings {
+        ws_url: ws_url(),
+        loop_interval: Duration::from_millis(bot.loop_interval_ms),
+        restart_policy: RetryPolicy {
+            max_retries: bot.max_restarts,
+            base_delay: Duration::from_millis(bot.restart_base_delay_ms),
+            max_delay: Duration::from_millis(bot.restart_max_delay_ms),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn parse(contents: &str) -> ArcanaConfig {
+        toml::from_str(contents).unwrap()
+    }
+
+    fn overrides(config: &mut ArcanaConfig, vars: &[(&str, &str)]) -> Result<(), String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        config.apply_overrides(|name| vars.get(name).cloned())
+    }
+
+    //removed again when the test is done, whatever happens in it
+    struct TempConfig(PathBuf);
+
+    impl TempConfig {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("arcana-config-{}.toml", uuid::Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempConfig {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn an_empty_file_is_the_default_config() {
+        let config = parse("");
+        assert!(config.validate().is_ok());
+        assert_eq!(config.server.address, "127.0.0.1");
+        assert_eq!(config.rpc.urls, vec![DEFAULT_RPC_URL]);
+    }
+
+    #[test]
+    fn unknown_keys_are_refused() {
+        assert!(toml::from_str::<ArcanaConfig>("[server]\nprot = 8080\n").is_err());
+        assert!(toml::from_str::<ArcanaConfig>("[bots]\n").is_err());
+    }
+
+    #[test]
+    fn validate_lists_every_problem() {
+        let config = parse(r#"
+            [server]
+            address = "localhost"
+            port = 0
+
+            [rpc]
+            urls = ["ftp://example.com"]
+            ws_url = "http://example.com"
+            requests_per_second = nan
+
+            [compute_budget]
+            percentile = 101.0
+
+            [bot]
+            price_strategy = "oracle"
+            bps_spread = 10000.0
+            amount_bid = 0.0
+            restart_base_delay_ms = 120000
+        "#);
+        let err = config.validate().unwrap_err();
+        for problem in [
+            "server.address: localhost is not an ip address",
+            "server.port: must not be 0",
+            "rpc.urls: ftp://example.com is not an http(s) url",
+            "rpc.ws_url: http://example.com is not a ws(s) url",
+            "rpc.requests_per_second: must be above 0",
+            "compute_budget.percentile: must be between 0 and 100",
+            "bot.price_strategy: oracle is not one of jupiter, orderbook",
+            "bot.bps_spread: must be at least 0 and below 10000",
+            "bot.amount_bid, bot.amount_ask: must be above 0",
+            "bot.restart_base_delay_ms: must be above 0 and at most restart_max_delay_ms",
+        ] {
+            assert!(err.contains(problem), "{} is missing from {}", problem, err);
+        }
+    }
+
+    #[test]
+    fn env_overrides_win_over_the_file() {
+        let mut config = parse(r#"
+            [server]
+            port = 9000
+
+            [rpc]
+            urls = ["https://file.example.com"]
+        "#);
+        overrides(&mut config, &[
+            ("ARCANA_ADDRESS", "0.0.0.0"),
+            ("ARCANA_PORT", "9100"),
+            ("ARCANA_RPC_URLS", "http://localhost:8899, https://env.example.com,"),
+            ("ARCANA_WS_URL", "ws://localhost:8900"),
+            ("ARCANA_RPC_REQUESTS_PER_SECOND", "2.5"),
+            ("ARCANA_SIGNER", "unix:/run/arcana-signer.sock"),
+            ("ARCANA_DATABASE_PATH", "/var/lib/arcana/arcana.db"),
+        ]).unwrap();
+        assert_eq!(config.server.address, "0.0.0.0");
+        assert_eq!(config.server.port, 9100);
+        assert_eq!(config.rpc.urls, vec!["http://localhost:8899", "https://env.example.com"]);
+        assert_eq!(config.rpc.ws_url.as_deref(), Some("ws://localhost:8900"));
+        assert_eq!(config.rpc.requests_per_second, 2.5);
+        assert_eq!(config.keys.signer.as_deref(), Some("unix:/run/arcana-signer.sock"));
+        assert_eq!(config.storage.database_path, "/var/lib/arcana/arcana.db");
+        //untouched by the environment
+        assert_eq!(config.keys.keystore_path, "arcana_keystore.json");
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn env_overrides_that_dont_parse_are_errors() {
+        let mut config = ArcanaConfig::default();
+        assert_eq!(overrides(&mut config, &[("ARCANA_PORT", "http")]).unwrap_err(), "ARCANA_PORT: http is not a port");
+        let err = overrides(&mut config, &[("ARCANA_RPC_REQUESTS_PER_SECOND", "fast")]).unwrap_err();
+        assert_eq!(err, "ARCANA_RPC_REQUESTS_PER_SECOND: fast is not a number");
+    }
+
+    #[test]
+    fn saved_changes_read_back_the_same() {
+        let path = TempConfig::new();
+        fs::write(&path.0, "[server]\nport = 9000\n\n[bot]\nbid_spread_bps = 25.0\n").unwrap();
+
+        save_file(&path.0, |config| {
+            config.bot.amount_ask = 2.5;
+            config.bot.price_strategy = "orderbook".to_string();
+        }).unwrap();
+        let saved = ArcanaConfig::read_file(&path.0).unwrap();
+        assert_eq!(saved.server.port, 9000);
+        assert_eq!(saved.bot.bid_spread_bps, Some(25.0));
+        assert_eq!(saved.bot.amount_ask, 2.5);
+        assert_eq!(saved.bot.price_strategy, "orderbook");
+        //everything else is written out with its default
+        assert_eq!(saved.rpc.urls, vec![DEFAULT_RPC_URL]);
+    }
+
+    #[test]
+    fn an_invalid_change_leaves_the_file_alone() {
+        let path = TempConfig::new();
+        fs::write(&path.0, "[bot]\namount_ask = 1.0\n").unwrap();
+
+        let err = save_file(&path.0, |config| config.bot.amount_ask = -1.0).unwrap_err();
+        assert!(err.contains("bot.amount_bid, bot.amount_ask: must be above 0"), "{}", err);
+        assert_eq!(fs::read_to_string(&path.0).unwrap(), "[bot]\namount_ask = 1.0\n");
+    }
+}
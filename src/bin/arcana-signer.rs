@@ -0,0 +1,337 @@
+//reference external signer for arcana: holds a keypair outside the web process and signs
+//only messages whose instructions target allowed programs. Of the system program only the
+//instructions that set up accounts and nonces are signed, never a transfer.
+//
+//  arcana-signer --keypair <keypair.json> (--socket <path> | --http <address:port>) [--allow <program_id>]...
+
+use std::collections::HashSet;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::fs::Permissions;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rocket::config::Config;
+use rocket::serde::json::Json;
+use rocket::State;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, read_keypair_file, Signer};
+use solana_sdk::system_instruction::SystemInstruction;
+use solana_sdk::system_program;
+
+#[path = "../keys/signer_protocol.rs"]
+#[allow(dead_code)]
+mod signer_protocol;
+
+use signer_protocol::{read_line, SignerRequest, SignerResponse, write_line};
+
+//everything the bots send: orders, their lookup tables, and the nonce accounts the emergency cancels are signed against.
+//The token program isn't here, serum calls into it itself and the bots never do
+const DEFAULT_ALLOWED_PROGRAMS: [&str; 5] = [
+    "srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX", //serum dex v3
+    "ComputeBudget111111111111111111111111111111",
+    "Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo",
+    "11111111111111111111111111111111", //system, see system_instruction_allowed
+    "AddressLookupTab1e1111111111111111111111111",
+];
+
+//the bots only create accounts (open orders, nonces) and initialize and advance nonces, anything that moves lamports
+//or hands over an account is refused whatever the allow list says
+fn system_instruction_allowed(data: &[u8]) -> Result<(), String> {
+    let instruction: SystemInstruction = bincode::deserialize(data)
+        .map_err(|e| format!("system instruction could not be decoded: {}", e))?;
+    match instruction {
+        SystemInstruction::CreateAccount { .. }
+        | SystemInstruction::InitializeNonceAccount(_)
+        | SystemInstruction::AdvanceNonceAccount => Ok(()),
+        other => Err(format!("system instruction {:?} is not allowed by the signing policy", other)),
+    }
+}
+
+fn decode_message(message: &str) -> Result<Vec<u8>, String> {
+    BASE64.decode(message).map_err(|e| format!("message is not base64: {}", e))
+}
+
+//the signer refuses any message that invokes a program outside its allow list, or a system instruction the bots
+//don't send
+struct SigningPolicy {
+    allowed_program_ids: HashSet<Pubkey>,
+}
+
+impl SigningPolicy {
+    fn new(allowed_program_ids: impl IntoIterator<Item = Pubkey>) -> Self {
+        Self {
+            allowed_program_ids: allowed_program_ids.into_iter().collect(),
+        }
+    }
+
+    fn check(&self, message_bytes: &[u8]) -> Result<(), String> {
+        let message: VersionedMessage = bincode::deserialize(message_bytes)
+            .map_err(|e| format!("message could not be decoded: {}", e))?;
+
+        //program ids can't be loaded from lookup tables, so the static keys are enough here
+        let account_keys = message.static_account_keys();
+        for instruction in message.instructions() {
+            let program_id = account_keys.get(instruction.program_id_index as usize)
+                .ok_or_else(|| format!("program index {} out of range", instruction.program_id_index))?;
+            if !self.allowed_program_ids.contains(program_id) {
+                return Err(format!("program {} is not allowed by the signing policy", program_id));
+            }
+            if system_program::check_id(program_id) {
+                system_instruction_allowed(&instruction.data)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_allowed_programs() -> Vec<Pubkey> {
+    DEFAULT_ALLOWED_PROGRAMS.iter().map(|id| Pubkey::from_str(id).unwrap()).collect()
+}
+
+struct ReferenceSigner {
+    keypair: Keypair,
+    policy: SigningPolicy,
+}
+
+impl ReferenceSigner {
+    fn handle(&self, request: SignerRequest) -> SignerResponse {
+        match request {
+            SignerRequest::GetPubkey => SignerResponse::Pubkey {
+                pubkey: self.keypair.pubkey().to_string(),
+            },
+            SignerRequest::SignMessage { message } => {
+                let message = match decode_message(&message) {
+                    Ok(message) => message,
+                    Err(reason) => return SignerResponse::Rejected { reason },
+                };
+                if let Err(reason) = self.policy.check(&message) {
+                    eprintln!("Rejected signing request: {}", reason);
+                    return SignerResponse::Rejected { reason };
+                }
+                SignerResponse::Signature {
+                    signature: self.keypair.sign_message(&message).to_string(),
+                }
+            }
+        }
+    }
+}
+
+enum Listen {
+    Socket(PathBuf),
+    Http(SocketAddr),
+}
+
+struct Args {
+    keypair_path: String,
+    listen: Listen,
+    allowed_programs: Vec<Pubkey>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut keypair_path = None;
+    let mut listen = None;
+    let mut allowed_programs = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--keypair" => keypair_path = Some(value()?),
+            "--socket" => listen = Some(Listen::Socket(PathBuf::from(value()?))),
+            "--http" => {
+                let address = value()?;
+                listen = Some(Listen::Http(address.parse().map_err(|_| format!("bad address {}", address))?));
+            }
+            "--allow" => {
+                let program_id = value()?;
+                allowed_programs.push(Pubkey::from_str(&program_id).map_err(|_| format!("bad program id {}", program_id))?);
+            }
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+
+    if allowed_programs.is_empty() {
+        allowed_programs = default_allowed_programs();
+    }
+
+    Ok(Args {
+        keypair_path: keypair_path.ok_or("--keypair is required")?,
+        listen: listen.ok_or("one of --socket or --http is required")?,
+        allowed_programs,
+    })
+}
+
+//only the user the signer runs as may connect, the socket would otherwise follow the umask and let any local user
+//ask for signatures
+fn bind_private(path: &PathBuf) -> std::io::Result<UnixListener> {
+    //a stale socket file from a previous run would make bind fail
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+fn serve_unix(signer: Arc<ReferenceSigner>, path: PathBuf) -> std::io::Result<()> {
+    let listener = bind_private(&path)?;
+    println!("arcana-signer listening on unix:{}", path.display());
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let signer = signer.clone();
+                std::thread::spawn(move || {
+                    if let Err(err) = handle_connection(&signer, stream) {
+                        eprintln!("Signer connection error: {}", err);
+                    }
+                });
+            }
+            Err(err) => eprintln!("Signer accept error: {}", err),
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(signer: &ReferenceSigner, stream: UnixStream) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    while let Some(request) = read_line::<_, SignerRequest>(&mut reader)? {
+        write_line(&mut writer, &signer.handle(request))?;
+    }
+    Ok(())
+}
+
+#[rocket::post("/sign", data = "<request>")]
+fn sign(signer: &State<Arc<ReferenceSigner>>, request: Json<SignerRequest>) -> Json<SignerResponse> {
+    Json(signer.handle(request.into_inner()))
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!("usage: arcana-signer --keypair <keypair.json> (--socket <path> | --http <address:port>) [--allow <program_id>]...");
+            std::process::exit(2);
+        }
+    };
+
+    let keypair = read_keypair_file(&args.keypair_path)
+        .unwrap_or_else(|e| panic!("Error reading keypair {}: {}", args.keypair_path, e));
+    println!("Signing for {}, allowed programs: {:?}", keypair.pubkey(), args.allowed_programs);
+
+    let signer = Arc::new(ReferenceSigner {
+        keypair,
+        policy: SigningPolicy::new(args.allowed_programs),
+    });
+
+    match args.listen {
+        Listen::Socket(path) => serve_unix(signer, path).expect("Error serving unix socket"),
+        Listen::Http(address) => {
+            let mut config = Config::release_default();
+            config.address = address.ip();
+            config.port = address.port();
+            let result = rocket::execute(
+                rocket::custom(config)
+                    .manage(signer)
+                    .mount("/", rocket::routes![sign])
+                    .launch()
+            );
+            if let Err(err) = result {
+                eprintln!("arcana-signer http server failed: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+    use solana_sdk::message::Message;
+    use solana_sdk::system_instruction;
+
+    fn message_bytes(instructions: &[Instruction], payer: &Pubkey) -> Vec<u8> {
+        let message = VersionedMessage::Legacy(Message::new_with_blockhash(instructions, Some(payer), &Hash::new_unique()));
+        bincode::serialize(&message).unwrap()
+    }
+
+    #[test]
+    fn default_policy_allows_nonce_and_lookup_table_setup() {
+        let policy = SigningPolicy::new(default_allowed_programs());
+        let authority = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+
+        let create_nonce = system_instruction::create_nonce_account(&authority, &nonce_account, &authority, 1_500_000);
+        assert_eq!(policy.check(&message_bytes(&create_nonce, &authority)), Ok(()));
+        let advance = system_instruction::advance_nonce_account(&nonce_account, &authority);
+        assert_eq!(policy.check(&message_bytes(&[advance], &authority)), Ok(()));
+
+        let (create_table, table) = solana_address_lookup_table_program::instruction::create_lookup_table(authority, authority, 1);
+        let extend = solana_address_lookup_table_program::instruction::extend_lookup_table(table, authority, Some(authority), vec![Pubkey::new_unique()]);
+        assert_eq!(policy.check(&message_bytes(&[create_table, extend], &authority)), Ok(()));
+    }
+
+    #[test]
+    fn transfers_are_refused() {
+        let policy = SigningPolicy::new(default_allowed_programs());
+        let payer = Pubkey::new_unique();
+
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1_000_000_000);
+        let err = policy.check(&message_bytes(&[transfer], &payer)).unwrap_err();
+        assert!(err.contains("Transfer"), "{}", err);
+
+        //a transfer tucked in behind an allowed nonce advance is refused all the same
+        let nonce_account = Pubkey::new_unique();
+        let advance = system_instruction::advance_nonce_account(&nonce_account, &payer);
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+        assert!(policy.check(&message_bytes(&[advance, transfer], &payer)).is_err());
+
+        let assign = system_instruction::assign(&payer, &Pubkey::new_unique());
+        assert!(policy.check(&message_bytes(&[assign], &payer)).is_err());
+        let authorize = system_instruction::authorize_nonce_account(&nonce_account, &payer, &Pubkey::new_unique());
+        assert!(policy.check(&message_bytes(&[authorize], &payer)).is_err());
+    }
+
+    #[test]
+    fn token_program_is_refused() {
+        let policy = SigningPolicy::new(default_allowed_programs());
+        let owner = Pubkey::new_unique();
+        let token_program = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
+        //spl token Transfer, tag 3 then the amount
+        let mut data = vec![3];
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        let transfer = Instruction::new_with_bytes(token_program, &data, vec![
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new_readonly(owner, true),
+        ]);
+        assert!(policy.check(&message_bytes(&[transfer], &owner)).is_err());
+    }
+
+    #[test]
+    fn socket_is_only_open_to_its_owner() {
+        let path = std::env::temp_dir().join(format!("arcana-signer-test-{}.sock", std::process::id()));
+        let _listener = bind_private(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn unknown_program_is_refused() {
+        let policy = SigningPolicy::new(default_allowed_programs());
+        let payer = Pubkey::new_unique();
+        let unknown = Instruction::new_with_bytes(Pubkey::new_unique(), &[1, 2, 3], vec![]);
+        assert!(policy.check(&message_bytes(&[unknown], &payer)).is_err());
+    }
+}
@@ -0,0 +1,450 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use clap::{Args, Parser, Subcommand};
+use serde_derive::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use crate::arcana_web_config;
+use crate::keys::keystore::Keystore;
+use crate::keys::remote_signer::RemoteSigner;
+use crate::rpc::chain_client::ChainClient;
+use crate::serum::market::MarketWrapper;
+use crate::serum::market_builder::{list_markets, MarketBuilder};
+use crate::serum::open_orders::{cancel_all, create_open_orders, find_open_orders, settle_instruction};
+use crate::serum::serum_utils::pub_key;
+use crate::strategies::bot_manager::KillSwitchReport;
+use crate::strategies::open_book_bot::BotSummary;
+use crate::strategies::strategy_registry::StrategyKindInfo;
+use crate::strategies::quote_params::QuoteParamsUpdate;
+use crate::transactions::direct_send::send_and_confirm;
+
+#[derive(Parser)]
+#[command(about = "Arcana market maker. Runs the web ui by default, the other subcommands are for scripting and debugging")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Run the web ui and the bot manager")]
+    Serve,
+    #[command(subcommand, about = "Find and inspect openbook markets")]
+    Markets(MarketsCommand),
+    #[command(about = "Print a market's L2 order book")]
+    Orderbook {
+        market: Pubkey,
+        #[arg(long, default_value_t = 10, help = "Price levels per side")]
+        depth: usize,
+    },
+    #[command(subcommand, about = "Find or create open orders accounts for the trading key")]
+    Oo(OpenOrdersCommand),
+    #[command(about = "Manage the bots of a running server")]
+    Bot {
+        #[arg(long, help = "Server url, defaults to the [server] section of the config")]
+        server: Option<String>,
+        #[command(subcommand)]
+        command: BotCommand,
+    },
+    #[command(about = "Settle free funds from an open orders account back to the wallets")]
+    Settle {
+        market: Pubkey,
+        #[arg(long)]
+        open_orders: Pubkey,
+        #[arg(long)]
+        base_wallet: Pubkey,
+        #[arg(long)]
+        quote_wallet: Pubkey,
+    },
+    #[command(about = "Cancel every resting order of an open orders account")]
+    CancelAll {
+        market: Pubkey,
+        #[arg(long)]
+        open_orders: Pubkey,
+    },
+    #[command(about = "Stop every bot of a running server and cancel all of their orders")]
+    Kill {
+        #[arg(long, help = "Server url, defaults to the [server] section of the config")]
+        server: Option<String>,
+    },
+}
+
+//the quoting options add and params share, anything left out is left as it is
+#[derive(Args)]
+pub struct QuoteArgs {
+    #[arg(long, help = "Spread on both sides of the reference price, in bps")]
+    bps_spread: Option<f64>,
+    #[arg(long, help = "Bid spread in bps, overrides --bps-spread for the bid")]
+    bid_spread_bps: Option<f64>,
+    #[arg(long, help = "Ask spread in bps, overrides --bps-spread for the ask")]
+    ask_spread_bps: Option<f64>,
+    #[arg(long, help = "Bid size in quote")]
+    amount_bid: Option<f64>,
+    #[arg(long, help = "Ask size in base")]
+    amount_ask: Option<f64>,
+    #[arg(long, help = "Fraction the price has to move by before a quote is replaced")]
+    min_midpoint_change: Option<f64>,
+}
+
+impl QuoteArgs {
+    fn update(self) -> QuoteParamsUpdate {
+        QuoteParamsUpdate {
+            bps_spread: self.bps_spread,
+            bid_spread_bps: self.bid_spread_bps,
+            ask_spread_bps: self.ask_spread_bps,
+            amount_bid: self.amount_bid,
+            amount_ask: self.amount_ask,
+            min_midpoint_change: self.min_midpoint_change,
+        }
+    }
+}
+
+//the ladder options of bot add, anything left out comes from the config file or the server's defaults
+#[derive(Args, Serialize)]
+pub struct LadderArgs {
+    #[arg(long, help = "Orders per side, 1 quotes a single bid and ask")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    levels: Option<usize>,
+    #[arg(long, help = "Distance between neighbouring levels, in bps")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    level_spacing_bps: Option<f64>,
+    #[arg(long, help = "How the size grows away from the first level: flat, linear or geometric")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_progression: Option<String>,
+    #[arg(long, help = "Linear: each level adds this many times the first level's size, geometric: each level is this many times the last")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_factor: Option<f64>,
+}
+
+#[derive(Subcommand)]
+pub enum MarketsCommand {
+    #[command(about = "List every market, optionally only the ones quoted in one mint")]
+    List {
+        #[arg(long)]
+        quote: Option<Pubkey>,
+    },
+    #[command(about = "Show a market's accounts, lot sizes and top of book")]
+    Show {
+        id: Pubkey,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum OpenOrdersCommand {
+    #[command(about = "List open orders accounts on a market, for the trading key unless --owner is given")]
+    Find {
+        market: Pubkey,
+        #[arg(long)]
+        owner: Option<Pubkey>,
+    },
+    #[command(about = "Create an open orders account on a market for the trading key")]
+    Create {
+        market: Pubkey,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BotCommand {
+    #[command(about = "Start a bot, anything left out comes from the [bot] section of the server's config")]
+    Add {
+        #[arg(long, help = "Strategy kind, see bot strategies. Defaults to the config file's strategy field, then openbook_spl_usdc")]
+        strategy: Option<String>,
+        #[arg(long, help = "Strategy config as a .toml or .json file, the other options override it")]
+        config: Option<PathBuf>,
+        #[arg(long)]
+        market: Option<Pubkey>,
+        #[arg(long)]
+        price_strategy: Option<String>,
+        //boxed, they would make add far bigger than every other command
+        #[command(flatten)]
+        quote: Box<QuoteArgs>,
+        #[command(flatten)]
+        ladder: Box<LadderArgs>,
+        #[arg(long)]
+        open_orders: Option<Pubkey>,
+        #[arg(long)]
+        base_wallet: Option<Pubkey>,
+        #[arg(long)]
+        quote_wallet: Option<Pubkey>,
+    },
+    #[command(about = "List the server's bots")]
+    List,
+    #[command(about = "List the strategy kinds the server can run, with their default config")]
+    Strategies,
+    #[command(about = "Start a stopped or failed bot again")]
+    Start {
+        uuid: String,
+    },
+    #[command(about = "Stop placing quotes, the bot keeps following the market")]
+    Pause {
+        uuid: String,
+    },
+    #[command(about = "Resume quoting on a paused bot")]
+    Resume {
+        uuid: String,
+    },
+    #[command(about = "Stop a bot, it stays listed and can be started again")]
+    Stop {
+        uuid: String,
+    },
+    #[command(about = "Change what a bot quotes with, a running bot picks it up on its next tick")]
+    Params {
+        uuid: String,
+        #[command(flatten)]
+        quote: QuoteArgs,
+    },
+    #[command(about = "Stop a bot if needed and forget it")]
+    Delete {
+        uuid: String,
+    },
+}
+
+pub async fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    let chain_client: Arc<dyn ChainClient> = Arc::new(arcana_web_config::rpc_pool());
+    match command {
+        //handled by main, it needs rocket's runtime rather than this one
+        Command::Serve => unreachable!(),
+        Command::Markets(MarketsCommand::List { quote }) => {
+            let markets = list_markets(chain_client.as_ref(), quote).await?;
+            println!("{:<44}  {:<44}  {:<44}", "market", "base mint", "quote mint");
+            for market in &markets {
+                println!("{:<44}  {:<44}  {:<44}", market.market_id, market.base_mint, market.quote_mint);
+            }
+            println!("{} markets", markets.len());
+        }
+        Command::Markets(MarketsCommand::Show { id }) => {
+            let market = MarketBuilder::new(chain_client, id).build().await?;
+            print_market(&id, &market);
+        }
+        Command::Orderbook { market, depth } => {
+            let market = MarketBuilder::new(chain_client, market).build().await?;
+            print_order_book(&market, depth);
+        }
+        Command::Oo(OpenOrdersCommand::Find { market, owner }) => {
+            let owner = match owner {
+                Some(owner) => owner,
+                None => trading_signer().await?.pubkey(),
+            };
+            let open_orders = find_open_orders(chain_client.as_ref(), &market, &owner).await?;
+            if open_orders.is_empty() {
+                println!("{} has no open orders account on {}", owner, market);
+            }
+            for (pubkey, snapshot) in open_orders {
+                println!("{}  base free/total {}/{}  quote free/total {}/{}  {} orders",
+                         pubkey, snapshot.base_free, snapshot.base_total, snapshot.quote_free, snapshot.quote_total, snapshot.orders.len());
+            }
+        }
+        Command::Oo(OpenOrdersCommand::Create { market }) => {
+            let open_orders = create_open_orders(chain_client.as_ref(), &market, trading_signer().await?).await?;
+            println!("Created open orders account {}", open_orders);
+        }
+        Command::Bot { server, command } => run_bot_command(server.unwrap_or_else(default_server_url), command).await?,
+        Command::Settle { market, open_orders, base_wallet, quote_wallet } => {
+            let signer = trading_signer().await?;
+            let market = MarketBuilder::new(chain_client.clone(), market).build().await?;
+            let instruction = settle_instruction(&market, &open_orders, &signer.pubkey(), &base_wallet, &quote_wallet)?;
+            let signature = send_and_confirm(chain_client.as_ref(), &[instruction], vec![signer]).await?;
+            println!("Settled {}: {}", open_orders, signature);
+        }
+        Command::CancelAll { market, open_orders } => {
+            let signer = trading_signer().await?;
+            let market = MarketBuilder::new(chain_client.clone(), market).build().await?;
+            let market_state = *market.market.lock().unwrap();
+            let cancelled = cancel_all(chain_client.as_ref(), &market_state, &open_orders, signer).await?;
+            println!("Cancelled {} orders", cancelled);
+        }
+        Command::Kill { server } => {
+            let server = server.unwrap_or_else(default_server_url);
+            let url = format!("{}/api/kill", server.trim_end_matches('/'));
+            let response = check_response(reqwest::Client::new().post(url).send().await?).await?;
+            print_kill_report(&response.json::<KillSwitchReport>().await?);
+        }
+    }
+    Ok(())
+}
+
+async fn run_bot_command(server: String, command: BotCommand) -> Result<(), Box<dyn Error>> {
+    let client = reqwest::Client::new();
+    let server = server.trim_end_matches('/');
+    match command {
+        BotCommand::Add { strategy, config, market, price_strategy, quote, ladder, open_orders, base_wallet, quote_wallet } => {
+            //anything still missing is filled in by the server from its own config
+            let mut fields = match config {
+                Some(path) => read_strategy_config(&path)?,
+                None => serde_json::Map::new(),
+            };
+            let options = [
+                ("market_id", market.map(|market| market.to_string())),
+                ("price_strategy", price_strategy),
+                ("ooa", open_orders.map(|open_orders| open_orders.to_string())),
+                ("base_wallet", base_wallet.map(|wallet| wallet.to_string())),
+                ("quote_wallet", quote_wallet.map(|wallet| wallet.to_string())),
+            ];
+            for (name, value) in options {
+                if let Some(value) = value {
+                    fields.insert(name.to_string(), serde_json::Value::String(value));
+                }
+            }
+            if let serde_json::Value::Object(quote) = serde_json::to_value(quote.update())? {
+                fields.extend(quote);
+            }
+            if let serde_json::Value::Object(ladder) = serde_json::to_value(ladder)? {
+                fields.extend(ladder);
+            }
+            let file_strategy = fields.remove("strategy").and_then(|strategy| strategy.as_str().map(str::to_string));
+            let mut body = serde_json::Value::Object(fields);
+            if let Some(strategy) = strategy.or(file_strategy) {
+                body["strategy"] = serde_json::Value::String(strategy);
+            }
+            let response = check_response(client.post(format!("{}/api/bots", server)).json(&body).send().await?).await?;
+            print_bots(&[response.json::<BotSummary>().await?]);
+        }
+        BotCommand::List => {
+            let response = check_response(client.get(format!("{}/api/bots", server)).send().await?).await?;
+            print_bots(&response.json::<Vec<BotSummary>>().await?);
+        }
+        BotCommand::Strategies => {
+            let response = check_response(client.get(format!("{}/api/strategies", server)).send().await?).await?;
+            for kind in response.json::<Vec<StrategyKindInfo>>().await? {
+                println!("{}  {}", kind.name, kind.description);
+                println!("    {}", kind.default_config);
+            }
+        }
+        BotCommand::Start { uuid } => bot_action(&client, server, &uuid, "start").await?,
+        BotCommand::Pause { uuid } => bot_action(&client, server, &uuid, "pause").await?,
+        BotCommand::Resume { uuid } => bot_action(&client, server, &uuid, "resume").await?,
+        BotCommand::Stop { uuid } => bot_action(&client, server, &uuid, "stop").await?,
+        BotCommand::Params { uuid, quote } => {
+            let url = format!("{}/api/bots/{}/params", server, uuid);
+            let response = check_response(client.patch(url).json(&quote.update()).send().await?).await?;
+            print_bots(&[response.json::<BotSummary>().await?]);
+        }
+        BotCommand::Delete { uuid } => {
+            let response = check_response(client.delete(format!("{}/api/bots/{}", server, uuid)).send().await?).await?;
+            print_bots(&[response.json::<BotSummary>().await?]);
+        }
+    }
+    Ok(())
+}
+
+async fn bot_action(client: &reqwest::Client, server: &str, uuid: &str, action: &str) -> Result<(), Box<dyn Error>> {
+    let response = check_response(client.post(format!("{}/api/bots/{}/{}", server, uuid, action)).send().await?).await?;
+    print_bots(&[response.json::<BotSummary>().await?]);
+    Ok(())
+}
+
+//toml for files written by hand, json for anything else
+fn read_strategy_config(path: &Path) -> Result<serde_json::Map<String, serde_json::Value>, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path.display(), e))?;
+    let config: serde_json::Value = if path.extension().is_some_and(|extension| extension == "toml") {
+        toml::from_str(&text)?
+    } else {
+        serde_json::from_str(&text)?
+    };
+    match config {
+        serde_json::Value::Object(fields) => Ok(fields),
+        _ => Err(format!("{} isn't a table of strategy options", path.display()).into()),
+    }
+}
+
+async fn check_response(response: reqwest::Response) -> Result<reqwest::Response, Box<dyn Error>> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    Err(format!("server returned {}: {}", status, response.text().await.unwrap_or_default()).into())
+}
+
+fn default_server_url() -> String {
+    let server = arcana_web_config::config().server;
+    //the server listens everywhere but that isn't an address to connect to
+    let address = if server.address == "0.0.0.0" { "127.0.0.1".to_string() } else { server.address };
+    format!("http://{}:{}", address, server.port)
+}
+
+//same rule as the server, the remote signer wins over the keystore. The keystore is unlocked with
+//ARCANA_KEYSTORE_PASSPHRASE since there is no web form to do it from
+async fn trading_signer() -> Result<Arc<dyn Signer + Send + Sync>, Box<dyn Error>> {
+    //both the remote signer and keystore do blocking io
+    let signer = tokio::task::spawn_blocking(|| -> Result<Arc<dyn Signer + Send + Sync>, String> {
+        if let Some(endpoint) = arcana_web_config::signer_endpoint() {
+            return Ok(Arc::new(RemoteSigner::connect(&endpoint).map_err(|e| e.to_string())?));
+        }
+        let passphrase = arcana_web_config::keystore_passphrase()
+            .ok_or("no remote signer configured and ARCANA_KEYSTORE_PASSPHRASE isn't set")?;
+        let mut keystore = Keystore::new(arcana_web_config::keystore_path());
+        keystore.unlock(&passphrase).map_err(|e| e.to_string())?;
+        let pubkey = keystore.default_pubkey().ok_or("keystore has no trading key")?;
+        Ok(keystore.get_keypair(&pubkey).map_err(|e| e.to_string())?)
+    }).await??;
+    Ok(signer)
+}
+
+fn print_market(id: &Pubkey, market: &MarketWrapper) {
+    let market_state = *market.market.lock().unwrap();
+    println!("market       {}", id);
+    println!("base mint    {} ({} decimals)", pub_key(market_state.coin_mint), market.base_decimals);
+    println!("quote mint   {} ({} decimals)", pub_key(market_state.pc_mint), market.quote_decimals);
+    println!("base lot     {}", { market_state.coin_lot_size });
+    println!("quote lot    {}", { market_state.pc_lot_size });
+    println!("bids         {}", pub_key(market_state.bids));
+    println!("asks         {}", pub_key(market_state.asks));
+    println!("event queue  {}", pub_key(market_state.event_q));
+    println!("base vault   {}", pub_key(market_state.coin_vault));
+    println!("quote vault  {}", pub_key(market_state.pc_vault));
+    println!("best bid     {}", market.bid_order_book.get_best_bid_price());
+    println!("best ask     {}", market.ask_order_book.get_best_ask_price());
+}
+
+//asks on top in descending price so the spread sits in the middle
+fn print_order_book(market: &MarketWrapper, depth: usize) {
+    let asks = market.ask_order_book.levels();
+    let bids = market.bid_order_book.levels();
+    println!("{:>16}  {:>16}", "price", "size");
+    for (price, quantity) in asks.iter().take(depth).rev() {
+        println!("{:>16.6}  {:>16.4}  ask", price, quantity);
+    }
+    match (bids.first(), asks.first()) {
+        (Some((bid, _)), Some((ask, _))) => println!("{:-^36}", format!(" spread {:.6} ", ask - bid)),
+        _ => println!("{:-^36}", " one sided "),
+    }
+    for (price, quantity) in bids.iter().take(depth) {
+        println!("{:>16.6}  {:>16.4}  bid", price, quantity);
+    }
+}
+
+fn print_bots(bots: &[BotSummary]) {
+    for bot in bots {
+        println!("{}  {}  {}", bot.uuid, bot.state.as_str(), bot.strategy);
+        println!("    {}", serde_json::Value::Object(bot.params.config.clone()));
+        println!(
+            "    {} SOL in fees over {} transactions, {} SOL of it priority",
+            bot.fees.sol_spent, bot.fees.transactions, bot.fees.priority_sol_spent
+        );
+        if let Some(last_error) = &bot.last_error {
+            println!("    {} restarts, last error: {}", bot.restarts, last_error);
+        }
+    }
+}
+
+fn print_kill_report(report: &KillSwitchReport) {
+    println!("stopped {} bots", report.bots_stopped.len());
+    for cancel in &report.presigned {
+        match (&cancel.signature, &cancel.error) {
+            (Some(signature), _) => println!("pre-signed cancel for {} sent: {}", cancel.open_orders, signature),
+            (None, Some(err)) => println!("pre-signed cancel for {} failed: {}", cancel.open_orders, err),
+            (None, None) => {}
+        }
+    }
+    for bot in &report.bots {
+        match (bot.cancelled, &bot.error) {
+            (Some(cancelled), _) => println!("bot {} cancelled {} orders", bot.uuid, cancelled),
+            (None, Some(err)) => println!("bot {} could not cancel: {}", bot.uuid, err),
+            (None, None) => {}
+        }
+    }
+    if report.timed_out {
+        println!("timed out before every bot had cancelled, check the server log");
+    }
+}
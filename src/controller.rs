@@ -1,22 +1,379 @@
 use rocket_dyn_templates::context;
 use rocket_dyn_templates::Template;
 use crate::AppState;
+use crate::arcana_web_config;
+use crate::keys::keystore::{KeyInfo, Keystore, parse_private_key};
+use rocket::form::{Form, FromForm};
 use rocket::get;
 use rocket::post;
+use rocket::response::Redirect;
+use rocket::Shutdown;
+use rocket::delete;
+use rocket::patch;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::status::{BadRequest, Custom};
+use rocket::serde::json::Json;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use uuid::Uuid;
+use std::time::Duration;
+use crate::strategies::bot_manager::{BotManager, KillSwitchReport};
+use crate::strategies::open_book_bot::BotSummary;
+use crate::strategies::strategy_registry::{BotConfig, StrategyKindInfo};
+use crate::strategies::quote_params::QuoteParamsUpdate;
 
 #[get("/")]
-pub fn index(state: &rocket::State<AppState>) -> Template {
+pub fn index() -> Template {
     let context = context! { title: "Arcana-rs" };
     Template::render("index", &context)
 }
 
 #[get("/settings")]
 pub fn settings(state: &rocket::State<AppState>) -> Template {
+    let keystore = state.keystore.lock().unwrap();
     let context = context! {
         title: "Settings",
-        rpc_endpoint: state.rpc_client.url(),
-        trading_account_pubkey: state.bot_manager.trading_account.as_ref().unwrap().owner.to_string(),
+        rpc_endpoint: state.rpc_pool.primary_url(),
+        rpc_endpoints: state.rpc_pool.health(),
+        rpc_methods: state.rpc_pool.method_stats(),
+        trading_account_pubkey: match &state.remote_signer {
+            Some(remote_signer) => remote_signer.pubkey().to_string(),
+            None => keystore.default_pubkey().map(|pubkey| pubkey.to_string()).unwrap_or_default(),
+        },
+        remote_signer: state.remote_signer.as_ref().map(|remote_signer| remote_signer.endpoint().to_string()),
+        keystore_unlocked: keystore.is_unlocked(),
+        keys: keystore.list_keys().unwrap_or_default(),
+        config: arcana_web_config::config(),
+        config_path: arcana_web_config::config_path().display().to_string(),
     };
     Template::render("settings", &context)
 }
-//todo need to rewrite .jsp into tera
\ No newline at end of file
+
+#[derive(FromForm)]
+pub struct SettingsUpdate {
+    //comma separated
+    rpc_urls: String,
+    ws_url: Option<String>,
+    requests_per_second: f64,
+    price_strategy: String,
+    bps_spread: f64,
+    amount_bid: f64,
+    amount_ask: f64,
+    loop_interval_ms: u64,
+}
+
+#[post("/settings/save", data = "<update>")]
+pub fn settings_save(_origin: SameOrigin, update: Form<SettingsUpdate>) -> Result<Redirect, BadRequest<String>> {
+    let update = update.into_inner();
+    arcana_web_config::save(|config| {
+        config.rpc.urls = update.rpc_urls.split(',')
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect();
+        config.rpc.ws_url = update.ws_url.filter(|url| !url.trim().is_empty());
+        config.rpc.requests_per_second = update.requests_per_second;
+        config.bot.price_strategy = update.price_strategy;
+        config.bot.bps_spread = update.bps_spread;
+        config.bot.amount_bid = update.amount_bid;
+        config.bot.amount_ask = update.amount_ask;
+        config.bot.loop_interval_ms = update.loop_interval_ms;
+    }).map_err(|e| BadRequest(Some(e)))?;
+    Ok(Redirect::to("/settings"))
+}
+
+#[derive(FromForm)]
+pub struct PrivateKeyUpload<'r> {
+    file: &'r [u8],
+    label: Option<String>,
+}
+
+#[post("/privateKeyUpload", data = "<upload>")]
+pub fn private_key_upload(_origin: SameOrigin, state: &rocket::State<AppState>, upload: Form<PrivateKeyUpload<'_>>) -> Result<Redirect, BadRequest<String>> {
+    let keypair = parse_private_key(upload.file).map_err(|e| BadRequest(Some(e.to_string())))?;
+    let label = upload.label.clone().unwrap_or_else(|| "trading".to_string());
+
+    let mut keystore = state.keystore.lock().unwrap();
+    let pubkey = keystore.add_key(&label, keypair).map_err(|e| BadRequest(Some(e.to_string())))?;
+    println!("Added key {} ({}) to keystore", pubkey, label);
+
+    Ok(Redirect::to("/settings"))
+}
+
+#[derive(FromForm)]
+pub struct KeystoreUnlock {
+    passphrase: String,
+}
+
+#[post("/keystore/unlock", data = "<unlock>")]
+pub async fn keystore_unlock(_origin: SameOrigin, state: &rocket::State<AppState>, unlock: Form<KeystoreUnlock>) -> Result<Redirect, BadRequest<String>> {
+    //the key derivation takes a good part of a second, it runs on a blocking thread against its own copy of the
+    //keystore and only takes the lock to swap the unlocked one in
+    let path = state.keystore.lock().unwrap().path().to_path_buf();
+    let passphrase = unlock.into_inner().passphrase;
+    let unlocked = tokio::task::spawn_blocking(move || {
+        let mut keystore = Keystore::new(path);
+        keystore.unlock_or_create(&passphrase).map(|()| keystore)
+    }).await
+        .map_err(|e| BadRequest(Some(e.to_string())))?
+        .map_err(|e| BadRequest(Some(e.to_string())))?;
+    *state.keystore.lock().unwrap() = unlocked;
+    //bots that had no key to sign with at startup come back now. Restoring blocks on the bots' own runtime
+    if let Ok(signer) = state.trading_signer() {
+        tokio::task::block_in_place(|| {
+            state.bot_manager.lock().unwrap().restore_bots(state.rpc_pool.clone(), &state.jupiter_pricing_source, Some(signer));
+        });
+    }
+    Ok(Redirect::to("/settings"))
+}
+
+#[post("/keystore/lock")]
+pub fn keystore_lock(_origin: SameOrigin, state: &rocket::State<AppState>) -> Redirect {
+    state.keystore.lock().unwrap().lock();
+    Redirect::to("/settings")
+}
+
+#[derive(FromForm)]
+pub struct KeyRemoval {
+    pubkey: String,
+}
+
+#[post("/keys/remove", data = "<removal>")]
+pub fn remove_key(_origin: SameOrigin, state: &rocket::State<AppState>, removal: Form<KeyRemoval>) -> Result<Redirect, BadRequest<String>> {
+    let pubkey: Pubkey = removal.pubkey.parse().map_err(|_| BadRequest(Some(format!("bad pubkey {}", removal.pubkey))))?;
+    let mut keystore = state.keystore.lock().unwrap();
+    keystore.remove_key(&pubkey).map_err(|e| BadRequest(Some(e.to_string())))?;
+    println!("Removed key {} from keystore", pubkey);
+    Ok(Redirect::to("/settings"))
+}
+
+#[get("/keys")]
+pub fn list_keys(state: &rocket::State<AppState>) -> Result<Json<Vec<KeyInfo>>, BadRequest<String>> {
+    let keystore = state.keystore.lock().unwrap();
+    keystore.list_keys().map(Json).map_err(|e| BadRequest(Some(e.to_string())))
+}
+#[get("/api/bots")]
+pub fn bot_list(state: &rocket::State<AppState>) -> Json<Vec<BotSummary>> {
+    let bot_manager = state.bot_manager.lock().unwrap();
+    Json(bot_manager.get_bot_list().iter().map(|bot| bot.summary()).collect())
+}
+
+#[get("/api/strategies")]
+pub fn strategy_list(state: &rocket::State<AppState>) -> Json<Vec<StrategyKindInfo>> {
+    Json(state.bot_manager.lock().unwrap().strategy_kinds())
+}
+
+#[get("/bots/add")]
+pub fn add_bot(state: &rocket::State<AppState>) -> Template {
+    let context = context! {
+        title: "Start New Bot",
+        rpc_endpoint: state.rpc_pool.primary_url(),
+        strategy_kinds: state.bot_manager.lock().unwrap().strategy_kinds(),
+    };
+    Template::render("add_bot", &context)
+}
+
+//the body is the strategy's config plus a strategy field naming the kind, openbook_spl_usdc when it is left out
+#[post("/api/bots", format = "json", data = "<config>")]
+pub async fn bot_add(_origin: SameOrigin, state: &rocket::State<AppState>, config: Json<BotConfig>) -> Result<Json<BotSummary>, BadRequest<String>> {
+    let signer = state.trading_signer().map_err(|e| BadRequest(Some(e.to_string())))?;
+    let build = state.bot_manager.lock().unwrap()
+        .prepare_bot(Uuid::new_v4(), config.into_inner(), state.rpc_pool.clone(), state.jupiter_pricing_source.clone(), signer)
+        .map_err(|e| BadRequest(Some(e)))?;
+    //the market is loaded before the bot manager is locked, that takes a few rpc round trips
+    let bot = build.await.map_err(|e| BadRequest(Some(e)))?;
+    let uuid = bot.uuid;
+    let mut bot_manager = state.bot_manager.lock().unwrap();
+    bot_manager.add_bot(bot);
+    println!("Added bot {}", uuid);
+    Ok(Json(bot_manager.get_bot(&uuid).unwrap().summary()))
+}
+
+#[post("/api/bots/<uuid>/start")]
+pub fn bot_start(_origin: SameOrigin, state: &rocket::State<AppState>, uuid: &str) -> Result<Json<BotSummary>, Custom<String>> {
+    bot_action(state, uuid, BotManager::start_bot)
+}
+
+#[post("/api/bots/<uuid>/pause")]
+pub fn bot_pause(_origin: SameOrigin, state: &rocket::State<AppState>, uuid: &str) -> Result<Json<BotSummary>, Custom<String>> {
+    bot_action(state, uuid, BotManager::pause_bot)
+}
+
+#[post("/api/bots/<uuid>/resume")]
+pub fn bot_resume(_origin: SameOrigin, state: &rocket::State<AppState>, uuid: &str) -> Result<Json<BotSummary>, Custom<String>> {
+    bot_action(state, uuid, BotManager::resume_bot)
+}
+
+#[post("/api/bots/<uuid>/stop")]
+pub fn bot_stop(_origin: SameOrigin, state: &rocket::State<AppState>, uuid: &str) -> Result<Json<BotSummary>, Custom<String>> {
+    bot_action(state, uuid, BotManager::stop_bot)
+}
+
+//only the fields that are sent change, 400 when the result doesn't validate
+#[patch("/api/bots/<uuid>/params", data = "<update>")]
+pub fn bot_params(_origin: SameOrigin, state: &rocket::State<AppState>, uuid: &str, update: Json<QuoteParamsUpdate>) -> Result<Json<BotSummary>, Custom<String>> {
+    let uuid = parse_bot_uuid(uuid)?;
+    let mut bot_manager = state.bot_manager.lock().unwrap();
+    if bot_manager.get_bot(&uuid).is_none() {
+        return Err(Custom(Status::NotFound, format!("no bot {}", uuid)));
+    }
+    bot_manager.update_params(&uuid, &update).map_err(|e| Custom(Status::BadRequest, e))?;
+    Ok(Json(bot_manager.get_bot(&uuid).unwrap().summary()))
+}
+
+//empty fields are left as they are
+#[derive(FromForm)]
+pub struct QuoteParamsForm {
+    bps_spread: Option<f64>,
+    bid_spread_bps: Option<f64>,
+    ask_spread_bps: Option<f64>,
+    amount_bid: Option<f64>,
+    amount_ask: Option<f64>,
+    min_midpoint_change: Option<f64>,
+}
+
+#[post("/bots/<uuid>/params", data = "<form>")]
+pub fn bot_params_form(origin: SameOrigin, state: &rocket::State<AppState>, uuid: &str, form: Form<QuoteParamsForm>) -> Result<Redirect, Custom<String>> {
+    let form = form.into_inner();
+    let update = QuoteParamsUpdate {
+        bps_spread: form.bps_spread,
+        bid_spread_bps: form.bid_spread_bps,
+        ask_spread_bps: form.ask_spread_bps,
+        amount_bid: form.amount_bid,
+        amount_ask: form.amount_ask,
+        min_midpoint_change: form.min_midpoint_change,
+    };
+    bot_params(origin, state, uuid, Json(update))?;
+    Ok(Redirect::to("/"))
+}
+
+#[delete("/api/bots/<uuid>")]
+pub fn bot_delete(_origin: SameOrigin, state: &rocket::State<AppState>, uuid: &str) -> Result<Json<BotSummary>, Custom<String>> {
+    let uuid = parse_bot_uuid(uuid)?;
+    let mut bot_manager = state.bot_manager.lock().unwrap();
+    let summary = bot_manager.get_bot(&uuid).ok_or_else(|| Custom(Status::NotFound, format!("no bot {}", uuid)))?.summary();
+    bot_manager.delete_bot(&uuid).map_err(|e| Custom(Status::InternalServerError, e))?;
+    Ok(Json(summary))
+}
+
+//404 for an unknown bot, 409 when the bot's state doesn't allow the action
+fn bot_action(state: &rocket::State<AppState>, uuid: &str, action: fn(&mut BotManager, &Uuid) -> Result<(), String>) -> Result<Json<BotSummary>, Custom<String>> {
+    let uuid = parse_bot_uuid(uuid)?;
+    let mut bot_manager = state.bot_manager.lock().unwrap();
+    if bot_manager.get_bot(&uuid).is_none() {
+        return Err(Custom(Status::NotFound, format!("no bot {}", uuid)));
+    }
+    action(&mut bot_manager, &uuid).map_err(|e| Custom(Status::Conflict, e))?;
+    Ok(Json(bot_manager.get_bot(&uuid).unwrap().summary()))
+}
+
+fn parse_bot_uuid(uuid: &str) -> Result<Uuid, Custom<String>> {
+    Uuid::parse_str(uuid).map_err(|_| Custom(Status::NotFound, format!("no bot {}", uuid)))
+}
+#[post("/api/kill")]
+pub async fn kill(_origin: SameOrigin, state: &rocket::State<AppState>) -> Result<Json<KillSwitchReport>, Custom<String>> {
+    println!("Kill switch pulled");
+    Ok(Json(kill_switch(state).await?))
+}
+
+//the settings page button, the report only goes to the log
+#[post("/kill")]
+pub async fn kill_form(_origin: SameOrigin, state: &rocket::State<AppState>) -> Result<Redirect, Custom<String>> {
+    println!("Kill switch pulled from the web ui");
+    kill_switch(state).await?;
+    Ok(Redirect::to("/settings"))
+}
+
+async fn kill_switch(state: &rocket::State<AppState>) -> Result<KillSwitchReport, Custom<String>> {
+    let timeout = Duration::from_millis(arcana_web_config::bot_defaults().shutdown_timeout_ms);
+    let kill = state.bot_manager.lock().unwrap().kill_switch(state.rpc_pool.clone(), timeout);
+    kill.await.map_err(|e| Custom(Status::InternalServerError, format!("kill switch task failed: {}", e)))
+}
+
+//the bots wind down in the shutdown fairing, this only starts it
+#[post("/shutdown")]
+pub fn shutdown(_origin: SameOrigin, shutdown: Shutdown) -> &'static str {
+    println!("Shutdown requested from the web ui");
+    shutdown.notify();
+    "Shutting down, bots are cancelling their orders and settling"
+}
+//todo need to rewrite .jsp into tera
+
+//refuses a request a browser sent from another site. Browsers send an Origin (or at least a Referer) with every
+//POST, so a page elsewhere can't submit a form that changes keys, settings or bots, pulls the kill switch or shuts the
+//server down. The cli sends neither and gets through
+pub struct SameOrigin;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SameOrigin {
+    type Error = String;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let headers = request.headers();
+        let source = match headers.get_one("Origin").or_else(|| headers.get_one("Referer")) {
+            Some(source) => source,
+            None => return Outcome::Success(SameOrigin),
+        };
+        match (url_authority(source), headers.get_one("Host")) {
+            (Some(authority), Some(host)) if authority.eq_ignore_ascii_case(host) => Outcome::Success(SameOrigin),
+            _ => Outcome::Failure((Status::Forbidden, format!("cross-origin request from {} refused", source))),
+        }
+    }
+}
+
+//"http://127.0.0.1:8080/settings" is 127.0.0.1:8080, an opaque origin ("null") has none
+fn url_authority(url: &str) -> Option<&str> {
+    let rest = url.split_once("://")?.1;
+    rest.split(['/', '?', '#']).next().filter(|authority| !authority.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::Header;
+    use rocket::local::blocking::Client;
+    use rocket::{routes, Route};
+
+    use super::*;
+
+    fn post(routes: Vec<Route>, uri: &'static str, headers: &[(&'static str, &'static str)]) -> Status {
+        let client = Client::untracked(rocket::build().mount("/", routes)).unwrap();
+        let mut request = client.post(uri).header(Header::new("Host", "127.0.0.1:8080"));
+        for (name, value) in headers {
+            request = request.header(Header::new(*name, *value));
+        }
+        let status = request.dispatch().status();
+        status
+    }
+
+    fn post_shutdown(headers: &[(&'static str, &'static str)]) -> Status {
+        post(routes![shutdown], "/shutdown", headers)
+    }
+
+    #[test]
+    fn shutdown_from_the_ui_or_the_cli_is_allowed() {
+        assert_eq!(post_shutdown(&[("Origin", "http://127.0.0.1:8080")]), Status::Ok);
+        assert_eq!(post_shutdown(&[("Referer", "http://127.0.0.1:8080/settings")]), Status::Ok);
+        assert_eq!(post_shutdown(&[]), Status::Ok);
+    }
+
+    #[test]
+    fn shutdown_from_another_site_is_refused() {
+        assert_eq!(post_shutdown(&[("Origin", "https://evil.example")]), Status::Forbidden);
+        assert_eq!(post_shutdown(&[("Origin", "http://127.0.0.1:9999")]), Status::Forbidden);
+        assert_eq!(post_shutdown(&[("Origin", "null")]), Status::Forbidden);
+        assert_eq!(post_shutdown(&[("Referer", "https://evil.example/page?next=http://127.0.0.1:8080")]), Status::Forbidden);
+    }
+
+    #[test]
+    fn settings_from_another_site_are_refused() {
+        assert_eq!(post(routes![settings_save], "/settings/save", &[("Origin", "https://evil.example")]), Status::Forbidden);
+    }
+
+    #[test]
+    fn url_authority_is_the_host_and_port() {
+        assert_eq!(url_authority("http://127.0.0.1:8080"), Some("127.0.0.1:8080"));
+        assert_eq!(url_authority("http://localhost:8080/settings?tab=keys"), Some("localhost:8080"));
+        assert_eq!(url_authority("null"), None);
+        assert_eq!(url_authority("http://"), None);
+    }
+}
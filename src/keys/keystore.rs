@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use aes_gcm_siv::aead::{Aead, NewAead, Payload};
+use aes_gcm_siv::{Aes256GcmSiv, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use hmac::Hmac;
+use rand::RngCore;
+use serde_derive::{Deserialize, Serialize};
+use sha2::Sha256;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use zeroize::Zeroize;
+
+use rocket::serde::json::serde_json;
+
+const KEYSTORE_VERSION: u32 = 1;
+#[cfg(not(test))]
+const KDF_ITERATIONS: u32 = 600_000;
+//unlocking reads the count from the file, so the tests don't have to wait on the real one
+#[cfg(test)]
+const KDF_ITERATIONS: u32 = 1_000;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+//encrypted with the derived key so a wrong passphrase is caught even when the keystore holds no keys yet
+const PASSPHRASE_CHECK: &[u8] = b"arcana-keystore";
+
+#[derive(Debug)]
+pub enum KeystoreError {
+    Locked,
+    NotFound(PathBuf),
+    AlreadyExists(PathBuf),
+    WrongPassphrase,
+    InvalidKey(String),
+    DuplicateKey(Pubkey),
+    UnknownKey(Pubkey),
+    Corrupted(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeystoreError::Locked => write!(f, "keystore is locked"),
+            KeystoreError::NotFound(path) => write!(f, "no keystore at {}", path.display()),
+            KeystoreError::AlreadyExists(path) => write!(f, "keystore already exists at {}", path.display()),
+            KeystoreError::WrongPassphrase => write!(f, "wrong keystore passphrase"),
+            KeystoreError::InvalidKey(reason) => write!(f, "invalid private key: {}", reason),
+            KeystoreError::DuplicateKey(pubkey) => write!(f, "key {} is already in the keystore", pubkey),
+            KeystoreError::UnknownKey(pubkey) => write!(f, "key {} is not in the keystore", pubkey),
+            KeystoreError::Corrupted(reason) => write!(f, "keystore file is corrupted: {}", reason),
+            KeystoreError::Io(err) => write!(f, "keystore io error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for KeystoreError {}
+
+impl From<std::io::Error> for KeystoreError {
+    fn from(err: std::io::Error) -> Self {
+        KeystoreError::Io(err)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct EncryptedBlob {
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreEntry {
+    label: String,
+    pubkey: String,
+    secret: EncryptedBlob,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    salt: String,
+    iterations: u32,
+    check: EncryptedBlob,
+    keys: Vec<KeystoreEntry>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct KeyInfo {
+    pub label: String,
+    pub pubkey: String,
+}
+
+struct UnlockedKeys {
+    cipher_key: [u8; 32],
+    keypairs: HashMap<Pubkey, Arc<Keypair>>,
+}
+
+impl Drop for UnlockedKeys {
+    fn drop(&mut self) {
+        self.cipher_key.zeroize();
+    }
+}
+
+//keys are only ever decrypted into memory, the file on disk holds AES-256-GCM-SIV ciphertexts
+//keyed by PBKDF2-HMAC-SHA256(passphrase), with each entry's pubkey bound in as associated data
+pub struct Keystore {
+    path: PathBuf,
+    unlocked: Option<UnlockedKeys>,
+}
+
+impl Keystore {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            unlocked: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.unlocked.is_some()
+    }
+
+    pub fn create(&mut self, passphrase: &str) -> Result<(), KeystoreError> {
+        if self.exists() {
+            return Err(KeystoreError::AlreadyExists(self.path.clone()));
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let cipher_key = derive_key(passphrase, &salt, KDF_ITERATIONS);
+
+        let file = KeystoreFile {
+            version: KEYSTORE_VERSION,
+            salt: BASE64.encode(salt),
+            iterations: KDF_ITERATIONS,
+            check: encrypt(&cipher_key, PASSPHRASE_CHECK, b"")?,
+            keys: Vec::new(),
+        };
+        write_file(&self.path, &file)?;
+
+        self.unlocked = Some(UnlockedKeys {
+            cipher_key,
+            keypairs: HashMap::new(),
+        });
+        Ok(())
+    }
+
+    //creates the keystore on first use so a fresh install can be unlocked straight away
+    pub fn unlock_or_create(&mut self, passphrase: &str) -> Result<(), KeystoreError> {
+        if self.exists() {
+            self.unlock(passphrase)
+        } else {
+            self.create(passphrase)
+        }
+    }
+
+    pub fn unlock(&mut self, passphrase: &str) -> Result<(), KeystoreError> {
+        let file = read_file(&self.path)?;
+        let salt = decode(&file.salt)?;
+        let cipher_key = derive_key(passphrase, &salt, file.iterations);
+
+        match decrypt(&cipher_key, &file.check, b"") {
+            Ok(check) if check == PASSPHRASE_CHECK => {}
+            _ => return Err(KeystoreError::WrongPassphrase),
+        }
+
+        let mut keypairs = HashMap::new();
+        for entry in &file.keys {
+            let pubkey = parse_pubkey(&entry.pubkey)?;
+            let mut secret = decrypt(&cipher_key, &entry.secret, pubkey.as_ref())?;
+            let keypair = Keypair::from_bytes(&secret)
+                .map_err(|e| KeystoreError::Corrupted(e.to_string()));
+            secret.zeroize();
+            let keypair = keypair?;
+            if keypair.pubkey() != pubkey {
+                return Err(KeystoreError::Corrupted(format!("entry {} does not match its secret key", pubkey)));
+            }
+            keypairs.insert(pubkey, Arc::new(keypair));
+        }
+
+        self.unlocked = Some(UnlockedKeys { cipher_key, keypairs });
+        Ok(())
+    }
+
+    pub fn lock(&mut self) {
+        self.unlocked = None;
+    }
+
+    //listing only needs the public half, so it works while locked
+    pub fn list_keys(&self) -> Result<Vec<KeyInfo>, KeystoreError> {
+        if !self.exists() {
+            return Ok(Vec::new());
+        }
+        let file = read_file(&self.path)?;
+        Ok(file.keys.iter()
+            .map(|entry| KeyInfo { label: entry.label.clone(), pubkey: entry.pubkey.clone() })
+            .collect())
+    }
+
+    pub fn add_key(&mut self, label: &str, keypair: Keypair) -> Result<Pubkey, KeystoreError> {
+        let unlocked = self.unlocked.as_mut().ok_or(KeystoreError::Locked)?;
+        let pubkey = keypair.pubkey();
+        if unlocked.keypairs.contains_key(&pubkey) {
+            return Err(KeystoreError::DuplicateKey(pubkey));
+        }
+
+        let mut file = read_file(&self.path)?;
+        let mut secret = keypair.to_bytes();
+        let encrypted = encrypt(&unlocked.cipher_key, &secret, pubkey.as_ref());
+        secret.zeroize();
+
+        file.keys.push(KeystoreEntry {
+            label: label.to_string(),
+            pubkey: pubkey.to_string(),
+            secret: encrypted?,
+        });
+        write_file(&self.path, &file)?;
+
+        unlocked.keypairs.insert(pubkey, Arc::new(keypair));
+        Ok(pubkey)
+    }
+
+    pub fn remove_key(&mut self, pubkey: &Pubkey) -> Result<(), KeystoreError> {
+        let unlocked = self.unlocked.as_mut().ok_or(KeystoreError::Locked)?;
+        let mut file = read_file(&self.path)?;
+
+        let key_count = file.keys.len();
+        let pubkey_string = pubkey.to_string();
+        file.keys.retain(|entry| entry.pubkey != pubkey_string);
+        if file.keys.len() == key_count {
+            return Err(KeystoreError::UnknownKey(*pubkey));
+        }
+        write_file(&self.path, &file)?;
+
+        unlocked.keypairs.remove(pubkey);
+        Ok(())
+    }
+
+    pub fn get_keypair(&self, pubkey: &Pubkey) -> Result<Arc<Keypair>, KeystoreError> {
+        let unlocked = self.unlocked.as_ref().ok_or(KeystoreError::Locked)?;
+        unlocked.keypairs.get(pubkey).cloned().ok_or(KeystoreError::UnknownKey(*pubkey))
+    }
+
+    //the first key added is the trading account used when a bot doesn't name one
+    pub fn default_pubkey(&self) -> Option<Pubkey> {
+        self.list_keys().ok()?
+            .first()
+            .and_then(|info| parse_pubkey(&info.pubkey).ok())
+    }
+}
+
+//accepts the solana-cli json byte array format as well as a base58 encoded secret key
+pub fn parse_private_key(data: &[u8]) -> Result<Keypair, KeystoreError> {
+    let text = std::str::from_utf8(data)
+        .map_err(|_| KeystoreError::InvalidKey("key file is not utf-8".to_string()))?
+        .trim();
+
+    let mut bytes = if text.starts_with('[') {
+        serde_json::from_str::<Vec<u8>>(text)
+            .map_err(|e| KeystoreError::InvalidKey(e.to_string()))?
+    } else {
+        solana_sdk::bs58::decode(text).into_vec()
+            .map_err(|e| KeystoreError::InvalidKey(e.to_string()))?
+    };
+
+    let keypair = Keypair::from_bytes(&bytes)
+        .map_err(|e| KeystoreError::InvalidKey(e.to_string()));
+    bytes.zeroize();
+    keypair
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+fn encrypt(cipher_key: &[u8; 32], plaintext: &[u8], aad: &[u8]) -> Result<EncryptedBlob, KeystoreError> {
+    let cipher = Aes256GcmSiv::new(Key::from_slice(cipher_key));
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+        .map_err(|_| KeystoreError::Corrupted("encryption failed".to_string()))?;
+
+    Ok(EncryptedBlob {
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn decrypt(cipher_key: &[u8; 32], blob: &EncryptedBlob, aad: &[u8]) -> Result<Vec<u8>, KeystoreError> {
+    let cipher = Aes256GcmSiv::new(Key::from_slice(cipher_key));
+    let nonce = decode(&blob.nonce)?;
+    if nonce.len() != NONCE_LEN {
+        return Err(KeystoreError::Corrupted("bad nonce length".to_string()));
+    }
+    let ciphertext = decode(&blob.ciphertext)?;
+
+    cipher.decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad })
+        .map_err(|_| KeystoreError::WrongPassphrase)
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, KeystoreError> {
+    BASE64.decode(value).map_err(|e| KeystoreError::Corrupted(e.to_string()))
+}
+
+fn parse_pubkey(value: &str) -> Result<Pubkey, KeystoreError> {
+    value.parse().map_err(|_| KeystoreError::Corrupted(format!("bad pubkey {}", value)))
+}
+
+fn read_file(path: &Path) -> Result<KeystoreFile, KeystoreError> {
+    if !path.exists() {
+        return Err(KeystoreError::NotFound(path.to_path_buf()));
+    }
+    let contents = fs::read_to_string(path)?;
+    let file: KeystoreFile = serde_json::from_str(&contents)
+        .map_err(|e| KeystoreError::Corrupted(e.to_string()))?;
+    if file.version != KEYSTORE_VERSION {
+        return Err(KeystoreError::Corrupted(format!("unsupported version {}", file.version)));
+    }
+    Ok(file)
+}
+
+//write to a sibling file first so a crash mid-write can't truncate the only copy of the keys
+fn write_file(path: &Path, file: &KeystoreFile) -> Result<(), KeystoreError> {
+    let contents = serde_json::to_string_pretty(file)
+        .map_err(|e| KeystoreError::Corrupted(e.to_string()))?;
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, contents)?;
+    restrict_permissions(&tmp_path)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //removed again when the test is done, whatever happens in it
+    struct TempKeystore(PathBuf);
+
+    impl TempKeystore {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("arcana-keystore-{}.json", uuid::Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempKeystore {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn keys_survive_lock_and_unlock() {
+        let path = TempKeystore::new();
+        let mut keystore = Keystore::new(&path.0);
+        keystore.create("correct horse").unwrap();
+        let keypair = Keypair::new();
+        let secret = keypair.to_bytes();
+        let pubkey = keystore.add_key("trading", keypair).unwrap();
+
+        let contents = fs::read_to_string(&path.0).unwrap();
+        assert!(!contents.contains(&BASE64.encode(secret)));
+
+        let mut reopened = Keystore::new(&path.0);
+        assert!(matches!(reopened.get_keypair(&pubkey), Err(KeystoreError::Locked)));
+        reopened.unlock("correct horse").unwrap();
+        assert_eq!(reopened.get_keypair(&pubkey).unwrap().to_bytes(), secret);
+        assert_eq!(reopened.default_pubkey(), Some(pubkey));
+
+        reopened.lock();
+        assert!(matches!(reopened.get_keypair(&pubkey), Err(KeystoreError::Locked)));
+        assert_eq!(reopened.list_keys().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let path = TempKeystore::new();
+        let mut keystore = Keystore::new(&path.0);
+        keystore.create("correct horse").unwrap();
+
+        let mut reopened = Keystore::new(&path.0);
+        assert!(matches!(reopened.unlock("battery staple"), Err(KeystoreError::WrongPassphrase)));
+        assert!(!reopened.is_unlocked());
+    }
+
+    #[test]
+    fn tampered_secret_is_rejected() {
+        let path = TempKeystore::new();
+        let mut keystore = Keystore::new(&path.0);
+        keystore.create("correct horse").unwrap();
+        keystore.add_key("trading", Keypair::new()).unwrap();
+
+        let mut file = read_file(&path.0).unwrap();
+        let mut ciphertext = decode(&file.keys[0].secret.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        file.keys[0].secret.ciphertext = BASE64.encode(ciphertext);
+        write_file(&path.0, &file).unwrap();
+
+        assert!(Keystore::new(&path.0).unlock("correct horse").is_err());
+    }
+
+    #[test]
+    fn secret_moved_to_another_pubkey_is_rejected() {
+        let path = TempKeystore::new();
+        let mut keystore = Keystore::new(&path.0);
+        keystore.create("correct horse").unwrap();
+        keystore.add_key("trading", Keypair::new()).unwrap();
+
+        //the pubkey is bound in as associated data, swapping it breaks decryption
+        let mut file = read_file(&path.0).unwrap();
+        file.keys[0].pubkey = Keypair::new().pubkey().to_string();
+        write_file(&path.0, &file).unwrap();
+
+        assert!(Keystore::new(&path.0).unlock("correct horse").is_err());
+    }
+
+    #[test]
+    fn garbage_file_is_corrupted() {
+        let path = TempKeystore::new();
+        fs::write(&path.0, "not a keystore").unwrap();
+        assert!(matches!(Keystore::new(&path.0).unlock("correct horse"), Err(KeystoreError::Corrupted(_))));
+    }
+}
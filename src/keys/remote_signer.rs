@@ -0,0 +1,118 @@
+use std::io::BufReader;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::{Signer, SignerError};
+
+use crate::keys::signer_protocol::{HTTP_SIGN_PATH, read_line, SignerRequest, SignerResponse, write_line};
+
+const SIGNER_TIMEOUT: Duration = Duration::from_secs(5);
+
+enum SignerTransport {
+    Unix(PathBuf),
+    Http { url: String, client: Client },
+}
+
+//delegates signing to a separate process so the web process never holds hot keys
+pub struct RemoteSigner {
+    endpoint: String,
+    transport: SignerTransport,
+    pubkey: Pubkey,
+}
+
+impl RemoteSigner {
+    //endpoint is either unix:/path/to/socket or an http(s) base url
+    pub fn connect(endpoint: &str) -> Result<Self, SignerError> {
+        let transport = if let Some(path) = endpoint.strip_prefix("unix:") {
+            SignerTransport::Unix(PathBuf::from(path))
+        } else if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+            let client = Client::builder()
+                .timeout(SIGNER_TIMEOUT)
+                .build()
+                .map_err(|e| SignerError::Connection(e.to_string()))?;
+            SignerTransport::Http {
+                url: format!("{}{}", endpoint.trim_end_matches('/'), HTTP_SIGN_PATH),
+                client,
+            }
+        } else {
+            return Err(SignerError::Custom(format!("unsupported signer endpoint {}", endpoint)));
+        };
+
+        let mut signer = Self {
+            endpoint: endpoint.to_string(),
+            transport,
+            pubkey: Pubkey::default(),
+        };
+
+        signer.pubkey = match signer.request(&SignerRequest::GetPubkey)? {
+            SignerResponse::Pubkey { pubkey } => pubkey.parse()
+                .map_err(|_| SignerError::Custom(format!("signer returned bad pubkey {}", pubkey)))?,
+            other => return Err(unexpected_response(other)),
+        };
+        println!("Connected to remote signer {} for {}", signer.endpoint, signer.pubkey);
+
+        Ok(signer)
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    fn request(&self, request: &SignerRequest) -> Result<SignerResponse, SignerError> {
+        match &self.transport {
+            SignerTransport::Unix(path) => {
+                let stream = UnixStream::connect(path)
+                    .map_err(|e| SignerError::Connection(format!("{}: {}", path.display(), e)))?;
+                stream.set_read_timeout(Some(SIGNER_TIMEOUT)).ok();
+                stream.set_write_timeout(Some(SIGNER_TIMEOUT)).ok();
+
+                let mut writer = stream.try_clone().map_err(|e| SignerError::Connection(e.to_string()))?;
+                write_line(&mut writer, request).map_err(|e| SignerError::Connection(e.to_string()))?;
+
+                let mut reader = BufReader::new(stream);
+                read_line(&mut reader)
+                    .map_err(|e| SignerError::Protocol(e.to_string()))?
+                    .ok_or_else(|| SignerError::Protocol("signer closed the connection".to_string()))
+            }
+            SignerTransport::Http { url, client } => {
+                client.post(url)
+                    .json(request)
+                    .send()
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| SignerError::Connection(e.to_string()))?
+                    .json::<SignerResponse>()
+                    .map_err(|e| SignerError::Protocol(e.to_string()))
+            }
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        match self.request(&SignerRequest::sign_message(message))? {
+            SignerResponse::Signature { signature } => signature.parse()
+                .map_err(|_| SignerError::Protocol(format!("signer returned bad signature {}", signature))),
+            SignerResponse::Rejected { reason } => {
+                eprintln!("Remote signer rejected message: {}", reason);
+                Err(SignerError::Custom(reason))
+            }
+            other => Err(unexpected_response(other)),
+        }
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+fn unexpected_response(response: SignerResponse) -> SignerError {
+    SignerError::Protocol(format!("unexpected signer response {:?}", response))
+}
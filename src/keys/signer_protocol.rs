@@ -0,0 +1,49 @@
+use std::io::{BufRead, Write};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_derive::{Deserialize, Serialize};
+
+//shared between the web process and the arcana-signer binary, so it must not depend on anything else in the crate.
+//over a unix socket each request/response is a single line of json, over http it's the body of POST /sign. What
+//only the signer needs, like its signing policy, lives in the binary
+
+pub const HTTP_SIGN_PATH: &str = "/sign";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum SignerRequest {
+    GetPubkey,
+    SignMessage { message: String },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum SignerResponse {
+    Pubkey { pubkey: String },
+    Signature { signature: String },
+    Rejected { reason: String },
+}
+
+impl SignerRequest {
+    pub fn sign_message(message: &[u8]) -> Self {
+        SignerRequest::SignMessage { message: BASE64.encode(message) }
+    }
+}
+
+pub fn write_line<W: Write, T: serde::Serialize>(writer: &mut W, value: &T) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()
+}
+
+pub fn read_line<R: BufRead, T: serde::de::DeserializeOwned>(reader: &mut R) -> std::io::Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    serde_json::from_str(&line)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
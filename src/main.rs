@@ -1,30 +1,66 @@
 //#![feature(proc_macro_hygiene, decl_macro)] todo check this macro out
+//solana's ClientError is what every rpc call returns, boxing it at each call site buys nothing
+#![allow(clippy::result_large_err)]
 
 use std::net::IpAddr;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use rocket::config::Config;
 use rocket::fairing::AdHoc;
-use rocket_dyn_templates::{context, Template};
+use rocket_dyn_templates::Template;
 use rocket::routes;
-use rocket::get;
-use solana_client::rpc_client::RpcClient;
+use clap::Parser;
+use solana_sdk::signer::Signer;
 
 //PC MINT IS QUOTE CURRENCY
 //COIN MINT IS THE BASE CURRENCY
 
 mod pricing {
     pub mod jupiter_pricing_source;
-    pub mod pyth_pricing_source;
 }
 mod strategies {
+    pub mod bot_lifecycle;
     pub mod bot_manager;
+    pub mod bot_store;
+    pub mod client_order_ids;
+    pub mod ladder;
     pub mod open_book_bot;
+    pub mod order_executor;
+    pub mod order_tracker;
+    pub mod quote_params;
+    pub mod reconciler;
     pub mod strategy;
+    pub mod strategy_registry;
     mod open_book {
         pub mod open_book_spl_usdc;
     }
 }
+mod transactions {
+    pub mod priority_fees;
+    pub mod confirmation_tracker;
+    pub mod lookup_tables;
+    pub mod direct_send;
+    pub mod emergency_cancel;
+}
+mod rpc {
+    pub mod chain_client;
+    #[cfg(test)]
+    pub mod mock_chain_client;
+    pub mod rpc_pool;
+    pub mod rate_limiter;
+    pub mod retry;
+}
+mod keys {
+    pub mod keystore;
+    pub mod remote_signer;
+    pub mod signer_protocol;
+}
 pub mod arcana_web_config;
+//rocket's FromForm derive still emits allow(private_in_public), which newer compilers no longer know
+#[allow(renamed_and_removed_lints)]
 pub mod controller;
+pub mod cli;
 
 mod serum {
     pub mod serum_utils;
@@ -32,40 +68,148 @@ mod serum {
     pub mod market;
     pub mod market_builder;
     pub mod order_book;
+    pub mod market_feed;
+    pub mod open_orders;
+    #[cfg(test)]
+    pub mod market_fixture;
 }
 
 use pricing::jupiter_pricing_source::JupiterPricingSource;
-use pricing::pyth_pricing_source::PythPricingSource;
+use crate::arcana_web_config::ArcanaConfig;
+use crate::cli::{Cli, Command};
 use crate::strategies::bot_manager::BotManager;
+use crate::strategies::bot_store::BotStore;
+use crate::keys::keystore::Keystore;
+use crate::keys::remote_signer::RemoteSigner;
+use crate::rpc::rpc_pool::RpcPool;
 
-struct AppState {
+pub struct AppState {
     jupiter_pricing_source: JupiterPricingSource,
-    pyth_pricing_source: PythPricingSource,
-    bot_manager: BotManager,
-    rpc_client: RpcClient,
+    bot_manager: Mutex<BotManager>,
+    rpc_pool: Arc<RpcPool>,
+    keystore: Mutex<Keystore>,
+    remote_signer: Option<Arc<RemoteSigner>>,
+}
+
+impl AppState {
+    //an external signer always wins so hot keys stay out of this process when one is configured
+    fn trading_signer(&self) -> Result<Arc<dyn Signer + Send + Sync>, Box<dyn Error>> {
+        trading_signer(&self.remote_signer, &self.keystore.lock().unwrap())
+    }
+}
+
+fn trading_signer(remote_signer: &Option<Arc<RemoteSigner>>, keystore: &Keystore) -> Result<Arc<dyn Signer + Send + Sync>, Box<dyn Error>> {
+    if let Some(remote_signer) = remote_signer {
+        return Ok(remote_signer.clone());
+    }
+    let pubkey = keystore.default_pubkey().ok_or("keystore has no trading key")?;
+    Ok(keystore.get_keypair(&pubkey)?)
 }
 
 fn main() {
+    let cli = Cli::parse();
+    let arcana_config = match arcana_web_config::load() {
+        Ok(arcana_config) => arcana_config,
+        Err(err) => {
+            eprintln!("Error loading {}: {}", arcana_web_config::config_path().display(), err);
+            std::process::exit(1);
+        }
+    };
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(arcana_config),
+        command => {
+            let runtime = tokio::runtime::Runtime::new().expect("Error building a tokio runtime");
+            if let Err(err) = runtime.block_on(cli::run(command)) {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+fn serve(arcana_config: ArcanaConfig) {
     let mut config = Config::debug_default();
-    config.address = IpAddr::V4("0.0.0.0".parse().unwrap());
-    config.port = 8080;
+    //already validated
+    config.address = arcana_config.server.address.parse::<IpAddr>().unwrap();
+    config.port = arcana_config.server.port;
 
-    let rpc_client = arcana_web_config::rpc_client();
-    let jupiter_pricing_source = JupiterPricingSource::new();
-    let pyth_pricing_source = PythPricingSource::new(rpc_client); // You would need to implement this
+    //one pool for the whole process so every component sees the same endpoint health
+    let rpc_pool = Arc::new(arcana_web_config::rpc_pool());
+    rpc_pool.start_health_checks();
+    let jupiter_pricing_source = JupiterPricingSource::new(arcana_config.pricing.jupiter_price_url.clone());
     // let strategy_manager = StrategyManager::new();
-    let bot_manager = BotManager::new();
 
-    rocket::custom(config)
+    let mut keystore = Keystore::new(arcana_web_config::keystore_path());
+    if let Some(passphrase) = arcana_web_config::keystore_passphrase() {
+        match keystore.unlock_or_create(&passphrase) {
+            Ok(()) => println!("Keystore unlocked: {}", keystore.path().display()),
+            Err(err) => eprintln!("Keystore unlock failed: {}", err),
+        }
+    }
+
+    let remote_signer = arcana_web_config::signer_endpoint().map(|endpoint| {
+        Arc::new(RemoteSigner::connect(&endpoint).expect("Error connecting to remote signer"))
+    });
+
+    let bot_store = match BotStore::open(arcana_web_config::database_path()) {
+        Ok(bot_store) => Arc::new(bot_store),
+        Err(err) => {
+            eprintln!("Error opening {}: {}", arcana_web_config::database_path(), err);
+            std::process::exit(1);
+        }
+    };
+    let mut bot_manager = BotManager::new(bot_store, arcana_web_config::bot_settings);
+    bot_manager.restore_bots(rpc_pool.clone(), &jupiter_pricing_source, trading_signer(&remote_signer, &keystore).ok());
+
+    let rocket = rocket::custom(config)
         .attach(AdHoc::on_ignite("State Configuration", |rocket| async move {
             rocket.manage(AppState {
                 jupiter_pricing_source,
-                pyth_pricing_source,
-                bot_manager,
-                rpc_client: arcana_web_config::rpc_client(),
+                bot_manager: Mutex::new(bot_manager),
+                rpc_pool,
+                keystore: Mutex::new(keystore),
+                remote_signer,
             })
         }))
+        //rocket runs this on ctrl-c, SIGTERM or POST /shutdown and waits for it before exiting
+        .attach(AdHoc::on_shutdown("Bot Shutdown", |rocket| Box::pin(async move {
+            if let Some(state) = rocket.state::<AppState>() {
+                let timeout = Duration::from_millis(arcana_web_config::bot_defaults().shutdown_timeout_ms);
+                let shutdown = state.bot_manager.lock().unwrap().shutdown(timeout);
+                if let Err(err) = shutdown.await {
+                    eprintln!("Error shutting down bots: {}", err);
+                }
+            }
+        })))
         .attach(Template::fairing())
-        .mount("/", routes![controller::index, controller::settings])
-        .launch();
+        .mount("/", routes![
+            controller::index,
+            controller::settings,
+            controller::settings_save,
+            controller::private_key_upload,
+            controller::keystore_unlock,
+            controller::keystore_lock,
+            controller::remove_key,
+            controller::list_keys,
+            controller::bot_list,
+            controller::bot_add,
+            controller::bot_start,
+            controller::bot_pause,
+            controller::bot_resume,
+            controller::bot_stop,
+            controller::bot_delete,
+            controller::strategy_list,
+            controller::add_bot,
+            controller::bot_params,
+            controller::bot_params_form,
+            controller::kill,
+            controller::kill_form,
+            controller::shutdown,
+        ]);
+
+    if let Err(err) = rocket::execute(rocket.launch()) {
+        eprintln!("Server stopped with an error: {}", err);
+        std::process::exit(1);
+    }
 }
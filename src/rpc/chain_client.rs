@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+use solana_client::client_error::Result as ClientResult;
+use solana_client::rpc_config::{RpcProgramAccountsConfig, RpcSendTransactionConfig, RpcSimulateTransactionConfig};
+use solana_client::rpc_response::RpcSimulateTransactionResult;
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use solana_transaction_status::TransactionStatus;
+
+use crate::rpc::rpc_pool::RpcPool;
+
+//every chain call the bots make, so market loading, pricing and order flow can run against
+//something other than a live node. Errors are solana's ClientError so retry classification still applies.
+//Everything is async so any number of bots can share one runtime
+#[async_trait]
+pub trait ChainClient: Send + Sync {
+    async fn get_account(&self, pubkey: &Pubkey, commitment: CommitmentConfig) -> ClientResult<Option<Account>>;
+
+    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey], commitment: CommitmentConfig) -> ClientResult<Vec<Option<Account>>>;
+
+    async fn get_program_accounts(&self, program_id: &Pubkey, config: RpcProgramAccountsConfig) -> ClientResult<Vec<(Pubkey, Account)>>;
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction, config: RpcSendTransactionConfig) -> ClientResult<Signature>;
+
+    //blocks until the transaction is confirmed, only meant for one-off setup transactions
+    async fn send_and_confirm_transaction(&self, transaction: &VersionedTransaction) -> ClientResult<Signature>;
+
+    async fn simulate_transaction(&self, transaction: &VersionedTransaction, config: RpcSimulateTransactionConfig) -> ClientResult<RpcSimulateTransactionResult>;
+
+    async fn get_signature_statuses(&self, signatures: &[Signature]) -> ClientResult<Vec<Option<TransactionStatus>>>;
+
+    //the blockhash and the last block height it can still land in
+    async fn get_latest_blockhash(&self, commitment: CommitmentConfig) -> ClientResult<(Hash, u64)>;
+
+    async fn get_slot(&self, commitment: CommitmentConfig) -> ClientResult<u64>;
+
+    async fn get_block_height(&self, commitment: CommitmentConfig) -> ClientResult<u64>;
+
+    //per-slot fees paid to write lock all of the given accounts
+    async fn get_recent_prioritization_fees(&self, writable_accounts: &[Pubkey]) -> ClientResult<Vec<u64>>;
+}
+
+#[async_trait]
+impl ChainClient for RpcPool {
+    async fn get_account(&self, pubkey: &Pubkey, commitment: CommitmentConfig) -> ClientResult<Option<Account>> {
+        self.call("getAccountInfo", |endpoint| async move {
+            endpoint.async_client().get_account_with_commitment(pubkey, commitment).await
+        }).await.map(|response| response.value)
+    }
+
+    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey], commitment: CommitmentConfig) -> ClientResult<Vec<Option<Account>>> {
+        self.call("getMultipleAccounts", |endpoint| async move {
+            endpoint.async_client().get_multiple_accounts_with_commitment(pubkeys, commitment).await
+        }).await.map(|response| response.value)
+    }
+
+    async fn get_program_accounts(&self, program_id: &Pubkey, config: RpcProgramAccountsConfig) -> ClientResult<Vec<(Pubkey, Account)>> {
+        self.call("getProgramAccounts", |endpoint| {
+            let config = config.clone();
+            async move { endpoint.async_client().get_program_accounts_with_config(program_id, config).await }
+        }).await
+    }
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction, config: RpcSendTransactionConfig) -> ClientResult<Signature> {
+        self.call("sendTransaction", |endpoint| async move {
+            endpoint.async_client().send_transaction_with_config(transaction, config).await
+        }).await
+    }
+
+    async fn send_and_confirm_transaction(&self, transaction: &VersionedTransaction) -> ClientResult<Signature> {
+        self.call("sendTransaction", |endpoint| async move {
+            endpoint.async_client().send_and_confirm_transaction(transaction).await
+        }).await
+    }
+
+    async fn simulate_transaction(&self, transaction: &VersionedTransaction, config: RpcSimulateTransactionConfig) -> ClientResult<RpcSimulateTransactionResult> {
+        self.call("simulateTransaction", |endpoint| {
+            let config = config.clone();
+            async move { endpoint.async_client().simulate_transaction_with_config(transaction, config).await }
+        }).await.map(|response| response.value)
+    }
+
+    async fn get_signature_statuses(&self, signatures: &[Signature]) -> ClientResult<Vec<Option<TransactionStatus>>> {
+        self.call("getSignatureStatuses", |endpoint| async move {
+            endpoint.async_client().get_signature_statuses(signatures).await
+        }).await.map(|response| response.value)
+    }
+
+    async fn get_latest_blockhash(&self, commitment: CommitmentConfig) -> ClientResult<(Hash, u64)> {
+        self.call("getLatestBlockhash", |endpoint| async move {
+            endpoint.async_client().get_latest_blockhash_with_commitment(commitment).await
+        }).await
+    }
+
+    async fn get_slot(&self, commitment: CommitmentConfig) -> ClientResult<u64> {
+        self.call("getSlot", |endpoint| async move {
+            endpoint.async_client().get_slot_with_commitment(commitment).await
+        }).await
+    }
+
+    async fn get_block_height(&self, commitment: CommitmentConfig) -> ClientResult<u64> {
+        self.call("getBlockHeight", |endpoint| async move {
+            endpoint.async_client().get_block_height_with_commitment(commitment).await
+        }).await
+    }
+
+    async fn get_recent_prioritization_fees(&self, writable_accounts: &[Pubkey]) -> ClientResult<Vec<u64>> {
+        self.call("getRecentPrioritizationFees", |endpoint| async move {
+            endpoint.async_client().get_recent_prioritization_fees(writable_accounts).await
+        }).await.map(|fees| fees.into_iter().map(|fee| fee.prioritization_fee).collect())
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_config::{RpcProgramAccountsConfig, RpcSendTransactionConfig, RpcSimulateTransactionConfig};
+use solana_client::rpc_response::RpcSimulateTransactionResult;
+use solana_sdk::account::{Account, AccountSharedData};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{TransactionError, VersionedTransaction};
+use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+
+use crate::rpc::chain_client::ChainClient;
+
+//blockhashes stay valid for this many blocks on mainnet
+const BLOCKHASH_VALIDITY_BLOCKS: u64 = 150;
+const DEFAULT_UNITS_CONSUMED: u64 = 40_000;
+
+struct MockChainState {
+    accounts: HashMap<Pubkey, Account>,
+    sent_transactions: Vec<VersionedTransaction>,
+    slot: u64,
+    blockhash: Hash,
+    prioritization_fees: Vec<u64>,
+    units_consumed: u64,
+    //the next send fails with this instead of being recorded
+    send_error: Option<String>,
+    //the next send lands but the program fails with this
+    execution_error: Option<TransactionError>,
+    //sends are recorded but never land, so they only ever expire
+    dropping_sends: bool,
+    //signatures that landed and how they ended
+    landed: HashMap<Signature, Option<TransactionError>>,
+}
+
+//in-memory chain for tests: serves fixture accounts, confirms everything it is sent straight away
+//unless told otherwise and keeps the sent transactions around for inspection
+pub struct MockChainClient {
+    state: Mutex<MockChainState>,
+}
+
+impl MockChainClient {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(MockChainState {
+                accounts: HashMap::new(),
+                sent_transactions: Vec::new(),
+                slot: 1,
+                blockhash: Hash::new_unique(),
+                prioritization_fees: Vec::new(),
+                units_consumed: DEFAULT_UNITS_CONSUMED,
+                send_error: None,
+                execution_error: None,
+                dropping_sends: false,
+                landed: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn set_account(&self, pubkey: Pubkey, account: Account) {
+        self.state.lock().unwrap().accounts.insert(pubkey, account);
+    }
+
+    pub fn remove_account(&self, pubkey: &Pubkey) {
+        self.state.lock().unwrap().accounts.remove(pubkey);
+    }
+
+    pub fn sent_transactions(&self) -> Vec<VersionedTransaction> {
+        self.state.lock().unwrap().sent_transactions.clone()
+    }
+
+    //moves the chain forward, which also gives it a fresh blockhash
+    pub fn advance_slot(&self, slots: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.slot += slots;
+        state.blockhash = Hash::new_unique();
+    }
+
+    pub fn set_prioritization_fees(&self, fees: Vec<u64>) {
+        self.state.lock().unwrap().prioritization_fees = fees;
+    }
+
+    pub fn set_units_consumed(&self, units_consumed: u64) {
+        self.state.lock().unwrap().units_consumed = units_consumed;
+    }
+
+    pub fn fail_next_send(&self, error: &str) {
+        self.state.lock().unwrap().send_error = Some(error.to_string());
+    }
+
+    pub fn fail_next_execution(&self, error: TransactionError) {
+        self.state.lock().unwrap().execution_error = Some(error);
+    }
+
+    pub fn drop_sends(&self, dropping_sends: bool) {
+        self.state.lock().unwrap().dropping_sends = dropping_sends;
+    }
+
+    fn record_sent(&self, transaction: &VersionedTransaction) -> ClientResult<Signature> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(error) = state.send_error.take() {
+            return Err(ClientError::from(ClientErrorKind::Custom(error)));
+        }
+        let signature = transaction.signatures[0];
+        state.sent_transactions.push(transaction.clone());
+        if !state.dropping_sends && !state.landed.contains_key(&signature) {
+            let execution_error = state.execution_error.take();
+            state.landed.insert(signature, execution_error);
+        }
+        Ok(signature)
+    }
+}
+
+#[async_trait]
+impl ChainClient for MockChainClient {
+    async fn get_account(&self, pubkey: &Pubkey, _commitment: CommitmentConfig) -> ClientResult<Option<Account>> {
+        Ok(self.state.lock().unwrap().accounts.get(pubkey).cloned())
+    }
+
+    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey], _commitment: CommitmentConfig) -> ClientResult<Vec<Option<Account>>> {
+        let state = self.state.lock().unwrap();
+        Ok(pubkeys.iter().map(|pubkey| state.accounts.get(pubkey).cloned()).collect())
+    }
+
+    async fn get_program_accounts(&self, program_id: &Pubkey, config: RpcProgramAccountsConfig) -> ClientResult<Vec<(Pubkey, Account)>> {
+        let filters = config.filters.unwrap_or_default();
+        let state = self.state.lock().unwrap();
+        Ok(state.accounts.iter()
+            .filter(|(_, account)| account.owner == *program_id)
+            .filter(|(_, account)| {
+                let shared = AccountSharedData::from((*account).clone());
+                filters.iter().all(|filter| filter.allows(&shared))
+            })
+            .map(|(pubkey, account)| (*pubkey, account.clone()))
+            .collect())
+    }
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction, _config: RpcSendTransactionConfig) -> ClientResult<Signature> {
+        self.record_sent(transaction)
+    }
+
+    async fn send_and_confirm_transaction(&self, transaction: &VersionedTransaction) -> ClientResult<Signature> {
+        self.record_sent(transaction)
+    }
+
+    async fn simulate_transaction(&self, _transaction: &VersionedTransaction, _config: RpcSimulateTransactionConfig) -> ClientResult<RpcSimulateTransactionResult> {
+        Ok(RpcSimulateTransactionResult {
+            err: None,
+            logs: Some(Vec::new()),
+            accounts: None,
+            units_consumed: Some(self.state.lock().unwrap().units_consumed),
+            return_data: None,
+        })
+    }
+
+    async fn get_signature_statuses(&self, signatures: &[Signature]) -> ClientResult<Vec<Option<TransactionStatus>>> {
+        let state = self.state.lock().unwrap();
+        Ok(signatures.iter()
+            .map(|signature| {
+                state.landed.get(signature).map(|error| TransactionStatus {
+                    slot: state.slot,
+                    confirmations: None,
+                    status: error.clone().map_or(Ok(()), Err),
+                    err: error.clone(),
+                    confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+                })
+            })
+            .collect())
+    }
+
+    async fn get_latest_blockhash(&self, _commitment: CommitmentConfig) -> ClientResult<(Hash, u64)> {
+        let state = self.state.lock().unwrap();
+        Ok((state.blockhash, state.slot + BLOCKHASH_VALIDITY_BLOCKS))
+    }
+
+    async fn get_slot(&self, _commitment: CommitmentConfig) -> ClientResult<u64> {
+        Ok(self.state.lock().unwrap().slot)
+    }
+
+    //no skipped slots here, so block height and slot are the same thing
+    async fn get_block_height(&self, _commitment: CommitmentConfig) -> ClientResult<u64> {
+        Ok(self.state.lock().unwrap().slot)
+    }
+
+    async fn get_recent_prioritization_fees(&self, _writable_accounts: &[Pubkey]) -> ClientResult<Vec<u64>> {
+        Ok(self.state.lock().unwrap().prioritization_fees.clone())
+    }
+}
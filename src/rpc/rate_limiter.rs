@@ -0,0 +1,101 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+//token bucket shared by every caller of one endpoint, bursts up to capacity then settles at the refill rate
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_second: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl TokenBucket {
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        let capacity = (burst.max(1)) as f64;
+        Self {
+            capacity,
+            refill_per_second: requests_per_second.max(f64::MIN_POSITIVE),
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    //takes a token if one is available, otherwise says how long until the next one
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let refilled = now.duration_since(bucket.last_refill).as_secs_f64() * self.refill_per_second;
+        bucket.tokens = (bucket.tokens + refilled).min(self.capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_second))
+        }
+    }
+
+    pub fn acquire(&self) {
+        while let Err(wait) = self.try_acquire() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    pub async fn acquire_async(&self) {
+        while let Err(wait) = self.try_acquire() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_up_to_capacity_then_waits_for_the_refill() {
+        let bucket = TokenBucket::new(10.0, 3);
+        for _ in 0..3 {
+            assert!(bucket.try_acquire().is_ok());
+        }
+        //a token every 100ms, a little of it has already refilled
+        let wait = bucket.try_acquire().unwrap_err();
+        assert!(wait > Duration::from_millis(50) && wait <= Duration::from_millis(100), "{:?}", wait);
+
+        std::thread::sleep(Duration::from_millis(110));
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_err());
+    }
+
+    #[test]
+    fn refill_stops_at_capacity() {
+        let bucket = TokenBucket::new(1000.0, 2);
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_err());
+    }
+
+    #[test]
+    fn a_zero_burst_still_allows_one_request() {
+        let bucket = TokenBucket::new(1.0, 0);
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_err());
+    }
+
+    #[tokio::test]
+    async fn acquire_waits_out_the_refill() {
+        let bucket = TokenBucket::new(20.0, 1);
+        let started = Instant::now();
+        bucket.acquire_async().await;
+        bucket.acquire_async().await;
+        assert!(started.elapsed() >= Duration::from_millis(45), "{:?}", started.elapsed());
+    }
+}
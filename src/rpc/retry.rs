@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_custom_error::{
+    JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE, JSON_RPC_SERVER_ERROR_BLOCK_STATUS_NOT_AVAILABLE_YET,
+    JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED, JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+};
+use solana_client::rpc_request::RpcError;
+
+const HTTP_TOO_MANY_REQUESTS: u16 = 429;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorClass {
+    //the endpoint was busy, slow or behind, the same request may well succeed later or elsewhere
+    Retryable,
+    //the request itself is wrong (bad params, failed preflight, bad signature), retrying won't help
+    Fatal,
+}
+
+impl ErrorClass {
+    pub fn of(err: &ClientError) -> Self {
+        let retryable = match err.kind() {
+            ClientErrorKind::Io(_) => true,
+            ClientErrorKind::Reqwest(err) => match err.status() {
+                Some(status) => status.as_u16() == HTTP_TOO_MANY_REQUESTS || status.is_server_error(),
+                None => err.is_timeout() || err.is_connect() || err.is_request(),
+            },
+            ClientErrorKind::RpcError(RpcError::RpcRequestError(_)) => true,
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => matches!(
+                *code,
+                JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+                    | JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE
+                    | JSON_RPC_SERVER_ERROR_BLOCK_STATUS_NOT_AVAILABLE_YET
+                    | JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED
+            ),
+            _ => false,
+        };
+
+        if retryable { ErrorClass::Retryable } else { ErrorClass::Fatal }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    //rounds over the whole pool after the first one
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+
+    //exponential backoff with the upper half jittered so bots that failed together don't retry together
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_delay);
+        let half = exponential / 2;
+        half + half.mul_f64(rand::random::<f64>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use solana_client::rpc_request::RpcResponseErrorData;
+    use solana_sdk::transaction::TransactionError;
+
+    use super::*;
+
+    fn response_error(code: i64) -> ClientError {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            code,
+            message: "error".to_string(),
+            data: RpcResponseErrorData::Empty,
+        }).into()
+    }
+
+    #[test]
+    fn busy_or_lagging_endpoints_are_retryable() {
+        let errors: Vec<ClientError> = vec![
+            ClientErrorKind::Io(io::Error::new(io::ErrorKind::TimedOut, "timed out")).into(),
+            ClientErrorKind::RpcError(RpcError::RpcRequestError("connection closed".to_string())).into(),
+            response_error(JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY),
+            response_error(JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE),
+            response_error(JSON_RPC_SERVER_ERROR_BLOCK_STATUS_NOT_AVAILABLE_YET),
+            response_error(JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED),
+        ];
+        for err in &errors {
+            assert_eq!(ErrorClass::of(err), ErrorClass::Retryable, "{}", err);
+        }
+    }
+
+    #[test]
+    fn bad_requests_are_fatal() {
+        let errors: Vec<ClientError> = vec![
+            //invalid params
+            response_error(-32602),
+            //preflight failure
+            response_error(-32002),
+            ClientErrorKind::TransactionError(TransactionError::InsufficientFundsForFee).into(),
+            ClientErrorKind::Custom("bad signature".to_string()).into(),
+        ];
+        for err in &errors {
+            assert_eq!(ErrorClass::of(err), ErrorClass::Fatal, "{}", err);
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_with_the_upper_half_jittered() {
+        let policy = RetryPolicy::new();
+        for _ in 0..100 {
+            for (attempt, exponential) in [(0, 200), (1, 400), (2, 800), (3, 1600)] {
+                let delay = policy.backoff(attempt);
+                assert!(delay >= Duration::from_millis(exponential / 2) && delay <= Duration::from_millis(exponential), "{:?}", delay);
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped_at_the_max_delay() {
+        let policy = RetryPolicy::new();
+        for attempt in [5, 10, 40, u32::MAX] {
+            let delay = policy.backoff(attempt);
+            assert!(delay >= policy.max_delay / 2 && delay <= policy.max_delay, "{:?}", delay);
+        }
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_derive::Serialize;
+use solana_client::client_error::Result as ClientResult;
+use solana_client::nonblocking::rpc_client::RpcClient as AsyncRpcClient;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::rpc::rate_limiter::TokenBucket;
+use crate::rpc::retry::{ErrorClass, RetryPolicy};
+
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const ERROR_RATE_WINDOW: usize = 100;
+const MAX_SLOT_LAG: u64 = 50;
+const MAX_ERROR_RATE: f64 = 0.25;
+
+#[derive(Clone, Serialize)]
+pub struct EndpointHealth {
+    pub url: String,
+    pub healthy: bool,
+    pub slot: u64,
+    pub slot_lag: u64,
+    pub latency_ms: u64,
+    pub error_rate: f64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct MethodStats {
+    pub method: String,
+    pub requests: u64,
+    pub retries: u64,
+    pub failures: u64,
+}
+
+struct EndpointStats {
+    reachable: bool,
+    slot: u64,
+    slot_lag: u64,
+    latency: Duration,
+    recent_results: VecDeque<bool>,
+    last_error: Option<String>,
+}
+
+impl EndpointStats {
+    fn error_rate(&self) -> f64 {
+        if self.recent_results.is_empty() {
+            return 0.0;
+        }
+        let errors = self.recent_results.iter().filter(|ok| !**ok).count();
+        errors as f64 / self.recent_results.len() as f64
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.reachable && self.slot_lag <= MAX_SLOT_LAG && self.error_rate() <= MAX_ERROR_RATE
+    }
+}
+
+pub struct RpcEndpoint {
+    url: String,
+    client: RpcClient,
+    async_client: AsyncRpcClient,
+    limiter: TokenBucket,
+    stats: Mutex<EndpointStats>,
+}
+
+impl RpcEndpoint {
+    fn new(url: String, requests_per_second: f64) -> Self {
+        Self {
+            client: RpcClient::new(url.clone()),
+            async_client: AsyncRpcClient::new(url.clone()),
+            url,
+            limiter: TokenBucket::new(requests_per_second, requests_per_second.ceil() as u32),
+            stats: Mutex::new(EndpointStats {
+                //optimistic until the first health check says otherwise, so startup isn't blocked on it
+                reachable: true,
+                slot: 0,
+                slot_lag: 0,
+                latency: Duration::ZERO,
+                recent_results: VecDeque::with_capacity(ERROR_RATE_WINDOW),
+                last_error: None,
+            }),
+        }
+    }
+
+    pub fn async_client(&self) -> &AsyncRpcClient {
+        &self.async_client
+    }
+
+    fn record<T>(&self, result: &ClientResult<T>) {
+        let mut stats = self.stats.lock().unwrap();
+        if stats.recent_results.len() == ERROR_RATE_WINDOW {
+            stats.recent_results.pop_front();
+        }
+        stats.recent_results.push_back(result.is_ok());
+        if let Err(err) = result {
+            stats.last_error = Some(err.to_string());
+        }
+    }
+
+    fn health(&self) -> EndpointHealth {
+        let stats = self.stats.lock().unwrap();
+        EndpointHealth {
+            url: self.url.clone(),
+            healthy: stats.is_healthy(),
+            slot: stats.slot,
+            slot_lag: stats.slot_lag,
+            latency_ms: stats.latency.as_millis() as u64,
+            error_rate: stats.error_rate(),
+            last_error: stats.last_error.clone(),
+        }
+    }
+}
+
+//all rpc traffic goes through here so every call is rate limited per endpoint, fails over to the
+//next best endpoint and is retried with backoff while the errors look transient
+pub struct RpcPool {
+    endpoints: Vec<Arc<RpcEndpoint>>,
+    retry_policy: RetryPolicy,
+    method_stats: Mutex<BTreeMap<&'static str, MethodStats>>,
+}
+
+impl RpcPool {
+    pub fn new(urls: Vec<String>, requests_per_second: f64, retry_policy: RetryPolicy) -> Self {
+        assert!(!urls.is_empty(), "RpcPool needs at least one endpoint");
+        Self {
+            endpoints: urls.into_iter().map(|url| Arc::new(RpcEndpoint::new(url, requests_per_second))).collect(),
+            retry_policy,
+            method_stats: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    //healthy endpoints first, fastest first, unhealthy ones are only kept as a last resort
+    pub fn ranked_endpoints(&self) -> Vec<Arc<RpcEndpoint>> {
+        let mut ranked: Vec<(bool, Duration, usize)> = self.endpoints.iter().enumerate()
+            .map(|(index, endpoint)| {
+                let stats = endpoint.stats.lock().unwrap();
+                (!stats.is_healthy(), stats.latency, index)
+            })
+            .collect();
+        ranked.sort();
+        ranked.into_iter().map(|(_, _, index)| self.endpoints[index].clone()).collect()
+    }
+
+    pub fn primary(&self) -> Arc<RpcEndpoint> {
+        self.ranked_endpoints().remove(0)
+    }
+
+    pub fn primary_url(&self) -> String {
+        self.primary().url.clone()
+    }
+
+    //method is the rpc method name, it only labels the request counters
+    pub async fn call<T, F, Fut>(&self, method: &'static str, f: F) -> ClientResult<T>
+    where
+        F: Fn(Arc<RpcEndpoint>) -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let mut last_result = None;
+            for endpoint in self.ranked_endpoints() {
+                endpoint.limiter.acquire_async().await;
+                let result = f(endpoint.clone()).await;
+                endpoint.record(&result);
+                if !self.should_retry(method, &endpoint, &result, attempt) {
+                    return result;
+                }
+                last_result = Some(result);
+            }
+            if attempt >= self.retry_policy.max_retries {
+                self.record_failure(method);
+                return last_result.unwrap();
+            }
+            tokio::time::sleep(self.retry_policy.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    //counts the request and says whether the error is worth another endpoint or another round
+    fn should_retry<T>(&self, method: &'static str, endpoint: &RpcEndpoint, result: &ClientResult<T>, attempt: u32) -> bool {
+        let mut method_stats = self.method_stats.lock().unwrap();
+        let stats = method_stats.entry(method).or_insert_with(|| MethodStats {
+            method: method.to_string(),
+            ..MethodStats::default()
+        });
+        stats.requests += 1;
+        if attempt > 0 {
+            stats.retries += 1;
+        }
+
+        match result {
+            Err(err) if ErrorClass::of(err) == ErrorClass::Retryable => {
+                eprintln!("{} to {} failed, trying again: {}", method, endpoint.url, err);
+                true
+            }
+            Err(_) => {
+                stats.failures += 1;
+                false
+            }
+            Ok(_) => false,
+        }
+    }
+
+    fn record_failure(&self, method: &'static str) {
+        if let Some(stats) = self.method_stats.lock().unwrap().get_mut(method) {
+            stats.failures += 1;
+        }
+    }
+
+    pub fn check_health(&self) {
+        let mut slots = Vec::with_capacity(self.endpoints.len());
+        for endpoint in &self.endpoints {
+            endpoint.limiter.acquire();
+            let started = Instant::now();
+            let result = endpoint.client.get_slot_with_commitment(CommitmentConfig::processed());
+            let latency = started.elapsed();
+            endpoint.record(&result);
+
+            let mut stats = endpoint.stats.lock().unwrap();
+            stats.reachable = result.is_ok();
+            stats.latency = latency;
+            if let Ok(slot) = result {
+                stats.slot = slot;
+                slots.push(slot);
+            }
+        }
+
+        //lag is measured against the most advanced endpoint in the pool
+        let max_slot = slots.into_iter().max().unwrap_or(0);
+        for endpoint in &self.endpoints {
+            let mut stats = endpoint.stats.lock().unwrap();
+            stats.slot_lag = max_slot.saturating_sub(stats.slot);
+        }
+    }
+
+    pub fn start_health_checks(self: &Arc<Self>) {
+        let pool = Arc::downgrade(self);
+        thread::spawn(move || loop {
+            match pool.upgrade() {
+                Some(pool) => pool.check_health(),
+                None => return,
+            }
+            thread::sleep(HEALTH_CHECK_INTERVAL);
+        });
+    }
+
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        self.endpoints.iter().map(|endpoint| endpoint.health()).collect()
+    }
+
+    pub fn method_stats(&self) -> Vec<MethodStats> {
+        self.method_stats.lock().unwrap().values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use solana_client::client_error::{ClientError, ClientErrorKind};
+    use solana_client::rpc_request::{RpcError, RpcResponseErrorData};
+
+    use super::*;
+
+    //endpoints that are never dialled, every call below hands them a closure standing in for the rpc
+    fn pool(urls: &[&str], max_retries: u32) -> RpcPool {
+        let retry_policy = RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+        };
+        RpcPool::new(urls.iter().map(|url| url.to_string()).collect(), 1000.0, retry_policy)
+    }
+
+    fn set_stats(pool: &RpcPool, index: usize, reachable: bool, latency_ms: u64, slot_lag: u64) {
+        let mut stats = pool.endpoints[index].stats.lock().unwrap();
+        stats.reachable = reachable;
+        stats.latency = Duration::from_millis(latency_ms);
+        stats.slot_lag = slot_lag;
+    }
+
+    fn ranked_urls(pool: &RpcPool) -> Vec<String> {
+        pool.ranked_endpoints().iter().map(|endpoint| endpoint.url.clone()).collect()
+    }
+
+    fn retryable() -> ClientError {
+        ClientErrorKind::Io(io::Error::new(io::ErrorKind::ConnectionReset, "connection reset")).into()
+    }
+
+    fn fatal() -> ClientError {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            code: -32602,
+            message: "invalid params".to_string(),
+            data: RpcResponseErrorData::Empty,
+        }).into()
+    }
+
+    #[test]
+    fn healthy_endpoints_rank_first_then_the_fastest() {
+        let pool = pool(&["http://a", "http://b", "http://c"], 0);
+        set_stats(&pool, 0, true, 50, 0);
+        set_stats(&pool, 1, true, 10, 0);
+        set_stats(&pool, 2, false, 1, 0);
+        assert_eq!(ranked_urls(&pool), vec!["http://b", "http://a", "http://c"]);
+        assert_eq!(pool.primary_url(), "http://b");
+
+        //too far behind the rest of the pool
+        set_stats(&pool, 1, true, 10, MAX_SLOT_LAG + 1);
+        assert_eq!(ranked_urls(&pool), vec!["http://a", "http://c", "http://b"]);
+
+        //failing too many of its recent requests
+        set_stats(&pool, 1, true, 10, 0);
+        for i in 0..4 {
+            let result: ClientResult<()> = if i == 0 { Err(retryable()) } else { Ok(()) };
+            pool.endpoints[1].record(&result);
+        }
+        assert_eq!(pool.health()[1].error_rate, 0.25);
+        assert!(pool.health()[1].healthy);
+        pool.endpoints[1].record::<()>(&Err(retryable()));
+        assert!(!pool.health()[1].healthy);
+        assert_eq!(ranked_urls(&pool), vec!["http://a", "http://c", "http://b"]);
+    }
+
+    #[tokio::test]
+    async fn call_fails_over_when_the_first_endpoint_errors() {
+        let pool = pool(&["http://a", "http://b"], 0);
+        set_stats(&pool, 0, true, 10, 0);
+        set_stats(&pool, 1, true, 20, 0);
+
+        let result = pool.call("getSlot", |endpoint| async move {
+            if endpoint.url == "http://a" { Err(retryable()) } else { Ok(endpoint.url.clone()) }
+        }).await;
+        assert_eq!(result.unwrap(), "http://b");
+        assert_eq!(pool.health()[0].last_error.as_deref(), Some("connection reset"));
+
+        let stats = pool.method_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!((stats[0].method.as_str(), stats[0].requests, stats[0].retries, stats[0].failures), ("getSlot", 2, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn fatal_errors_are_not_retried() {
+        let pool = pool(&["http://a", "http://b"], 3);
+        let calls = AtomicUsize::new(0);
+
+        let result: ClientResult<()> = pool.call("sendTransaction", |_| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            async { Err(fatal()) }
+        }).await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        let stats = pool.method_stats();
+        assert_eq!((stats[0].requests, stats[0].retries, stats[0].failures), (1, 0, 1));
+    }
+
+    #[tokio::test]
+    async fn retryable_errors_go_round_the_pool_until_the_retries_run_out() {
+        let pool = pool(&["http://a", "http://b"], 2);
+        let calls = AtomicUsize::new(0);
+
+        let result: ClientResult<()> = pool.call("getAccountInfo", |_| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            async { Err(retryable()) }
+        }).await;
+        assert!(result.is_err());
+        //both endpoints, in the first round and each of the 2 retries
+        assert_eq!(calls.load(Ordering::Relaxed), 6);
+
+        //counted per method
+        pool.call("getSlot", |_| async { Ok(1u64) }).await.unwrap();
+        let stats: Vec<(String, u64, u64, u64)> = pool.method_stats().into_iter()
+            .map(|stats| (stats.method, stats.requests, stats.retries, stats.failures))
+            .collect();
+        assert_eq!(stats, vec![("getAccountInfo".to_string(), 6, 4, 1), ("getSlot".to_string(), 1, 0, 0)]);
+    }
+}
@@ -1,18 +1,17 @@
 use std::sync::{Arc, Mutex};
-use serum_dex::state::{Market, MarketState};
-use crate::serum::market_builder::MarketBuilder;
+use serum_dex::state::MarketState;
 use crate::serum::order_book::OrderBook;
 
-//make sure while that this struct is Send and sync
-pub struct MarketWrapper<'a> {
-    pub market: Arc<Mutex<Market<'a>>>,
+//holds a copy of the market state rather than a view into an account, so it owns everything it needs
+pub struct MarketWrapper {
+    pub market: Arc<Mutex<MarketState>>,
     pub base_decimals: i8,
     pub quote_decimals: i8,
     pub bid_order_book: OrderBook,
     pub ask_order_book: OrderBook,
 }
 
-impl MarketWrapper<'_> {
+impl MarketWrapper {
     pub fn reload(&mut self) {
         //todo update the order books here
         //self.market.lock().unwrap().load_bids_mut()
@@ -22,17 +21,14 @@ impl MarketWrapper<'_> {
     }
 }
 
-impl Clone for MarketWrapper<'_> {
+impl Clone for MarketWrapper {
     fn clone(&self) -> Self {
         Self {
-            market: self.market.clone().into(),
+            market: self.market.clone(),
             base_decimals: self.base_decimals,
             quote_decimals: self.quote_decimals,
             bid_order_book: self.bid_order_book.clone(),
             ask_order_book: self.ask_order_book.clone(),
         }
     }
-}
-
-unsafe impl Send for MarketWrapper<'_> {}
-unsafe impl Sync for MarketWrapper<'_> {}
\ No newline at end of file
+}
\ No newline at end of file
@@ -1,38 +1,41 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use std::thread::sleep;
-use std::time::Duration;
-use std::borrow::BorrowMut;
-use bytemuck::cast;
-use serum_dex::critbit::SlabView;
+use serum_dex::matching::Side;
 use serum_dex::state::{Market, MarketState};
-use solana_account_decoder::UiAccountEncoding;
-use solana_client::rpc_client::RpcClient;
-use solana_client::rpc_config::RpcAccountInfoConfig;
-use solana_program::account_info::{AccountInfo, IntoAccountInfo};
+use solana_program::account_info::IntoAccountInfo;
 use solana_program::pubkey::Pubkey;
-use solana_sdk::account::Account;
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
 use solana_sdk::commitment_config::CommitmentConfig;
+use crate::rpc::chain_client::ChainClient;
 use crate::serum::market::MarketWrapper;
 use crate::serum::order_book::OrderBook;
 use crate::serum::serum_utils;
-use crate::serum::serum_utils::{pub_key, SerumUtils};
+use crate::serum::serum_utils::{BASE_MINT_OFFSET, MARKET_ACCOUNT_SIZE, OWN_ADDRESS_OFFSET, pub_key, QUOTE_MINT_OFFSET, SERUM_PROGRAM_ID_V3, SerumUtils};
+
+//enough of the market account to read its own address and both mints
+const MARKET_LISTING_SLICE_LENGTH: usize = QUOTE_MINT_OFFSET + 32;
+
+pub struct MarketListing {
+    pub market_id: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+}
 
 pub struct MarketBuilder {
-    client: RpcClient,
+    client: Arc<dyn ChainClient>,
     market_id: Pubkey,
-    min_context_slot: u64,
     //built: bool,
     //base64_account_info: Option<Arc<Mutex<AccountInfo<'a>>>>,
     decimals_cache: HashMap<Pubkey, i8>,
 }
 
 impl MarketBuilder {
-    pub fn new(client: RpcClient, public_key: Pubkey) -> Self {
+    pub fn new(client: Arc<dyn ChainClient>, public_key: Pubkey) -> Self {
         MarketBuilder {
             client,
             market_id: public_key,
-            min_context_slot: 0,
             //built: false,
             //base64_account_info: None,
             decimals_cache: HashMap::new(),
@@ -40,107 +43,218 @@ impl MarketBuilder {
     }
 
 
-    pub fn build(&mut self) -> MarketWrapper {
-        let acc = self.client.get_account_with_config(&self.market_id, RpcAccountInfoConfig {
-            encoding: None,
-            data_slice: None,
-            commitment: Some(CommitmentConfig::processed()),
-            min_context_slot: Some(self.min_context_slot, )
-        })
-            .expect("Error occurred while getting account info")
-            .value;
-
-        let account_info = (self.market_id, acc.unwrap()).into_account_info();
-
-        let market = Market::load(account_info.as_ref(), &self.market_id, true)
-            .expect("Error loading market");
+    pub async fn build(&mut self) -> Result<MarketWrapper, Box<dyn std::error::Error>> {
+        let acc = self.client.get_account(&self.market_id, CommitmentConfig::processed()).await?
+            .ok_or_else(|| format!("market {} not found", self.market_id))?;
 
+        //copied out of the account so the wrapper doesn't borrow anything local. AccountInfo isn't Send,
+        //so it can't live past this block into the awaits below
+        let market: MarketState = {
+            let mut keyed_account = (self.market_id, acc);
+            let account_info = (&mut keyed_account).into_account_info();
+            let market = *Market::load(&account_info, &SERUM_PROGRAM_ID_V3, true)
+                .map_err(|e| format!("Error loading market {}: {:?}", self.market_id, e))?;
+            market
+        };
 
         let base_mint = pub_key(market.coin_mint);
         let quote_mint = pub_key(market.pc_mint);
 
-        let mut base_decimals: i8 = 0;
-        let mut quote_decimals: i8 = 0;
-
-        if self.decimals_cache.contains_key(&base_mint) {
-            base_decimals = *self.decimals_cache.get(&base_mint).unwrap();
-        } else {
-            base_decimals = self.get_mint_decimals(&base_mint);
-            self.decimals_cache.insert(base_mint, base_decimals);
-        }
-
-        if self.decimals_cache.contains_key(&quote_mint) {
-            quote_decimals = *self.decimals_cache.get(&quote_mint).unwrap();
-        } else {
-            quote_decimals = self.get_mint_decimals(&quote_mint);
-            self.decimals_cache.insert(quote_mint, quote_decimals);
-        }
+        let base_decimals = self.cached_mint_decimals(&base_mint).await?;
+        let quote_decimals = self.cached_mint_decimals(&quote_mint).await?;
 
         let bids_pubkey = pub_key(market.bids);
-        let bid_acc = self.client.get_account_with_commitment(&bids_pubkey, CommitmentConfig::processed()).unwrap().value.unwrap();
+        let bid_acc = self.client.get_account(&bids_pubkey, CommitmentConfig::processed()).await?
+            .ok_or_else(|| format!("bids account {} not found", bids_pubkey))?;
         let asks_pubkey = pub_key(market.asks);
-        let ask_acc = self.client.get_account_with_commitment(&asks_pubkey, CommitmentConfig::processed()).unwrap().value.unwrap();
-        let mut x = (bids_pubkey, bid_acc);
-        let bids_info = x.into_account_info();
-        let mut x1 = (asks_pubkey, ask_acc);
-        let asks_info = x1.into_account_info();
-
-        let bid_orders = market.load_bids_mut(
-            &bids_info
-        ).unwrap();
-        let ask_orders = market.load_asks_mut(
-            &asks_info
-        ).unwrap();
+        let ask_acc = self.client.get_account(&asks_pubkey, CommitmentConfig::processed()).await?
+            .ok_or_else(|| format!("asks account {} not found", asks_pubkey))?;
+        let bid_order_book = OrderBook::from_account(&market, Side::Bid, bids_pubkey, bid_acc, base_decimals, quote_decimals)?;
+        let ask_order_book = OrderBook::from_account(&market, Side::Ask, asks_pubkey, ask_acc, base_decimals, quote_decimals)?;
 
         //self.built = true;
-        MarketWrapper {
+        Ok(MarketWrapper {
             base_decimals,
             quote_decimals,
-            bid_order_book: OrderBook::new(bid_orders, base_decimals, quote_decimals, market.coin_lot_size, market.pc_lot_size),
-            ask_order_book: OrderBook::new(ask_orders, base_decimals, quote_decimals, market.coin_lot_size, market.pc_lot_size),
+            bid_order_book,
+            ask_order_book,
             market: Arc::new(Mutex::new(market)),
+        })
+    }
+
+    //a mint's decimals never change, each is only fetched once per builder
+    async fn cached_mint_decimals(&mut self, token_mint: &Pubkey) -> Result<i8, Box<dyn std::error::Error>> {
+        if let Some(decimals) = self.decimals_cache.get(token_mint) {
+            return Ok(*decimals);
         }
+        let decimals = self.get_mint_decimals(token_mint).await?;
+        self.decimals_cache.insert(*token_mint, decimals);
+        Ok(decimals)
     }
 
-    fn get_mint_decimals(&self, token_mint: &Pubkey) -> i8 {
+    async fn get_mint_decimals(&self, token_mint: &Pubkey) -> Result<i8, Box<dyn std::error::Error>> {
         if token_mint == &*serum_utils::WRAPPED_SOL_MINT {
-            return 9;
+            return Ok(9);
         }
 
         // USDC and USDT cases
         if token_mint == &*serum_utils::USDC_MINT || token_mint == &*serum_utils::USDT_MINT {
-            return 6;
+            return Ok(6);
         }
 
-        // Sleep for 100ms to avoid rate limit
-        sleep(Duration::from_millis(100));
-
-        // RPC call to get mint's account data into decoded bytes (already base64 decoded)
-        let account_data = self.retrieve_account_data_confirmed(&token_mint);
-        if account_data.is_none() {
-            panic!("retrieve_account_data_confirmed function failed");
-        }
+        // RPC call to get mint's account data into decoded bytes (already base64 decoded), rate limited by the pool
+        let account_data = self.retrieve_account_data_confirmed(token_mint).await?;
 
         // Deserialize account_data into the MINT_LAYOUT enum
-        let decimals = SerumUtils::read_decimals_from_token_mint_data(&account_data.unwrap());
+        let decimals = SerumUtils::read_decimals_from_token_mint_data(&account_data);
+
+        Ok(decimals)
+    }
 
-        decimals
+    async fn retrieve_account_data_confirmed(&self, public_key: &Pubkey) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let account = self.client.get_account(public_key, CommitmentConfig::confirmed()).await?
+            .ok_or_else(|| format!("account {} not found", public_key))?;
+
+        Ok(account.data)
     }
+}
 
-    fn retrieve_account_data_confirmed(&self, public_key: &Pubkey) -> Option<Vec<u8>> {
-        let config = RpcAccountInfoConfig {
-            encoding: Some(UiAccountEncoding::Binary),
-            data_slice: None,
+//every market on the dex, optionally only the ones quoted in quote_mint. Only the head of each market
+//account is fetched, the full list is a few thousand accounts
+pub async fn list_markets(chain_client: &dyn ChainClient, quote_mint: Option<Pubkey>) -> Result<Vec<MarketListing>, Box<dyn std::error::Error>> {
+    let mut filters = vec![RpcFilterType::DataSize(MARKET_ACCOUNT_SIZE as u64)];
+    if let Some(quote_mint) = quote_mint {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(QUOTE_MINT_OFFSET, quote_mint.as_ref())));
+    }
+    let config = RpcProgramAccountsConfig {
+        filters: Some(filters),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: Some(UiDataSliceConfig { offset: 0, length: MARKET_LISTING_SLICE_LENGTH }),
             commitment: Some(CommitmentConfig::confirmed()),
-            min_context_slot: None,
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+
+    let read_pubkey = |data: &[u8], offset: usize| {
+        data.get(offset..offset + 32).map(|bytes| Pubkey::try_from(bytes).unwrap())
+    };
+    let accounts = chain_client.get_program_accounts(&SERUM_PROGRAM_ID_V3, config).await?;
+    Ok(accounts.into_iter()
+        .filter_map(|(_, account)| Some(MarketListing {
+            market_id: read_pubkey(&account.data, OWN_ADDRESS_OFFSET)?,
+            base_mint: read_pubkey(&account.data, BASE_MINT_OFFSET)?,
+            quote_mint: read_pubkey(&account.data, QUOTE_MINT_OFFSET)?,
+        }))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use bytemuck::cast;
+
+    use crate::rpc::mock_chain_client::MockChainClient;
+    use crate::serum::market_fixture::{MarketFixture, BASE_LOT_SIZE, QUOTE_LOT_SIZE};
+    use crate::serum::serum_utils::{USDC_MINT, USDT_MINT};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn build_loads_decimals_and_both_books() {
+        let chain_client = Arc::new(MockChainClient::new());
+        let fixture = MarketFixture::new();
+        fixture.install(&chain_client, &[(10_000, 1_000), (10_050, 2_000)], &[(10_100, 500), (10_200, 1_500)]);
+
+        let market = MarketBuilder::new(chain_client, fixture.market_id).build().await.unwrap();
+        assert_eq!(market.base_decimals, 9);
+        assert_eq!(market.quote_decimals, 6);
+        let (base_lot_size, quote_lot_size) = {
+            let state = market.market.lock().unwrap();
+            (state.coin_lot_size, state.pc_lot_size)
         };
-        let account_info_result = self.client.get_account_with_config(&public_key, config);
+        assert_eq!(base_lot_size, BASE_LOT_SIZE);
+        assert_eq!(quote_lot_size, QUOTE_LOT_SIZE);
 
-        if account_info_result.is_err() {
-            eprintln!("{:?}", account_info_result.err().unwrap());
-            return None;
-        }
+        assert_eq!(market.bid_order_book.get_best_bid_price(), 100.5);
+        assert_eq!(market.bid_order_book.levels(), vec![(100.5, 2.0), (100.0, 1.0)]);
+        assert_eq!(market.ask_order_book.get_best_ask_price(), 101.0);
+        assert_eq!(market.ask_order_book.levels(), vec![(101.0, 0.5), (102.0, 1.5)]);
+        assert_eq!(market.bid_order_book.get_orders().len(), 2);
+    }
 
-        Some(account_info_result.unwrap().value.unwrap().data)
+    #[tokio::test]
+    async fn empty_books_have_no_best_price() {
+        let chain_client = Arc::new(MockChainClient::new());
+        let fixture = MarketFixture::new();
+        fixture.install(&chain_client, &[], &[]);
+
+        let market = MarketBuilder::new(chain_client, fixture.market_id).build().await.unwrap();
+        assert!(market.bid_order_book.get_orders().is_empty());
+        assert_eq!(market.bid_order_book.get_best_bid_price(), 0.0);
+        assert!(market.ask_order_book.levels().is_empty());
+    }
+
+    #[tokio::test]
+    async fn mint_decimals_are_cached_between_builds() {
+        let chain_client = Arc::new(MockChainClient::new());
+        let fixture = MarketFixture::new();
+        fixture.install(&chain_client, &[], &[]);
+
+        let mut builder = MarketBuilder::new(chain_client.clone(), fixture.market_id);
+        builder.build().await.unwrap();
+        chain_client.remove_account(&fixture.base_mint);
+        assert_eq!(builder.build().await.unwrap().base_decimals, 9);
+
+        //a fresh builder has to fetch the mint again
+        assert!(MarketBuilder::new(chain_client, fixture.market_id).build().await.is_err());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn missing_accounts_are_errors() {
+        let chain_client = Arc::new(MockChainClient::new());
+        let fixture = MarketFixture::new();
+
+        let error = MarketBuilder::new(chain_client.clone(), fixture.market_id).build().await.err().unwrap();
+        assert!(error.to_string().contains("not found"), "{}", error);
+
+        fixture.install(&chain_client, &[], &[]);
+        chain_client.remove_account(&fixture.asks);
+        let error = MarketBuilder::new(chain_client, fixture.market_id).build().await.err().unwrap();
+        assert!(error.to_string().contains(&fixture.asks.to_string()), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn account_owned_by_another_program_is_not_a_market() {
+        let chain_client = Arc::new(MockChainClient::new());
+        let fixture = MarketFixture::new();
+        fixture.install(&chain_client, &[], &[]);
+        let mut account = fixture.market_account();
+        account.owner = Pubkey::new_unique();
+        chain_client.set_account(fixture.market_id, account);
+
+        assert!(MarketBuilder::new(chain_client, fixture.market_id).build().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn list_markets_filters_on_the_quote_mint() {
+        let chain_client = MockChainClient::new();
+        let usdc_market = MarketFixture::new();
+        usdc_market.install(&chain_client, &[], &[]);
+        let mut usdt_market = MarketFixture::new();
+        usdt_market.state.pc_mint = cast(USDT_MINT.to_bytes());
+        usdt_market.install(&chain_client, &[], &[]);
+
+        let mut all: Vec<Pubkey> = list_markets(&chain_client, None).await.unwrap().iter().map(|listing| listing.market_id).collect();
+        all.sort();
+        let mut expected = vec![usdc_market.market_id, usdt_market.market_id];
+        expected.sort();
+        assert_eq!(all, expected);
+
+        let usdc = list_markets(&chain_client, Some(*USDC_MINT)).await.unwrap();
+        assert_eq!(usdc.len(), 1);
+        assert_eq!(usdc[0].market_id, usdc_market.market_id);
+        assert_eq!(usdc[0].base_mint, usdc_market.base_mint);
+        assert_eq!(usdc[0].quote_mint, *USDC_MINT);
+    }
+}
@@ -0,0 +1,338 @@
+use std::mem::size_of;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream::{select_all, StreamExt};
+use serum_dex::matching::Side;
+use serum_dex::state::{Event, EventView, MarketState, OpenOrders};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_sdk::account::Account;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::{Instant, sleep};
+
+use crate::rpc::chain_client::ChainClient;
+use crate::serum::market::MarketWrapper;
+use crate::serum::order_book::OrderBook;
+use crate::serum::serum_utils::pub_key;
+
+//while the socket is down the same accounts are polled at this rate instead
+const POLL_FALLBACK_INTERVAL: Duration = Duration::from_millis(1000);
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+//"serum" in front of every dex account
+const ACCOUNT_HEAD_PADDING: usize = 5;
+//account_flags, head, count and seq_num, the events follow
+const EVENT_QUEUE_HEADER_SIZE: usize = 32;
+//"padding" after every dex account
+const ACCOUNT_TAIL_PADDING: usize = 7;
+
+#[derive(Clone, Debug)]
+pub struct OpenOrder {
+    pub order_id: u128,
+    pub client_order_id: u64,
+    pub side: Side,
+}
+
+//what the bot's open orders account holds, amounts are native (not lots, not decimal adjusted)
+#[derive(Clone, Debug)]
+pub struct OpenOrdersSnapshot {
+    pub base_free: u64,
+    pub base_total: u64,
+    pub quote_free: u64,
+    pub quote_total: u64,
+    pub orders: Vec<OpenOrder>,
+}
+
+impl OpenOrdersSnapshot {
+    pub fn from_account_data(data: &[u8]) -> Result<Self, String> {
+        let end = ACCOUNT_HEAD_PADDING + size_of::<OpenOrders>();
+        if data.len() < end {
+            return Err(format!("open orders account is {} bytes, expected at least {}", data.len(), end));
+        }
+        let open_orders: OpenOrders = bytemuck::pod_read_unaligned(&data[ACCOUNT_HEAD_PADDING..end]);
+
+        let free_slot_bits = open_orders.free_slot_bits;
+        let is_bid_bits = open_orders.is_bid_bits;
+        let order_ids = open_orders.orders;
+        let client_order_ids = open_orders.client_order_ids;
+        let orders = (0..order_ids.len())
+            .filter(|slot| free_slot_bits & (1u128 << slot) == 0)
+            .map(|slot| OpenOrder {
+                order_id: order_ids[slot],
+                client_order_id: client_order_ids[slot],
+                side: if is_bid_bits & (1u128 << slot) != 0 { Side::Bid } else { Side::Ask },
+            })
+            .collect();
+
+        Ok(Self {
+            base_free: open_orders.native_coin_free,
+            base_total: open_orders.native_coin_total,
+            quote_free: open_orders.native_pc_free,
+            quote_total: open_orders.native_pc_total,
+            orders,
+        })
+    }
+}
+
+//a fill of one of the bot's orders, seq_num orders the events and stays with one across updates
+#[derive(Clone, Debug)]
+pub struct Fill {
+    pub seq_num: u64,
+    pub order_id: u128,
+    pub client_order_id: u64,
+    pub side: Side,
+    //base, decimal adjusted
+    pub quantity: f64,
+}
+
+//what the event queue holds, only the fills that belong to the given open orders account are picked out
+pub struct EventQueueSnapshot {
+    pub fills: Vec<Fill>,
+}
+
+impl EventQueueSnapshot {
+    pub fn from_account_data(data: &[u8], open_orders: &Pubkey, base_decimals: i8) -> Result<Self, String> {
+        let end = data.len().saturating_sub(ACCOUNT_TAIL_PADDING);
+        if end < ACCOUNT_HEAD_PADDING + EVENT_QUEUE_HEADER_SIZE {
+            return Err(format!("event queue is {} bytes, too short for its header", data.len()));
+        }
+        let body = &data[ACCOUNT_HEAD_PADDING..end];
+        let [_, head, count, seq_num]: [u64; 4] = bytemuck::pod_read_unaligned(&body[..EVENT_QUEUE_HEADER_SIZE]);
+        let events = &body[EVENT_QUEUE_HEADER_SIZE..];
+        let capacity = (events.len() / size_of::<Event>()) as u64;
+        if capacity == 0 || count > capacity {
+            return Err(format!("event queue holds {} events in room for {}", count, capacity));
+        }
+
+        let mut fills = Vec::new();
+        for index in 0..count {
+            let slot = ((head + index) % capacity) as usize * size_of::<Event>();
+            let event: Event = bytemuck::pod_read_unaligned(&events[slot..slot + size_of::<Event>()]);
+            if pub_key(event.owner) != *open_orders {
+                continue;
+            }
+            if let Ok(EventView::Fill { side, native_qty_paid, native_qty_received, order_id, client_order_id, .. }) = event.as_view() {
+                //a bid is paid in quote and receives base, an ask the other way round
+                let native_base = match side {
+                    Side::Bid => native_qty_received,
+                    Side::Ask => native_qty_paid,
+                };
+                fills.push(Fill {
+                    seq_num: seq_num.wrapping_sub(count - index),
+                    order_id,
+                    client_order_id: client_order_id.map(|id| id.get()).unwrap_or(0),
+                    side,
+                    quantity: native_base as f64 / 10f64.powi(base_decimals as i32),
+                });
+            }
+        }
+        Ok(Self { fills })
+    }
+}
+
+pub enum MarketUpdate {
+    Bids(OrderBook),
+    Asks(OrderBook),
+    //events waiting to be cranked, a change here usually means one of our orders was filled
+    EventQueue(EventQueueSnapshot),
+    OpenOrders(OpenOrdersSnapshot),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum FeedAccount {
+    Bids,
+    Asks,
+    EventQueue,
+    OpenOrders,
+}
+
+//streams the accounts a market maker reacts to over accountSubscribe, reconnecting on its own and
+//polling the same accounts through the chain client whenever the socket is down
+pub struct MarketFeed {
+    updates: UnboundedReceiver<MarketUpdate>,
+    worker: Option<MarketFeedWorker>,
+}
+
+impl MarketFeed {
+    pub fn new(ws_url: String, chain_client: Arc<dyn ChainClient>, market: &MarketWrapper, open_orders: Pubkey) -> Self {
+        let (update_sender, update_receiver) = unbounded_channel();
+        let market_state = *market.market.lock().unwrap();
+
+        let accounts = vec![
+            (pub_key(market_state.bids), FeedAccount::Bids),
+            (pub_key(market_state.asks), FeedAccount::Asks),
+            (pub_key(market_state.event_q), FeedAccount::EventQueue),
+            (open_orders, FeedAccount::OpenOrders),
+        ];
+
+        Self {
+            updates: update_receiver,
+            worker: Some(MarketFeedWorker {
+                ws_url,
+                chain_client,
+                market: market_state,
+                base_decimals: market.base_decimals,
+                quote_decimals: market.quote_decimals,
+                accounts,
+                open_orders,
+                updates: update_sender,
+            }),
+        }
+    }
+
+    //spawns the worker onto the runtime the caller is running on
+    pub fn start(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            tokio::spawn(worker.run());
+        }
+    }
+
+    pub async fn next_update(&mut self) -> Option<MarketUpdate> {
+        self.updates.recv().await
+    }
+
+    pub fn try_next_update(&mut self) -> Option<MarketUpdate> {
+        self.updates.try_recv().ok()
+    }
+}
+
+struct MarketFeedWorker {
+    ws_url: String,
+    chain_client: Arc<dyn ChainClient>,
+    market: MarketState,
+    base_decimals: i8,
+    quote_decimals: i8,
+    accounts: Vec<(Pubkey, FeedAccount)>,
+    open_orders: Pubkey,
+    updates: UnboundedSender<MarketUpdate>,
+}
+
+impl MarketFeedWorker {
+    async fn run(self) {
+        let mut reconnect_delay = RECONNECT_BASE_DELAY;
+        loop {
+            match PubsubClient::new(&self.ws_url).await {
+                Ok(client) => {
+                    if self.stream(&client).await {
+                        reconnect_delay = RECONNECT_BASE_DELAY;
+                    }
+                    let _ = client.shutdown().await;
+                }
+                Err(err) => eprintln!("Market feed could not connect to {}: {}", self.ws_url, err),
+            }
+
+            if self.updates.is_closed() {
+                return;
+            }
+
+            eprintln!("Market feed disconnected, polling until reconnecting in {:?}", reconnect_delay);
+            let reconnect_at = Instant::now() + reconnect_delay;
+            while Instant::now() < reconnect_at {
+                if !self.poll().await {
+                    return;
+                }
+                sleep(POLL_FALLBACK_INTERVAL).await;
+            }
+            reconnect_delay = (reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
+        }
+    }
+
+    //returns true if every subscription was set up, i.e. the connection was actually usable
+    async fn stream(&self, client: &PubsubClient) -> bool {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(CommitmentConfig::processed()),
+            ..RpcAccountInfoConfig::default()
+        };
+
+        let mut streams = Vec::with_capacity(self.accounts.len());
+        for (pubkey, kind) in &self.accounts {
+            match client.account_subscribe(pubkey, Some(config.clone())).await {
+                Ok((stream, _unsubscribe)) => {
+                    let (pubkey, kind) = (*pubkey, *kind);
+                    streams.push(stream.map(move |response| (pubkey, kind, response.value)));
+                }
+                Err(err) => {
+                    eprintln!("Market feed could not subscribe to {}: {}", pubkey, err);
+                    return false;
+                }
+            }
+        }
+
+        println!("Market feed subscribed to {} accounts on {}", self.accounts.len(), self.ws_url);
+
+        //anything that changed before the subscriptions were live would otherwise be missed
+        if !self.poll().await {
+            return true;
+        }
+
+        let mut updates = select_all(streams);
+        while let Some((pubkey, kind, ui_account)) = updates.next().await {
+            match ui_account.decode::<Account>() {
+                Some(account) => {
+                    if !self.publish(pubkey, kind, account) {
+                        return true;
+                    }
+                }
+                None => eprintln!("Market feed could not decode {:?} account {}", kind, pubkey),
+            }
+        }
+        true
+    }
+
+    //returns false once nobody is listening anymore
+    async fn poll(&self) -> bool {
+        let pubkeys: Vec<Pubkey> = self.accounts.iter().map(|(pubkey, _)| *pubkey).collect();
+        let accounts = match self.chain_client.get_multiple_accounts(&pubkeys, CommitmentConfig::processed()).await {
+            Ok(accounts) => accounts,
+            Err(err) => {
+                eprintln!("Market feed poll failed: {}", err);
+                return !self.updates.is_closed();
+            }
+        };
+
+        for ((pubkey, kind), account) in self.accounts.iter().zip(accounts) {
+            if let Some(account) = account {
+                if !self.publish(*pubkey, *kind, account) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn publish(&self, pubkey: Pubkey, kind: FeedAccount, account: Account) -> bool {
+        let update = match kind {
+            FeedAccount::Bids | FeedAccount::Asks => {
+                let side = if let FeedAccount::Bids = kind { Side::Bid } else { Side::Ask };
+                match OrderBook::from_account(&self.market, side, pubkey, account, self.base_decimals, self.quote_decimals) {
+                    Ok(order_book) if side == Side::Bid => MarketUpdate::Bids(order_book),
+                    Ok(order_book) => MarketUpdate::Asks(order_book),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        return true;
+                    }
+                }
+            }
+            FeedAccount::EventQueue => match EventQueueSnapshot::from_account_data(&account.data, &self.open_orders, self.base_decimals) {
+                Ok(snapshot) => MarketUpdate::EventQueue(snapshot),
+                Err(err) => {
+                    eprintln!("Error decoding event queue {}: {}", pubkey, err);
+                    return true;
+                }
+            },
+            FeedAccount::OpenOrders => match OpenOrdersSnapshot::from_account_data(&account.data) {
+                Ok(snapshot) => MarketUpdate::OpenOrders(snapshot),
+                Err(err) => {
+                    eprintln!("Error decoding open orders {}: {}", pubkey, err);
+                    return true;
+                }
+            },
+        };
+        self.updates.send(update).is_ok()
+    }
+}
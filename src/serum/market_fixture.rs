@@ -0,0 +1,148 @@
+use std::mem::size_of;
+
+use bytemuck::{bytes_of, cast, Zeroable};
+use serum_dex::critbit::{LeafNode, Slab};
+use serum_dex::matching::Side;
+use serum_dex::state::{AccountFlag, EventQueueHeader, MarketState, OpenOrders};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::rpc::mock_chain_client::MockChainClient;
+use crate::serum::serum_utils::{SERUM_PROGRAM_ID_V3, TOKEN_PROGRAM_ID, TOKEN_MINT_DECIMALS_OFFSET, USDC_MINT};
+
+pub const BASE_DECIMALS: u8 = 9;
+//with 9 base and 6 quote decimals these make one price lot 0.01 and one size lot 0.001
+pub const BASE_LOT_SIZE: u64 = 1_000_000;
+pub const QUOTE_LOT_SIZE: u64 = 10;
+const SLAB_NODES: usize = 64;
+const SLAB_NODE_SIZE: usize = 72;
+const SLAB_HEADER_SIZE: usize = 32;
+const EVENT_QUEUE_CAPACITY: usize = 16;
+const EVENT_SIZE: usize = 88;
+const MINT_ACCOUNT_SIZE: usize = 82;
+
+//a serum market that only exists in memory: the market account, both books, an empty event queue
+//and a base mint, installed into a MockChainClient
+pub struct MarketFixture {
+    pub market_id: Pubkey,
+    pub base_mint: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub event_queue: Pubkey,
+    pub state: MarketState,
+}
+
+impl MarketFixture {
+    pub fn new() -> Self {
+        let base_mint = Pubkey::new_unique();
+        let bids = Pubkey::new_unique();
+        let asks = Pubkey::new_unique();
+        let event_queue = Pubkey::new_unique();
+
+        //get_vault_signer expects the stored nonce to derive an off-curve address, not every market id has one at 0
+        let (market_id, vault_signer_nonce) = loop {
+            let market_id = Pubkey::new_unique();
+            let nonce = (0..256u64).find(|nonce| {
+                Pubkey::create_program_address(&[market_id.as_ref(), &nonce.to_le_bytes()], &SERUM_PROGRAM_ID_V3).is_ok()
+            });
+            if let Some(nonce) = nonce {
+                break (market_id, nonce);
+            }
+        };
+
+        let mut state = MarketState::zeroed();
+        state.account_flags = (AccountFlag::Initialized | AccountFlag::Market).bits();
+        state.own_address = cast(market_id.to_bytes());
+        state.vault_signer_nonce = vault_signer_nonce;
+        state.coin_mint = cast(base_mint.to_bytes());
+        state.pc_mint = cast(USDC_MINT.to_bytes());
+        state.coin_vault = cast(Pubkey::new_unique().to_bytes());
+        state.pc_vault = cast(Pubkey::new_unique().to_bytes());
+        state.req_q = cast(Pubkey::new_unique().to_bytes());
+        state.event_q = cast(event_queue.to_bytes());
+        state.bids = cast(bids.to_bytes());
+        state.asks = cast(asks.to_bytes());
+        state.coin_lot_size = BASE_LOT_SIZE;
+        state.pc_lot_size = QUOTE_LOT_SIZE;
+
+        Self { market_id, base_mint, bids, asks, event_queue, state }
+    }
+
+    //orders are (price lots, quantity lots)
+    pub fn install(&self, chain_client: &MockChainClient, bids: &[(u64, u64)], asks: &[(u64, u64)]) {
+        chain_client.set_account(self.market_id, self.market_account());
+        chain_client.set_account(self.base_mint, mint_account(BASE_DECIMALS));
+        chain_client.set_account(self.bids, book_account(Side::Bid, bids));
+        chain_client.set_account(self.asks, book_account(Side::Ask, asks));
+        chain_client.set_account(self.event_queue, event_queue_account());
+    }
+
+    pub fn market_account(&self) -> Account {
+        dex_account(bytes_of(&self.state).to_vec())
+    }
+
+    //orders are (side, order id, client order id), each takes the next free slot
+    pub fn open_orders_account(&self, owner: &Pubkey, orders: &[(Side, u128, u64)]) -> Account {
+        let mut open_orders = OpenOrders::zeroed();
+        open_orders.account_flags = (AccountFlag::Initialized | AccountFlag::OpenOrders).bits();
+        open_orders.market = cast(self.market_id.to_bytes());
+        open_orders.owner = cast(owner.to_bytes());
+        let mut free_slot_bits = !0u128;
+        let mut is_bid_bits = 0u128;
+        let mut order_ids = [0u128; 128];
+        let mut client_order_ids = [0u64; 128];
+        for (slot, (side, order_id, client_order_id)) in orders.iter().enumerate() {
+            free_slot_bits &= !(1u128 << slot);
+            if *side == Side::Bid {
+                is_bid_bits |= 1u128 << slot;
+            }
+            order_ids[slot] = *order_id;
+            client_order_ids[slot] = *client_order_id;
+        }
+        open_orders.free_slot_bits = free_slot_bits;
+        open_orders.is_bid_bits = is_bid_bits;
+        open_orders.orders = order_ids;
+        open_orders.client_order_ids = client_order_ids;
+        dex_account(bytes_of(&open_orders).to_vec())
+    }
+}
+
+pub fn mint_account(decimals: u8) -> Account {
+    let mut data = vec![0u8; MINT_ACCOUNT_SIZE];
+    data[TOKEN_MINT_DECIMALS_OFFSET] = decimals;
+    Account { lamports: 1, data, owner: *TOKEN_PROGRAM_ID, executable: false, rent_epoch: 0 }
+}
+
+fn book_account(side: Side, orders: &[(u64, u64)]) -> Account {
+    let flags = match side {
+        Side::Bid => AccountFlag::Initialized | AccountFlag::Bids,
+        Side::Ask => AccountFlag::Initialized | AccountFlag::Asks,
+    };
+    let mut data = flags.bits().to_le_bytes().to_vec();
+    let mut slab_bytes = vec![0u8; SLAB_HEADER_SIZE + SLAB_NODES * SLAB_NODE_SIZE];
+    let slab = Slab::new(&mut slab_bytes);
+    //the fees module is private, a zeroed leaf carries the base tier
+    let fee_tier = LeafNode::zeroed().fee_tier();
+    for (seq_num, (price, quantity)) in orders.iter().enumerate() {
+        let key = ((*price as u128) << 64) | seq_num as u128;
+        let leaf = LeafNode::new(0, key, [0; 4], *quantity, fee_tier, 0);
+        slab.insert_leaf(&leaf).unwrap();
+    }
+    data.extend_from_slice(&slab_bytes);
+    dex_account(data)
+}
+
+fn event_queue_account() -> Account {
+    //the header is account_flags then head, count and seq_num, all zero for an empty queue
+    let mut data = (AccountFlag::Initialized | AccountFlag::EventQueue).bits().to_le_bytes().to_vec();
+    data.resize(size_of::<EventQueueHeader>() + EVENT_QUEUE_CAPACITY * EVENT_SIZE, 0);
+    dex_account(data)
+}
+
+//every dex account is wrapped in "serum" ... "padding"
+fn dex_account(content: Vec<u8>) -> Account {
+    let mut data = b"serum".to_vec();
+    data.extend_from_slice(&content);
+    data.extend_from_slice(b"padding");
+    Account { lamports: 1, data, owner: *SERUM_PROGRAM_ID_V3, executable: false, rent_epoch: 0 }
+}
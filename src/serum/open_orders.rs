@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use serum_dex::instruction::{cancel_order, init_open_orders, settle_funds};
+use serum_dex::state::MarketState;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::Keypair;
+use solana_sdk::signer::Signer;
+use solana_sdk::system_instruction;
+
+use crate::rpc::chain_client::ChainClient;
+use crate::serum::market::MarketWrapper;
+use crate::serum::market_feed::OpenOrdersSnapshot;
+use crate::serum::serum_utils::{pub_key, SERUM_PROGRAM_ID_V3, SerumUtils, TOKEN_PROGRAM_ID};
+use crate::transactions::direct_send::send_and_confirm;
+
+const OPEN_ORDERS_ACCOUNT_SIZE: usize = 3228;
+//a cancel by order id is 25 bytes of instruction data plus six account indices, the accounts themselves are listed
+//once per transaction. Ten come to about 670 bytes of a 1232 byte packet, leaving room for the nonce advance and
+//compute budget instructions of the kill switch's cancels
+pub const CANCELS_PER_TRANSACTION: usize = 10;
+//an open orders account holds this many orders across both sides, a new order beyond them fails
+pub const OPEN_ORDERS_SLOTS: usize = 128;
+//"serum" head padding (5) + account_flags (8), then the market and the owner
+const OPEN_ORDERS_MARKET_OFFSET: usize = 13;
+const OPEN_ORDERS_OWNER_OFFSET: usize = 45;
+
+//every open orders account the owner has on the market, there is usually only one
+pub async fn find_open_orders(
+    chain_client: &dyn ChainClient,
+    market: &Pubkey,
+    owner: &Pubkey,
+) -> Result<Vec<(Pubkey, OpenOrdersSnapshot)>, Box<dyn std::error::Error>> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::DataSize(OPEN_ORDERS_ACCOUNT_SIZE as u64),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(OPEN_ORDERS_MARKET_OFFSET, market.as_ref())),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(OPEN_ORDERS_OWNER_OFFSET, owner.as_ref())),
+        ]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+
+    let mut open_orders = Vec::new();
+    for (pubkey, account) in chain_client.get_program_accounts(&SERUM_PROGRAM_ID_V3, config).await? {
+        open_orders.push((pubkey, OpenOrdersSnapshot::from_account_data(&account.data)?));
+    }
+    Ok(open_orders)
+}
+
+//a fresh keypair funded for rent by the owner, returns the new account
+pub async fn create_open_orders(
+    chain_client: &dyn ChainClient,
+    market: &Pubkey,
+    owner: Arc<dyn Signer + Send + Sync>,
+) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    let open_orders = Arc::new(Keypair::new());
+    let owner_pubkey = owner.pubkey();
+    let instructions = vec![
+        system_instruction::create_account(
+            &owner_pubkey,
+            &open_orders.pubkey(),
+            Rent::default().minimum_balance(OPEN_ORDERS_ACCOUNT_SIZE),
+            OPEN_ORDERS_ACCOUNT_SIZE as u64,
+            &SERUM_PROGRAM_ID_V3,
+        ),
+        init_open_orders(&SERUM_PROGRAM_ID_V3, &open_orders.pubkey(), &owner_pubkey, market, None)?,
+    ];
+
+    send_and_confirm(chain_client, &instructions, vec![owner, open_orders.clone()]).await?;
+    Ok(open_orders.pubkey())
+}
+
+pub fn settle_instruction(
+    market: &MarketWrapper,
+    open_orders: &Pubkey,
+    owner: &Pubkey,
+    base_wallet: &Pubkey,
+    quote_wallet: &Pubkey,
+) -> Result<Instruction, Box<dyn std::error::Error>> {
+    let vault_signer = SerumUtils::get_vault_signer(market);
+    let market_state = *market.market.lock().unwrap();
+    Ok(settle_funds(
+        &SERUM_PROGRAM_ID_V3,
+        &pub_key(market_state.own_address),
+        &TOKEN_PROGRAM_ID,
+        open_orders,
+        owner,
+        &pub_key(market_state.coin_vault),
+        base_wallet,
+        &pub_key(market_state.pc_vault),
+        quote_wallet,
+        None,
+        &vault_signer,
+    )?)
+}
+
+//one cancel per resting order, by exchange order id so it works whatever client ids were used
+pub fn cancel_all_instructions(
+    market: &MarketState,
+    open_orders: &Pubkey,
+    snapshot: &OpenOrdersSnapshot,
+    owner: &Pubkey,
+) -> Result<Vec<Instruction>, Box<dyn std::error::Error>> {
+    let mut instructions = Vec::with_capacity(snapshot.orders.len());
+    for order in &snapshot.orders {
+        instructions.push(cancel_order(
+            &SERUM_PROGRAM_ID_V3,
+            &pub_key(market.own_address),
+            &pub_key(market.bids),
+            &pub_key(market.asks),
+            open_orders,
+            owner,
+            &pub_key(market.event_q),
+            order.side,
+            order.order_id,
+        )?);
+    }
+    Ok(instructions)
+}
+
+//cancels whatever is resting on the open orders account right now, read from the chain rather than from a
+//feed that may be stale. Returns how many orders were cancelled
+pub async fn cancel_all(
+    chain_client: &dyn ChainClient,
+    market: &MarketState,
+    open_orders: &Pubkey,
+    owner: Arc<dyn Signer + Send + Sync>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let account = chain_client.get_account(open_orders, CommitmentConfig::confirmed()).await?
+        .ok_or_else(|| format!("open orders account {} not found", open_orders))?;
+    let snapshot = OpenOrdersSnapshot::from_account_data(&account.data)?;
+    let instructions = cancel_all_instructions(market, open_orders, &snapshot, &owner.pubkey())?;
+    for chunk in instructions.chunks(CANCELS_PER_TRANSACTION) {
+        let signature = send_and_confirm(chain_client, chunk, vec![owner.clone()]).await?;
+        println!("Cancelled {} orders on {}: {}", chunk.len(), open_orders, signature);
+    }
+    Ok(instructions.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use serum_dex::matching::Side;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::packet::PACKET_DATA_SIZE;
+    use solana_sdk::transaction::Transaction;
+
+    use crate::rpc::mock_chain_client::MockChainClient;
+    use crate::serum::market_fixture::MarketFixture;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn find_open_orders_only_returns_the_owners_accounts() {
+        let chain_client = MockChainClient::new();
+        let fixture = MarketFixture::new();
+        let owner = Pubkey::new_unique();
+        let open_orders = Pubkey::new_unique();
+        chain_client.set_account(open_orders, fixture.open_orders_account(&owner, &[(Side::Bid, 11, 1), (Side::Ask, 12, 2)]));
+        chain_client.set_account(Pubkey::new_unique(), fixture.open_orders_account(&Pubkey::new_unique(), &[]));
+        chain_client.set_account(Pubkey::new_unique(), MarketFixture::new().open_orders_account(&owner, &[]));
+
+        let found = find_open_orders(&chain_client, &fixture.market_id, &owner).await.unwrap();
+        assert_eq!(found.len(), 1);
+        let (pubkey, snapshot) = &found[0];
+        assert_eq!(*pubkey, open_orders);
+        let orders: Vec<(u128, u64, Side)> = snapshot.orders.iter().map(|order| (order.order_id, order.client_order_id, order.side)).collect();
+        assert_eq!(orders, vec![(11, 1, Side::Bid), (12, 2, Side::Ask)]);
+    }
+
+    #[tokio::test]
+    async fn cancel_all_sends_one_transaction_per_chunk() {
+        let chain_client = MockChainClient::new();
+        let fixture = MarketFixture::new();
+        let owner = Arc::new(Keypair::new());
+        let open_orders = Pubkey::new_unique();
+        let orders: Vec<(Side, u128, u64)> = (0..23).map(|i| (Side::Bid, i as u128 + 1, i + 1)).collect();
+        chain_client.set_account(open_orders, fixture.open_orders_account(&owner.pubkey(), &orders));
+
+        let cancelled = cancel_all(&chain_client, &fixture.state, &open_orders, owner).await.unwrap();
+        assert_eq!(cancelled, 23);
+        let instruction_counts: Vec<usize> = chain_client.sent_transactions().iter()
+            .map(|transaction| transaction.message.instructions().len())
+            .collect();
+        assert_eq!(instruction_counts, vec![CANCELS_PER_TRANSACTION, CANCELS_PER_TRANSACTION, 3]);
+    }
+
+    #[test]
+    fn a_full_chunk_of_cancels_fits_a_packet() {
+        let fixture = MarketFixture::new();
+        let owner = Keypair::new();
+        let open_orders = Pubkey::new_unique();
+        let orders: Vec<(Side, u128, u64)> = (0..CANCELS_PER_TRANSACTION)
+            .map(|i| (if i % 2 == 0 { Side::Bid } else { Side::Ask }, u128::MAX - i as u128, i as u64 + 1))
+            .collect();
+        let snapshot = OpenOrdersSnapshot::from_account_data(&fixture.open_orders_account(&owner.pubkey(), &orders).data).unwrap();
+        let instructions = cancel_all_instructions(&fixture.state, &open_orders, &snapshot, &owner.pubkey()).unwrap();
+        assert_eq!(instructions.len(), CANCELS_PER_TRANSACTION);
+        assert!(instructions.iter().all(|instruction| instruction.data.len() == 25 && instruction.accounts.len() == 6));
+
+        let transaction = Transaction::new_signed_with_payer(&instructions, Some(&owner.pubkey()), &[&owner], Hash::new_unique());
+        let size = bincode::serialized_size(&transaction).unwrap() as usize;
+        assert!(size <= PACKET_DATA_SIZE);
+    }
+}
@@ -1,26 +1,64 @@
 use std::cell::RefMut;
-use serum_dex::critbit::{Slab, SlabView};
-use serum_dex::matching::OrderBookState;
+use serum_dex::critbit::Slab;
+use serum_dex::matching::Side;
+use serum_dex::state::MarketState;
+use solana_program::account_info::IntoAccountInfo;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
 use crate::serum::serum_utils::SerumUtils;
 
-//order books just contain a price which is updated after every market reload
+#[derive(Clone, Debug)]
+pub struct Order {
+    pub order_id: u128,
+    pub price: f64,
+    pub quantity: f64,
+}
+
+//order books hold every resting order best price first, rebuilt from the slab after every market update
 #[derive(Clone)]
 pub struct OrderBook {
     best_price: f64,
-    orders: Vec<Order>
+    orders: Vec<Order>,
 }
 
 impl OrderBook {
-    pub(crate) fn new(slab: RefMut<Slab>, base_decimals: i8, quote_decimals: i8, base_lot_size: u64, quote_lot_size: u64) -> OrderBook {
-        let max = slab.find_max().unwrap();
-        let leaf_node = slab.get(max).unwrap().as_leaf().unwrap();
+    //drains the slab to walk it in price order, so it has to be loaded from a copy of the account data
+    pub(crate) fn new(mut slab: RefMut<Slab>, side: Side, base_decimals: i8, quote_decimals: i8, base_lot_size: u64, quote_lot_size: u64) -> OrderBook {
+        let mut orders = Vec::new();
+        loop {
+            let leaf_node = match side {
+                Side::Bid => slab.remove_max(),
+                Side::Ask => slab.remove_min(),
+            };
+            let leaf_node = match leaf_node {
+                Some(leaf_node) => leaf_node,
+                None => break,
+            };
 
-        let best_price = SerumUtils::price_lots_to_number(leaf_node.price().get() as i64, base_decimals, quote_decimals, base_lot_size, quote_lot_size);
+            orders.push(Order {
+                order_id: leaf_node.order_id(),
+                price: SerumUtils::price_lots_to_number(leaf_node.price().get() as i64, base_decimals, quote_decimals, base_lot_size, quote_lot_size),
+                quantity: (leaf_node.quantity() * base_lot_size) as f64 / SerumUtils::get_base_spl_token_multiplier(base_decimals as u32),
+            });
+        }
 
         OrderBook {
-            best_price
+            best_price: orders.first().map(|order| order.price).unwrap_or(0.0),
+            orders,
         }
     }
+
+    pub fn from_account(market: &MarketState, side: Side, pubkey: Pubkey, account: Account, base_decimals: i8, quote_decimals: i8) -> Result<OrderBook, Box<dyn std::error::Error>> {
+        let mut keyed_account = (pubkey, account);
+        let account_info = (&mut keyed_account).into_account_info();
+        let slab = match side {
+            Side::Bid => market.load_bids_mut(&account_info),
+            Side::Ask => market.load_asks_mut(&account_info),
+        }.map_err(|e| format!("Error loading {:?} slab {}: {:?}", side, pubkey, e))?;
+
+        Ok(OrderBook::new(slab, side, base_decimals, quote_decimals, market.coin_lot_size, market.pc_lot_size))
+    }
+
     pub fn get_best_bid_price(&self) -> f64 {
         self.best_price
     }
@@ -28,4 +66,20 @@ impl OrderBook {
     pub fn get_best_ask_price(&self) -> f64 {
         self.best_price
     }
-}
\ No newline at end of file
+
+    pub fn get_orders(&self) -> &Vec<Order> {
+        &self.orders
+    }
+
+    //L2 view, (price, total quantity) best price first
+    pub fn levels(&self) -> Vec<(f64, f64)> {
+        let mut levels: Vec<(f64, f64)> = Vec::new();
+        for order in &self.orders {
+            match levels.last_mut() {
+                Some((price, quantity)) if *price == order.price => *quantity += order.quantity,
+                _ => levels.push((order.price, order.quantity)),
+            }
+        }
+        levels
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde_derive::{Deserialize, Serialize};
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+
+//Starting -> Running <-> Paused, then Stopping -> Stopped once the strategy loop has returned.
+//Failed is a loop that panicked, a failed or stopped bot can be started again
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BotState {
+    Starting,
+    Running,
+    Paused,
+    Stopping,
+    Stopped,
+    Failed,
+}
+
+impl BotState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BotState::Starting => "starting",
+            BotState::Running => "running",
+            BotState::Paused => "paused",
+            BotState::Stopping => "stopping",
+            BotState::Stopped => "stopped",
+            BotState::Failed => "failed",
+        }
+    }
+
+    pub fn parse(state: &str) -> Result<Self, String> {
+        match state {
+            "starting" => Ok(BotState::Starting),
+            "running" => Ok(BotState::Running),
+            "paused" => Ok(BotState::Paused),
+            "stopping" => Ok(BotState::Stopping),
+            "stopped" => Ok(BotState::Stopped),
+            "failed" => Ok(BotState::Failed),
+            _ => Err(format!("unknown bot state {}", state)),
+        }
+    }
+
+    //what a restart should bring the bot back as, the in between states settle on where they were heading
+    pub fn settled(&self) -> BotState {
+        match self {
+            BotState::Starting => BotState::Running,
+            BotState::Stopping => BotState::Stopped,
+            state => *state,
+        }
+    }
+
+    //whether a strategy task exists (or is about to) for the bot
+    pub fn is_active(&self) -> bool {
+        matches!(self, BotState::Starting | BotState::Running | BotState::Paused)
+    }
+}
+
+//the strategy's side of the lifecycle, a fresh one for every start since a cancelled token stays cancelled
+#[derive(Clone)]
+pub struct BotControl {
+    cancel: CancellationToken,
+    paused: Arc<AtomicBool>,
+}
+
+impl BotControl {
+    pub fn new(paused: bool) -> Self {
+        Self {
+            cancel: CancellationToken::new(),
+            paused: Arc::new(AtomicBool::new(paused)),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn cancelled(&self) -> WaitForCancellationFuture<'_> {
+        self.cancel.cancelled()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+    }
+
+    //a paused strategy keeps following the market but doesn't place or replace quotes
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+}
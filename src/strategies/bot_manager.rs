@@ -1,31 +1,617 @@
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use std::sync::{Arc, Mutex};
-use solana_sdk::account::Account;
-use crate::strategies::open_book_bot::OpenBookBot;
-use crate::strategies::strategy::Strategy;
+use std::time::{Duration, Instant};
+use futures::future::join_all;
+use futures::FutureExt;
+use serde_derive::{Deserialize, Serialize};
+use tokio::runtime::Runtime;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use uuid::Uuid;
+use solana_sdk::signer::Signer;
+use crate::arcana_web_config::BotSettings;
+use crate::pricing::jupiter_pricing_source::JupiterPricingSource;
+use crate::rpc::chain_client::ChainClient;
+use crate::rpc::retry::RetryPolicy;
+use crate::strategies::bot_lifecycle::{BotControl, BotState};
+use crate::strategies::bot_store::{BotStore, OrderStateRecorder};
+use crate::strategies::open_book_bot::{BotHealth, OpenBookBot};
+use crate::strategies::quote_params::QuoteParamsUpdate;
+use crate::strategies::strategy_registry::{BotConfig, StrategyContext, StrategyKindInfo, StrategyRegistry};
+use crate::transactions::emergency_cancel::{broadcast, EmergencyCancels};
+use crate::transactions::priority_fees::FeeTracker;
 
 pub struct BotManager {
-    pub trading_account: Option<Account>,
     bot_list: Vec<OpenBookBot>,
+    store: Arc<BotStore>,
+    registry: StrategyRegistry,
+    //the order executors keep these signed, the kill switch sends them
+    emergency_cancels: Arc<EmergencyCancels>,
+    //shared by every bot, kept apart from the web server's runtime. Only an Option so Drop can take it
+    executor: Option<Runtime>,
+    //arcana_web_config::bot_settings outside of tests
+    settings: fn() -> BotSettings,
 }
 
 impl BotManager {
-    pub fn new() -> Self {
+    pub fn new(store: Arc<BotStore>, settings: fn() -> BotSettings) -> Self {
+        let executor = tokio::runtime::Builder::new_multi_thread()
+            .thread_name("strategy-executor")
+            .enable_all()
+            .build().expect("Error building a tokio runtime for strategy executor");
         Self {
-            trading_account: None,
             bot_list: Vec::new(),
+            store,
+            registry: StrategyRegistry::with_builtin(),
+            emergency_cancels: Arc::new(EmergencyCancels::default()),
+            executor: Some(executor),
+            settings,
         }
     }
 
+    pub fn strategy_kinds(&self) -> Vec<StrategyKindInfo> {
+        self.registry.kinds()
+    }
+
+    //checks the config against its strategy kind straight away. The returned future does the slow part, loading
+    //the market and the like, and borrows nothing from the manager so it can run with the manager unlocked
+    pub fn prepare_bot(
+        &self,
+        uuid: Uuid,
+        config: BotConfig,
+        chain_client: Arc<dyn ChainClient>,
+        jupiter_pricing_source: JupiterPricingSource,
+        signer: Arc<dyn Signer + Send + Sync>,
+    ) -> Result<impl Future<Output = Result<OpenBookBot, String>> + Send + 'static, String> {
+        let fee_tracker = Arc::new(FeeTracker::new());
+        let context = StrategyContext {
+            order_state: Arc::new(OrderStateRecorder::new(uuid, self.store.clone())),
+            emergency_cancels: self.emergency_cancels.clone(),
+            chain_client,
+            jupiter_pricing_source,
+            signer,
+            fee_tracker: fee_tracker.clone(),
+            settings: (self.settings)(),
+        };
+        let prepared = self.registry.prepare(&config, context)?;
+        let config = BotConfig {
+            strategy: config.strategy,
+            config: prepared.config,
+        };
+        let build = prepared.build;
+        Ok(async move {
+            let built = build.await?;
+            Ok(OpenBookBot::new(uuid, config, built, fee_tracker))
+        })
+    }
+
+    //persisted before it starts, so a crash right after adding still brings the bot back
     pub fn add_bot(&mut self, mut bot: OpenBookBot) {
-        let executor = tokio::runtime::Builder::new_multi_thread()
-            .worker_threads(1)
-            .build().expect("Error building a tokio runtime for strategy executor");
-        bot.strategy.as_mut().unwrap().start(&executor);
-        bot.strategy_executor = Some(executor);
+        if let Err(err) = self.store.save_bot(&bot.uuid, bot.config(), BotState::Running) {
+            eprintln!("Error saving bot {}: {}", bot.uuid, err);
+        }
+        launch(self.executor.as_ref().unwrap(), &self.store, (self.settings)().restart_policy, &mut bot, false);
         self.bot_list.push(bot);
     }
 
+    //rebuilds every stored bot in the state it was left in, running and paused bots get their strategy task back.
+    //Runs before the server starts and again whenever the keystore is unlocked, bots that are already back are left
+    //alone. A bot that can't be rebuilt stays in the database for the next try
+    pub fn restore_bots(
+        &mut self,
+        chain_client: Arc<dyn ChainClient>,
+        jupiter_pricing_source: &JupiterPricingSource,
+        signer: Option<Arc<dyn Signer + Send + Sync>>,
+    ) {
+        let mut records = match self.store.load_bots() {
+            Ok(records) => records,
+            Err(err) => {
+                eprintln!("Error loading bots: {}", err);
+                return;
+            }
+        };
+        records.retain(|record| self.get_bot(&record.uuid).is_none());
+        if records.is_empty() {
+            return;
+        }
+        let signer = match signer {
+            Some(signer) => signer,
+            None => {
+                eprintln!("Not restoring {} bots, there is no trading key to sign with", records.len());
+                return;
+            }
+        };
+
+        for record in records {
+            let build = self.prepare_bot(record.uuid, record.config, chain_client.clone(), jupiter_pricing_source.clone(), signer.clone());
+            match build.and_then(|build| self.executor.as_ref().unwrap().block_on(build)) {
+                Ok(mut bot) => {
+                    bot.health.lock().unwrap().last_error = record.last_error;
+                    let state = record.state.settled();
+                    match state {
+                        BotState::Running => launch(self.executor.as_ref().unwrap(), &self.store, (self.settings)().restart_policy, &mut bot, false),
+                        BotState::Paused => launch(self.executor.as_ref().unwrap(), &self.store, (self.settings)().restart_policy, &mut bot, true),
+                        state => *bot.state.lock().unwrap() = state,
+                    }
+                    println!("Restored bot {} ({})", bot.uuid, state.as_str());
+                    self.bot_list.push(bot);
+                }
+                Err(err) => eprintln!("Error restoring bot {}: {}", record.uuid, err),
+            }
+        }
+    }
+
     pub fn get_bot_list(&self) -> &Vec<OpenBookBot> {
         &self.bot_list
     }
+
+    pub fn get_bot(&self, uuid: &Uuid) -> Option<&OpenBookBot> {
+        self.bot_list.iter().find(|bot| &bot.uuid == uuid)
+    }
+
+    //starts a stopped or failed bot again, with the strategy it had before
+    pub fn start_bot(&mut self, uuid: &Uuid) -> Result<(), String> {
+        let index = self.index_of(uuid)?;
+        let bot = &mut self.bot_list[index];
+        match bot.state() {
+            BotState::Stopped | BotState::Failed => {
+                *bot.health.lock().unwrap() = BotHealth::default();
+                launch(self.executor.as_ref().unwrap(), &self.store, (self.settings)().restart_policy, bot, false);
+            }
+            state => return Err(format!("bot {} is {}, only a stopped or failed bot can be started", uuid, state.as_str())),
+        }
+        self.persist(uuid, BotState::Running);
+        if let Err(err) = self.store.set_last_error(uuid, None) {
+            eprintln!("Error saving bot {}: {}", uuid, err);
+        }
+        println!("Started bot {}", uuid);
+        Ok(())
+    }
+
+    pub fn pause_bot(&mut self, uuid: &Uuid) -> Result<(), String> {
+        let index = self.index_of(uuid)?;
+        let bot = &self.bot_list[index];
+        let mut state = bot.state.lock().unwrap();
+        if *state != BotState::Running {
+            return Err(format!("bot {} is {}, only a running bot can be paused", uuid, state.as_str()));
+        }
+        bot.control.as_ref().unwrap().set_paused(true);
+        *state = BotState::Paused;
+        drop(state);
+        self.persist(uuid, BotState::Paused);
+        println!("Paused bot {}", uuid);
+        Ok(())
+    }
+
+    pub fn resume_bot(&mut self, uuid: &Uuid) -> Result<(), String> {
+        let index = self.index_of(uuid)?;
+        let bot = &self.bot_list[index];
+        let mut state = bot.state.lock().unwrap();
+        if *state != BotState::Paused {
+            return Err(format!("bot {} is {}, only a paused bot can be resumed", uuid, state.as_str()));
+        }
+        bot.control.as_ref().unwrap().set_paused(false);
+        *state = BotState::Running;
+        drop(state);
+        self.persist(uuid, BotState::Running);
+        println!("Resumed bot {}", uuid);
+        Ok(())
+    }
+
+    //only asks the strategy to stop, it finishes the tick it is on and the task moves the bot to Stopped
+    pub fn stop_bot(&mut self, uuid: &Uuid) -> Result<(), String> {
+        let index = self.index_of(uuid)?;
+        let bot = &mut self.bot_list[index];
+        let mut state = bot.state.lock().unwrap();
+        if !state.is_active() {
+            return Err(format!("bot {} is {}, there is nothing to stop", uuid, state.as_str()));
+        }
+        if let Some(control) = bot.control.take() {
+            control.cancel();
+        }
+        *state = BotState::Stopping;
+        drop(state);
+        self.persist(uuid, BotState::Stopped);
+        println!("Stopping bot {}", uuid);
+        Ok(())
+    }
+
+    //works in any state, a bot that isn't running takes the new params the next time it starts
+    pub fn update_params(&mut self, uuid: &Uuid, update: &QuoteParamsUpdate) -> Result<(), String> {
+        let index = self.index_of(uuid)?;
+        let bot = &mut self.bot_list[index];
+        let changes = bot.update_quote_params(update)?;
+        if changes.is_empty() {
+            return Ok(());
+        }
+        if let Err(err) = self.store.set_config(uuid, bot.config()) {
+            eprintln!("Error saving bot {}: {}", uuid, err);
+        }
+        println!("Updated bot {}: {}", uuid, changes.join(", "));
+        Ok(())
+    }
+
+    //stops the bot first if it is still active, its stored params and order state go with it
+    pub fn delete_bot(&mut self, uuid: &Uuid) -> Result<(), String> {
+        let index = self.index_of(uuid)?;
+        if self.bot_list[index].state().is_active() {
+            self.stop_bot(uuid)?;
+        }
+        self.bot_list.remove(index);
+        self.store.delete_bot(uuid).map_err(|e| format!("Error deleting bot {}: {}", uuid, e))?;
+        println!("Deleted bot {}", uuid);
+        Ok(())
+    }
+
+    //stops every bot and has each one cancel its orders and settle. The persisted states are left alone so the
+    //bots come back as they were on the next start. The returned task ends when every bot is done or the timeout
+    //passes, whichever is first
+    pub fn shutdown(&mut self, timeout: Duration) -> JoinHandle<()> {
+        let mut strategies = Vec::with_capacity(self.bot_list.len());
+        for bot in &mut self.bot_list {
+            if let Some(control) = bot.control.take() {
+                control.cancel();
+            }
+            let mut state = bot.state.lock().unwrap();
+            if state.is_active() {
+                *state = BotState::Stopping;
+            }
+            strategies.push((bot.uuid, bot.order_executor.clone()));
+        }
+
+        println!("Shutting down {} bots", strategies.len());
+        self.executor.as_ref().unwrap().spawn(async move {
+            //each waits for its own loop to return before it touches the strategy
+            let wind_downs = strategies.into_iter().map(|(uuid, order_executor)| async move {
+                let mut order_executor = order_executor.lock().await;
+                match order_executor.shutdown().await {
+                    Ok(cancelled) => println!("Bot {} shut down, cancelled {} orders", uuid, cancelled),
+                    Err(err) => eprintln!("Bot {} did not shut down cleanly: {}", uuid, err),
+                }
+            });
+            if tokio::time::timeout(timeout, join_all(wind_downs)).await.is_err() {
+                eprintln!("Bots still shutting down after {:?}, their orders may still be on the book", timeout);
+            }
+        })
+    }
+
+    //halts quoting everywhere and gets every order off the book as fast as it can: the pre-signed cancels go out
+    //first since they need no blockhash, then each bot cancels whatever is still resting the slow way. The bots
+    //are left Stopped. The returned task ends when the sweep is done or the timeout passes
+    pub fn kill_switch(&mut self, chain_client: Arc<dyn ChainClient>, timeout: Duration) -> JoinHandle<KillSwitchReport> {
+        let mut report = KillSwitchReport::default();
+        let active: Vec<Uuid> = self.bot_list.iter()
+            .filter(|bot| bot.state().is_active())
+            .map(|bot| bot.uuid)
+            .collect();
+        for uuid in active {
+            if self.stop_bot(&uuid).is_ok() {
+                report.bots_stopped.push(uuid.to_string());
+            }
+        }
+        let strategies: Vec<_> = self.bot_list.iter().map(|bot| (bot.uuid, bot.order_executor.clone())).collect();
+        let presigned = self.emergency_cancels.take_all();
+
+        println!("Kill switch: stopped {} bots, broadcasting {} pre-signed cancels", report.bots_stopped.len(), presigned.len());
+        self.executor.as_ref().unwrap().spawn(async move {
+            for (open_orders, result) in broadcast(chain_client.as_ref(), presigned).await {
+                match &result {
+                    Ok(signature) => println!("Kill switch: sent cancel for {}, Tx Signature: {}", open_orders, signature),
+                    Err(err) => eprintln!("Kill switch: error sending cancel for {}: {}", open_orders, err),
+                }
+                report.presigned.push(PresignedCancel {
+                    open_orders: open_orders.to_string(),
+                    signature: result.as_ref().ok().map(|signature| signature.to_string()),
+                    error: result.err(),
+                });
+            }
+
+            //the sweep reads each open orders account from the chain, so it also catches anything the
+            //pre-signed cancels missed or orders placed after they were signed
+            let sweeps = strategies.into_iter().map(|(uuid, order_executor)| async move {
+                let result = order_executor.lock().await.cancel_orders().await;
+                match &result {
+                    Ok(cancelled) => println!("Kill switch: bot {} cancelled {} orders", uuid, cancelled),
+                    Err(err) => eprintln!("Kill switch: bot {} could not cancel its orders: {}", uuid, err),
+                }
+                BotCancel {
+                    uuid: uuid.to_string(),
+                    cancelled: result.as_ref().ok().copied(),
+                    error: result.err(),
+                }
+            });
+            match tokio::time::timeout(timeout, join_all(sweeps)).await {
+                Ok(bots) => report.bots = bots,
+                Err(_) => {
+                    eprintln!("Kill switch: bots still cancelling after {:?}, their orders may still be on the book", timeout);
+                    report.timed_out = true;
+                }
+            }
+            report
+        })
+    }
+
+    fn index_of(&self, uuid: &Uuid) -> Result<usize, String> {
+        self.bot_list.iter().position(|bot| &bot.uuid == uuid).ok_or_else(|| format!("no bot {}", uuid))
+    }
+
+    fn persist(&self, uuid: &Uuid, state: BotState) {
+        if let Err(err) = self.store.set_state(uuid, state) {
+            eprintln!("Error saving bot {}: {}", uuid, err);
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct KillSwitchReport {
+    pub bots_stopped: Vec<String>,
+    pub presigned: Vec<PresignedCancel>,
+    pub bots: Vec<BotCancel>,
+    pub timed_out: bool,
+}
+
+//a sent cancel only means the rpc took it, it can still fail on chain if the orders filled meanwhile
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PresignedCancel {
+    pub open_orders: String,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BotCancel {
+    pub uuid: String,
+    pub cancelled: Option<usize>,
+    pub error: Option<String>,
+}
+
+//spawns the strategy loop with a fresh control and supervises it: a loop that errors or panics is run again after
+//a backoff, until it has failed policy.max_retries times in a row. Then its orders are cancelled and the
+//bot is left Failed. The task waits for a previous loop to let go of the strategy before anything else
+fn launch(executor: &Runtime, store: &Arc<BotStore>, policy: RetryPolicy, bot: &mut OpenBookBot, paused: bool) {
+    let control = BotControl::new(paused);
+    *bot.state.lock().unwrap() = BotState::Starting;
+
+    let uuid = bot.uuid;
+    let order_executor = bot.order_executor.clone();
+    let state = bot.state.clone();
+    let health = bot.health.clone();
+    let store = store.clone();
+    let task_control = control.clone();
+    bot.strategy_task = Some(executor.spawn(async move {
+        let mut order_executor = order_executor.lock().await;
+        let mut failures = 0;
+        loop {
+            //stopped while it was waiting for the strategy or backing off
+            if task_control.is_cancelled() {
+                break;
+            }
+            settle_started(&state, &task_control);
+
+            let started = Instant::now();
+            let outcome = AssertUnwindSafe(order_executor.run(task_control.clone())).catch_unwind().await;
+            let reason = match outcome {
+                Ok(Ok(())) => break,
+                Ok(Err(reason)) => reason,
+                Err(panic) => format!("strategy panicked: {}", panic_message(&panic)),
+            };
+            if task_control.is_cancelled() {
+                eprintln!("Bot {} failed while stopping: {}", uuid, reason);
+                break;
+            }
+
+            //a long healthy run means this is a new problem, not the same one again
+            if started.elapsed() >= policy.max_delay {
+                failures = 0;
+            }
+            failures += 1;
+            {
+                let mut health = health.lock().unwrap();
+                health.last_error = Some(reason.clone());
+                health.restarts += 1;
+            }
+            if let Err(err) = store.set_last_error(&uuid, Some(&reason)) {
+                eprintln!("Error saving bot {}: {}", uuid, err);
+            }
+
+            if failures > policy.max_retries {
+                eprintln!("Bot {} failed {} times in a row, giving up: {}", uuid, failures, reason);
+                match order_executor.cancel_orders().await {
+                    Ok(cancelled) => println!("Cancelled {} orders of failed bot {}", cancelled, uuid),
+                    Err(err) => eprintln!("Bot {} failed and its orders could not be cancelled: {}", uuid, err),
+                }
+                *state.lock().unwrap() = BotState::Failed;
+                if let Err(err) = store.set_state(&uuid, BotState::Failed) {
+                    eprintln!("Error saving bot {}: {}", uuid, err);
+                }
+                return;
+            }
+
+            let delay = policy.backoff(failures - 1);
+            eprintln!("Bot {} failed: {}, restarting in {:?} ({}/{})", uuid, reason, delay, failures, policy.max_retries);
+            {
+                let mut state = state.lock().unwrap();
+                if state.is_active() {
+                    *state = BotState::Starting;
+                }
+            }
+            tokio::select! {
+                _ = task_control.cancelled() => break,
+                _ = sleep(delay) => {}
+            }
+        }
+        *state.lock().unwrap() = BotState::Stopped;
+    }));
+    bot.control = Some(control);
+}
+
+//Starting becomes Running or Paused once the loop is about to run, anything else was set by the manager meanwhile
+fn settle_started(state: &Mutex<BotState>, control: &BotControl) {
+    let mut state = state.lock().unwrap();
+    if *state == BotState::Starting {
+        *state = if control.is_paused() { BotState::Paused } else { BotState::Running };
+    }
+}
+
+fn panic_message(panic: &Box<dyn Any + Send>) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+impl Drop for BotManager {
+    //dropping a runtime blocks until its tasks finish, which panics when done from inside another runtime
+    fn drop(&mut self) {
+        if let Some(executor) = self.executor.take() {
+            executor.shutdown_background();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use serde_json::Map;
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::Signature;
+    use solana_sdk::signer::SignerError;
+
+    use crate::rpc::mock_chain_client::MockChainClient;
+    use crate::serum::market_fixture::MarketFixture;
+    use crate::strategies::order_executor::{MarketAccounts, OrderExecutor};
+    use crate::strategies::strategy::{DesiredQuotes, MarketSnapshot, Quote, Strategy};
+    use crate::strategies::strategy_registry::BuiltStrategy;
+
+    use super::*;
+
+    const FAILED_TIMEOUT: Duration = Duration::from_secs(30);
+
+    //fast ticks and restarts, and a websocket nothing listens on so the feed falls back to polling the mock
+    fn test_settings() -> BotSettings {
+        BotSettings {
+            ws_url: "ws://127.0.0.1:1".to_string(),
+            loop_interval: Duration::from_millis(10),
+            restart_policy: RetryPolicy {
+                max_retries: 2,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_secs(60),
+            },
+        }
+    }
+
+    //every order transaction fails to sign, so every tick that sends one fails
+    struct RefusingSigner(Pubkey);
+
+    impl Signer for RefusingSigner {
+        fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+            Ok(self.0)
+        }
+
+        fn try_sign_message(&self, _message: &[u8]) -> Result<Signature, SignerError> {
+            Err(SignerError::Custom("signing refused".to_string()))
+        }
+
+        fn is_interactive(&self) -> bool {
+            false
+        }
+    }
+
+    struct OneBid;
+
+    #[async_trait]
+    impl Strategy for OneBid {
+        async fn on_update(&mut self, _snapshot: &MarketSnapshot) -> DesiredQuotes {
+            DesiredQuotes { bids: vec![Quote { price: 95.0, size: 1.0 }], asks: Vec::new() }
+        }
+    }
+
+    #[test]
+    fn bot_whose_orders_keep_failing_ends_up_failed() {
+        let store = Arc::new(BotStore::open(":memory:").unwrap());
+        let mut manager = BotManager::new(store.clone(), test_settings);
+        let chain_client = Arc::new(MockChainClient::new());
+        let fixture = MarketFixture::new();
+        fixture.install(&chain_client, &[(9_000, 1_000)], &[(11_000, 1_000)]);
+        let signer: Arc<dyn Signer + Send + Sync> = Arc::new(RefusingSigner(Pubkey::new_unique()));
+        let open_orders = Pubkey::new_unique();
+        chain_client.set_account(open_orders, fixture.open_orders_account(&signer.pubkey(), &[]));
+
+        let uuid = Uuid::new_v4();
+        let fee_tracker = Arc::new(FeeTracker::new());
+        let context = StrategyContext {
+            order_state: Arc::new(OrderStateRecorder::new(uuid, store.clone())),
+            emergency_cancels: manager.emergency_cancels.clone(),
+            chain_client: chain_client.clone(),
+            jupiter_pricing_source: JupiterPricingSource::new(String::new()),
+            signer,
+            fee_tracker: fee_tracker.clone(),
+            settings: test_settings(),
+        };
+        let accounts = MarketAccounts {
+            market_id: fixture.market_id,
+            open_orders,
+            base_wallet: Pubkey::new_unique(),
+            quote_wallet: Pubkey::new_unique(),
+        };
+        let executor = manager.executor.as_ref().unwrap()
+            .block_on(OrderExecutor::new(Box::new(OneBid), accounts, false, context))
+            .unwrap();
+        let config = BotConfig { strategy: "one_bid".to_string(), config: Map::new() };
+        manager.add_bot(OpenBookBot::new(uuid, config, BuiltStrategy { executor, quote_updates: None }, fee_tracker));
+
+        let started = Instant::now();
+        while manager.get_bot(&uuid).unwrap().state() != BotState::Failed {
+            assert!(started.elapsed() < FAILED_TIMEOUT, "bot is {} after {:?}", manager.get_bot(&uuid).unwrap().state().as_str(), FAILED_TIMEOUT);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        let health = manager.get_bot(&uuid).unwrap().health.lock().unwrap().clone();
+        //the first run and two restarts
+        assert_eq!(health.restarts, 3);
+        let last_error = health.last_error.unwrap();
+        assert!(last_error.contains("ticks in a row") && last_error.contains("signing refused"), "{}", last_error);
+        let record = store.load_bots().unwrap().into_iter().find(|record| record.uuid == uuid).unwrap();
+        assert_eq!(record.state, BotState::Failed);
+        assert_eq!(record.last_error.as_deref(), Some(last_error.as_str()));
+    }
+
+    #[test]
+    fn restore_waits_for_a_signer_and_brings_each_bot_back_once() {
+        let store = Arc::new(BotStore::open(":memory:").unwrap());
+        let mut manager = BotManager::new(store.clone(), test_settings);
+        let chain_client = Arc::new(MockChainClient::new());
+        let fixture = MarketFixture::new();
+        fixture.install(&chain_client, &[(9_000, 1_000)], &[(11_000, 1_000)]);
+        let signer: Arc<dyn Signer + Send + Sync> = Arc::new(RefusingSigner(Pubkey::new_unique()));
+        let open_orders = Pubkey::new_unique();
+        chain_client.set_account(open_orders, fixture.open_orders_account(&signer.pubkey(), &[]));
+
+        let uuid = Uuid::new_v4();
+        let config = serde_json::json!({
+            "market_id": fixture.market_id.to_string(),
+            "price_strategy": "orderbook",
+            "ooa": open_orders.to_string(),
+            "base_wallet": Pubkey::new_unique().to_string(),
+            "quote_wallet": Pubkey::new_unique().to_string(),
+        });
+        let config = BotConfig { strategy: "openbook_spl_usdc".to_string(), config: config.as_object().unwrap().clone() };
+        store.save_bot(&uuid, &config, BotState::Stopped).unwrap();
+        let jupiter_pricing_source = JupiterPricingSource::new(String::new());
+
+        //the keystore is still locked
+        manager.restore_bots(chain_client.clone(), &jupiter_pricing_source, None);
+        assert!(manager.get_bot(&uuid).is_none());
+
+        //unlocked, then unlocked again
+        manager.restore_bots(chain_client.clone(), &jupiter_pricing_source, Some(signer.clone()));
+        manager.restore_bots(chain_client.clone(), &jupiter_pricing_source, Some(signer));
+        assert_eq!(manager.get_bot_list().len(), 1);
+        assert_eq!(manager.get_bot(&uuid).unwrap().state(), BotState::Stopped);
+    }
 }
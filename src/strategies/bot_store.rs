@@ -0,0 +1,316 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_derive::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::serum::market_feed::OpenOrdersSnapshot;
+use crate::strategies::bot_lifecycle::BotState;
+use crate::strategies::client_order_ids::OrderIntent;
+use crate::strategies::strategy_registry::BotConfig;
+
+//order state updates made within this long of each other are written together
+const ORDER_STATE_WRITE_DELAY: Duration = Duration::from_millis(250);
+
+//params and order state are json columns, they change shape far more often than the table does
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS bots (
+        uuid        TEXT PRIMARY KEY,
+        params      TEXT NOT NULL,
+        status      TEXT NOT NULL,
+        order_state TEXT NOT NULL,
+        last_error  TEXT,
+        created_at  INTEGER NOT NULL,
+        updated_at  INTEGER NOT NULL
+    );
+";
+
+//an order resting on the book as of the last open orders update, order_id is a u128 so it is kept as a string in json
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StoredOrder {
+    pub order_id: String,
+    pub client_order_id: u64,
+    pub side: String,
+}
+
+//what the strategy last did, enough to find and cancel its orders after a restart
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrderState {
+    //the last client order id sequence handed out, see ClientOrderIds
+    pub next_client_order_sequence: u64,
+    pub order_intents: BTreeMap<u64, OrderIntent>,
+    pub last_bid_price: f64,
+    pub last_ask_price: f64,
+    pub open_orders: Vec<StoredOrder>,
+    pub base_total: u64,
+    pub quote_total: u64,
+    //durable nonces the emergency cancels are signed against, one per transaction of cancels. The first is created
+    //the first time the bot runs, more as the resting orders outgrow them
+    pub nonce_accounts: Vec<String>,
+    pub updated_at: u64,
+}
+
+impl OrderState {
+    pub fn record_open_orders(&mut self, snapshot: &OpenOrdersSnapshot) {
+        self.open_orders = snapshot.orders.iter()
+            .map(|order| StoredOrder {
+                order_id: order.order_id.to_string(),
+                client_order_id: order.client_order_id,
+                side: format!("{:?}", order.side),
+            })
+            .collect();
+        self.base_total = snapshot.base_total;
+        self.quote_total = snapshot.quote_total;
+    }
+}
+
+pub struct BotRecord {
+    pub uuid: Uuid,
+    pub config: BotConfig,
+    pub state: BotState,
+    pub last_error: Option<String>,
+}
+
+//a single connection behind a mutex. Bot params and status change on user actions only, order state changes on
+//every open orders update but goes through OrderStateRecorder, which writes it off the async threads
+pub struct BotStore {
+    connection: Mutex<Connection>,
+}
+
+impl BotStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        //databases from before the supervisor recorded failures
+        let has_last_error: bool = connection.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('bots') WHERE name = 'last_error'", [], |row| row.get(0))?;
+        if !has_last_error {
+            connection.execute_batch("ALTER TABLE bots ADD COLUMN last_error TEXT")?;
+        }
+        Ok(Self { connection: Mutex::new(connection) })
+    }
+
+    //inserts the bot or replaces its params and status, the order state is left alone for an existing bot
+    pub fn save_bot(&self, uuid: &Uuid, config: &BotConfig, state: BotState) -> Result<(), Box<dyn std::error::Error>> {
+        let now = unix_time();
+        self.connection.lock().unwrap().execute(
+            "INSERT INTO bots (uuid, params, status, order_state, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+             ON CONFLICT(uuid) DO UPDATE SET params = excluded.params, status = excluded.status, updated_at = excluded.updated_at",
+            params![uuid.to_string(), serde_json::to_string(config)?, state.as_str(), serde_json::to_string(&OrderState::default())?, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_config(&self, uuid: &Uuid, config: &BotConfig) -> Result<(), Box<dyn std::error::Error>> {
+        self.connection.lock().unwrap().execute(
+            "UPDATE bots SET params = ?2, updated_at = ?3 WHERE uuid = ?1",
+            params![uuid.to_string(), serde_json::to_string(config)?, unix_time()],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_state(&self, uuid: &Uuid, state: BotState) -> Result<(), Box<dyn std::error::Error>> {
+        self.connection.lock().unwrap().execute(
+            "UPDATE bots SET status = ?2, updated_at = ?3 WHERE uuid = ?1",
+            params![uuid.to_string(), state.as_str(), unix_time()],
+        )?;
+        Ok(())
+    }
+
+    //the reason the strategy last failed, cleared when the bot is started by hand
+    pub fn set_last_error(&self, uuid: &Uuid, last_error: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        self.connection.lock().unwrap().execute(
+            "UPDATE bots SET last_error = ?2, updated_at = ?3 WHERE uuid = ?1",
+            params![uuid.to_string(), last_error, unix_time()],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_bot(&self, uuid: &Uuid) -> Result<(), Box<dyn std::error::Error>> {
+        self.connection.lock().unwrap().execute("DELETE FROM bots WHERE uuid = ?1", params![uuid.to_string()])?;
+        Ok(())
+    }
+
+    pub fn save_order_state(&self, uuid: &Uuid, order_state: &OrderState) -> Result<(), Box<dyn std::error::Error>> {
+        self.connection.lock().unwrap().execute(
+            "UPDATE bots SET order_state = ?2, updated_at = ?3 WHERE uuid = ?1",
+            params![uuid.to_string(), serde_json::to_string(order_state)?, unix_time()],
+        )?;
+        Ok(())
+    }
+
+    pub fn order_state(&self, uuid: &Uuid) -> Result<Option<OrderState>, Box<dyn std::error::Error>> {
+        let order_state: Option<String> = self.connection.lock().unwrap()
+            .query_row("SELECT order_state FROM bots WHERE uuid = ?1", params![uuid.to_string()], |row| row.get(0))
+            .optional()?;
+        Ok(match order_state {
+            Some(order_state) => Some(serde_json::from_str(&order_state)?),
+            None => None,
+        })
+    }
+
+    //oldest first so bots come back in the order they were added
+    pub fn load_bots(&self) -> Result<Vec<BotRecord>, Box<dyn std::error::Error>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare("SELECT uuid, params, status, last_error FROM bots ORDER BY created_at, uuid")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, Option<String>>(3)?))
+        })?;
+
+        let mut bots = Vec::new();
+        for row in rows {
+            let (uuid, params, status, last_error) = row?;
+            bots.push(BotRecord {
+                uuid: Uuid::parse_str(&uuid)?,
+                config: serde_json::from_str(&params)?,
+                state: BotState::parse(&status)?,
+                last_error,
+            });
+        }
+        Ok(bots)
+    }
+}
+
+//shared between a bot's strategy and the store. Updates are applied straight away and written by a blocking task
+//a moment later, so a burst of them (a batch of new orders) is one write and no write ever runs on an async thread.
+//A crash can lose the last ORDER_STATE_WRITE_DELAY of updates
+pub struct OrderStateRecorder {
+    recorded: Arc<RecordedState>,
+}
+
+struct RecordedState {
+    uuid: Uuid,
+    store: Arc<BotStore>,
+    state: Mutex<OrderState>,
+    //set while a write is queued, every update made before it runs goes out with it
+    write_queued: AtomicBool,
+    //held from reading the state to the end of the write, so an older state can never land after a newer one
+    writing: Mutex<()>,
+}
+
+impl RecordedState {
+    //a failed write is only logged, the strategy keeps trading and the next update tries again
+    fn write(&self) {
+        let _writing = self.writing.lock().unwrap();
+        self.write_queued.store(false, Ordering::SeqCst);
+        let state = self.state.lock().unwrap().clone();
+        if let Err(err) = self.store.save_order_state(&self.uuid, &state) {
+            eprintln!("Error saving order state of bot {}: {}", self.uuid, err);
+        }
+    }
+}
+
+impl OrderStateRecorder {
+    pub fn new(uuid: Uuid, store: Arc<BotStore>) -> Self {
+        let state = match store.order_state(&uuid) {
+            Ok(state) => state.unwrap_or_default(),
+            Err(err) => {
+                eprintln!("Error loading order state of bot {}: {}", uuid, err);
+                OrderState::default()
+            }
+        };
+        Self {
+            recorded: Arc::new(RecordedState {
+                uuid,
+                store,
+                state: Mutex::new(state),
+                write_queued: AtomicBool::new(false),
+                writing: Mutex::new(()),
+            }),
+        }
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.recorded.uuid
+    }
+
+    pub fn snapshot(&self) -> OrderState {
+        self.recorded.state.lock().unwrap().clone()
+    }
+
+    //a look at one part of the state without cloning all of it
+    pub fn read<R, F: FnOnce(&OrderState) -> R>(&self, read: F) -> R {
+        read(&self.recorded.state.lock().unwrap())
+    }
+
+    pub fn update<F: FnOnce(&mut OrderState)>(&self, change: F) {
+        {
+            let mut state = self.recorded.state.lock().unwrap();
+            change(&mut state);
+            state.updated_at = unix_time() as u64;
+        }
+        if self.recorded.write_queued.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let recorded = self.recorded.clone();
+                runtime.spawn(async move {
+                    tokio::time::sleep(ORDER_STATE_WRITE_DELAY).await;
+                    if let Err(err) = tokio::task::spawn_blocking(move || recorded.write()).await {
+                        eprintln!("Order state writer stopped: {}", err);
+                    }
+                });
+            }
+            //nothing to hand the write to outside a runtime (the cli), so it happens here
+            Err(_) => self.recorded.write(),
+        }
+    }
+}
+
+fn unix_time() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs() as i64).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Map;
+
+    use super::*;
+
+    fn store_with_bot(uuid: &Uuid) -> Arc<BotStore> {
+        let store = Arc::new(BotStore::open(":memory:").unwrap());
+        let config = BotConfig { strategy: "open_book_spl_usdc".to_string(), config: Map::new() };
+        store.save_bot(uuid, &config, BotState::Running).unwrap();
+        store
+    }
+
+    fn saved_sequence(store: &BotStore, uuid: &Uuid) -> u64 {
+        store.order_state(uuid).unwrap().unwrap().next_client_order_sequence
+    }
+
+    #[tokio::test]
+    async fn updates_are_written_together_after_the_delay() {
+        let uuid = Uuid::new_v4();
+        let store = store_with_bot(&uuid);
+        let recorder = OrderStateRecorder::new(uuid, store.clone());
+
+        for _ in 0..5 {
+            recorder.update(|state| state.next_client_order_sequence += 1);
+        }
+        //applied straight away, not written yet
+        assert_eq!(recorder.read(|state| state.next_client_order_sequence), 5);
+        assert_eq!(saved_sequence(&store, &uuid), 0);
+
+        tokio::time::sleep(ORDER_STATE_WRITE_DELAY * 3).await;
+        assert_eq!(saved_sequence(&store, &uuid), 5);
+
+        recorder.update(|state| state.nonce_accounts.push("nonce".to_string()));
+        tokio::time::sleep(ORDER_STATE_WRITE_DELAY * 3).await;
+        assert_eq!(store.order_state(&uuid).unwrap().unwrap().nonce_accounts, vec!["nonce".to_string()]);
+    }
+
+    #[test]
+    fn updates_outside_a_runtime_are_written_straight_away() {
+        let uuid = Uuid::new_v4();
+        let store = store_with_bot(&uuid);
+        let recorder = OrderStateRecorder::new(uuid, store.clone());
+
+        recorder.update(|state| state.next_client_order_sequence = 7);
+        assert_eq!(saved_sequence(&store, &uuid), 7);
+        assert_eq!(OrderStateRecorder::new(uuid, store).snapshot().next_client_order_sequence, 7);
+    }
+}
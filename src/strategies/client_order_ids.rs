@@ -0,0 +1,187 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serum_dex::matching::Side;
+use serde_derive::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::strategies::bot_store::OrderStateRecorder;
+use crate::strategies::strategy::Quote;
+
+//bits 63..48 are the bot, bit 47 the side and the rest a sequence that only ever goes up
+const BOT_SHIFT: u32 = 48;
+const SIDE_BIT: u64 = 1 << 47;
+const SEQUENCE_MASK: u64 = SIDE_BIT - 1;
+//intents of the latest orders are kept, far more than can rest at once so anything on the book can be attributed
+const KEPT_INTENTS: usize = 256;
+//the order state is written a moment after an id is handed out, so ids from just before a crash may be on the book
+//without being saved. Every start skips this far past the saved sequence rather than risk handing them out again
+const RESTART_SEQUENCE_GAP: u64 = 1 << 16;
+
+//what an order was placed for, looked up by its client order id when it is cancelled or leaves the book
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrderIntent {
+    pub side: String,
+    pub price: f64,
+    pub size: f64,
+    pub placed_at: u64,
+}
+
+//hands out a bot's client order ids. The sequence lives in the bot's order state, so it carries on past where it
+//left off after a restart and an id is never reused
+pub struct ClientOrderIds {
+    bot_tag: u64,
+    order_state: Arc<OrderStateRecorder>,
+}
+
+impl ClientOrderIds {
+    pub fn new(order_state: Arc<OrderStateRecorder>) -> Self {
+        order_state.update(|state| state.next_client_order_sequence += RESTART_SEQUENCE_GAP);
+        Self {
+            bot_tag: bot_tag(&order_state.uuid()),
+            order_state,
+        }
+    }
+
+    //recorded before the order goes out, an id burnt on a transaction that never landed is simply skipped
+    pub fn allocate(&self, side: Side, quote: Quote) -> u64 {
+        let mut client_order_id = 0;
+        self.order_state.update(|state| {
+            state.next_client_order_sequence += 1;
+            client_order_id = self.encode(side, state.next_client_order_sequence);
+            state.order_intents.insert(client_order_id, OrderIntent {
+                side: format!("{:?}", side),
+                price: quote.price,
+                size: quote.size,
+                placed_at: unix_time(),
+            });
+            while state.order_intents.len() > KEPT_INTENTS {
+                let oldest = state.order_intents.keys().copied().min_by_key(|id| sequence(*id));
+                if let Some(oldest) = oldest {
+                    state.order_intents.remove(&oldest);
+                }
+            }
+        });
+        client_order_id
+    }
+
+    pub fn intent(&self, client_order_id: u64) -> Option<OrderIntent> {
+        self.order_state.read(|state| state.order_intents.get(&client_order_id).cloned())
+    }
+
+    //ids from before the bot had an allocator, or from another client trading the same open orders account, aren't
+    pub fn is_ours(&self, client_order_id: u64) -> bool {
+        client_order_id >> BOT_SHIFT == self.bot_tag
+    }
+
+    //"Bid 0.1 @ 20.5 (client id ..)" for the log
+    pub fn describe(&self, client_order_id: u64) -> String {
+        match self.intent(client_order_id) {
+            Some(intent) => format!("{} {} @ {} (client id {})", intent.side, intent.size, intent.price, client_order_id),
+            None if self.is_ours(client_order_id) => format!("order with client id {}", client_order_id),
+            None => format!("order with client id {} not placed by this bot", client_order_id),
+        }
+    }
+
+    fn encode(&self, side: Side, sequence: u64) -> u64 {
+        let side_bit = match side {
+            Side::Bid => 0,
+            Side::Ask => SIDE_BIT,
+        };
+        (self.bot_tag << BOT_SHIFT) | side_bit | (sequence & SEQUENCE_MASK)
+    }
+}
+
+//where an id comes in the bot's order, whatever its side
+pub fn sequence(client_order_id: u64) -> u64 {
+    client_order_id & SEQUENCE_MASK
+}
+
+//16 bits of the uuid. Two bots can share a tag, they only ever share ids if they also trade through the same open
+//orders account
+fn bot_tag(uuid: &Uuid) -> u64 {
+    let bytes = uuid.as_bytes();
+    u16::from_be_bytes([bytes[0], bytes[1]]) as u64
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Map;
+
+    use super::*;
+    use crate::strategies::bot_lifecycle::BotState;
+    use crate::strategies::bot_store::BotStore;
+    use crate::strategies::strategy_registry::BotConfig;
+
+    //tagged 0xabcd
+    fn bot_uuid() -> Uuid {
+        Uuid::from_bytes([0xab, 0xcd, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14])
+    }
+
+    fn store_with_bot(uuid: &Uuid) -> Arc<BotStore> {
+        let store = Arc::new(BotStore::open(":memory:").unwrap());
+        let config = BotConfig { strategy: "open_book_spl_usdc".to_string(), config: Map::new() };
+        store.save_bot(uuid, &config, BotState::Running).unwrap();
+        store
+    }
+
+    //outside a runtime every update is written as it's made
+    fn client_order_ids(store: &Arc<BotStore>) -> ClientOrderIds {
+        ClientOrderIds::new(Arc::new(OrderStateRecorder::new(bot_uuid(), store.clone())))
+    }
+
+    #[test]
+    fn ids_carry_the_bot_tag_the_side_and_the_sequence() {
+        let ids = client_order_ids(&store_with_bot(&bot_uuid()));
+        let bid = ids.allocate(Side::Bid, Quote { price: 20.5, size: 0.1 });
+        let ask = ids.allocate(Side::Ask, Quote { price: 21.0, size: 0.2 });
+
+        assert_eq!(bid >> 48, 0xabcd);
+        assert_eq!(ask >> 48, 0xabcd);
+        assert_eq!(bid & (1 << 47), 0);
+        assert_eq!(ask & (1 << 47), 1 << 47);
+        assert_eq!(sequence(bid), RESTART_SEQUENCE_GAP + 1);
+        assert_eq!(sequence(ask), RESTART_SEQUENCE_GAP + 2);
+
+        assert!(ids.is_ours(bid) && ids.is_ours(ask));
+        assert!(!ids.is_ours(0));
+        assert!(!ids.is_ours((0xabce << 48) | sequence(bid)));
+
+        let intent = ids.intent(ask).unwrap();
+        assert_eq!((intent.side.as_str(), intent.price, intent.size), ("Ask", 21.0, 0.2));
+        assert_eq!(ids.describe(bid), format!("Bid 0.1 @ 20.5 (client id {})", bid));
+    }
+
+    #[test]
+    fn the_sequence_carries_on_after_a_reload() {
+        let store = store_with_bot(&bot_uuid());
+        let ids = client_order_ids(&store);
+        let placed: Vec<u64> = (0..3).map(|_| ids.allocate(Side::Bid, Quote { price: 20.0, size: 1.0 })).collect();
+        drop(ids);
+
+        let reloaded = client_order_ids(&store);
+        let next = reloaded.allocate(Side::Bid, Quote { price: 20.0, size: 1.0 });
+        assert_eq!(sequence(next), sequence(placed[2]) + RESTART_SEQUENCE_GAP + 1);
+        assert!(!placed.contains(&next));
+        //the orders placed before the restart can still be attributed
+        assert!(placed.iter().all(|id| reloaded.intent(*id).is_some()));
+    }
+
+    #[test]
+    fn only_the_latest_intents_are_kept() {
+        let store = store_with_bot(&bot_uuid());
+        let ids = client_order_ids(&store);
+        let placed: Vec<u64> = (0..KEPT_INTENTS + 10)
+            .map(|index| {
+                let side = if index % 2 == 0 { Side::Bid } else { Side::Ask };
+                ids.allocate(side, Quote { price: 20.0, size: 1.0 })
+            })
+            .collect();
+
+        assert!(placed[..10].iter().all(|id| ids.intent(*id).is_none()));
+        assert!(placed[10..].iter().all(|id| ids.intent(*id).is_some()));
+        assert_eq!(store.order_state(&bot_uuid()).unwrap().unwrap().order_intents.len(), KEPT_INTENTS);
+    }
+}
@@ -0,0 +1,214 @@
+use serde_derive::{Deserialize, Serialize};
+use crate::serum::open_orders::OPEN_ORDERS_SLOTS;
+use crate::strategies::quote_params::{QuoteParams, BPS};
+use crate::strategies::strategy::Quote;
+
+//per side. Both sides together stay well inside the slots of an open orders account, with room left for the
+//orders that are being replaced
+pub const MAX_LEVELS: usize = OPEN_ORDERS_SLOTS / 4;
+//all of a side's levels together, in multiples of the first level's size. A geometric ladder grows fast, 32 levels
+//doubling would put about 2^32 times the configured amount on the book
+pub const MAX_TOTAL_SIZE_MULTIPLIER: f64 = 100.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeProgression {
+    Flat,
+    Linear,
+    Geometric,
+}
+
+//how many orders a side quotes and how they step away from the first. The first level is the quote params' own
+//spread and size, a single level is the plain one bid one ask quote
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LadderParams {
+    pub levels: usize,
+    //between neighbouring levels, on top of the side's spread
+    pub level_spacing_bps: f64,
+    pub size_progression: SizeProgression,
+    //linear: level n is 1 + n * size_factor times the first level's size, geometric: size_factor^n times
+    pub size_factor: f64,
+}
+
+impl Default for LadderParams {
+    fn default() -> Self {
+        Self {
+            levels: 1,
+            level_spacing_bps: 10.0,
+            size_progression: SizeProgression::Flat,
+            size_factor: 1.0,
+        }
+    }
+}
+
+impl LadderParams {
+    pub fn validate(&self, quote: &QuoteParams) -> Result<(), String> {
+        let mut problems = Vec::new();
+        if self.levels == 0 || self.levels > MAX_LEVELS {
+            problems.push(format!("levels must be between 1 and {}", MAX_LEVELS));
+        }
+        if !(self.level_spacing_bps > 0.0 && self.level_spacing_bps.is_finite()) {
+            problems.push("level_spacing_bps must be above 0".to_string());
+        }
+        if !(self.size_factor > 0.0 && self.size_factor.is_finite()) {
+            problems.push("size_factor must be above 0".to_string());
+        } else {
+            let total = self.total_size_multiplier();
+            if total > MAX_TOTAL_SIZE_MULTIPLIER {
+                problems.push(format!("the levels add up to {} times the first level's size, at most {} are allowed", total, MAX_TOTAL_SIZE_MULTIPLIER));
+            }
+        }
+        //the furthest bid still has to be above 0
+        let furthest_bid_bps = quote.bid_spread_bps + self.level_spacing_bps * self.levels.saturating_sub(1) as f64;
+        if furthest_bid_bps >= BPS {
+            problems.push(format!("the furthest bid is {} bps below the reference price, it must be below 10000", furthest_bid_bps));
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join(", "))
+        }
+    }
+
+    //closest to the reference price first
+    pub fn bids(&self, quote: &QuoteParams, reference_price: f64) -> Vec<Quote> {
+        (0..self.levels)
+            .map(|level| {
+                let price = reference_price * (1.0 - (quote.bid_spread_bps + self.offset_bps(level)) / BPS);
+                Quote { price, size: quote.bid_base_amount(price) * self.size_multiplier(level) }
+            })
+            .collect()
+    }
+
+    pub fn asks(&self, quote: &QuoteParams, reference_price: f64) -> Vec<Quote> {
+        (0..self.levels)
+            .map(|level| {
+                let price = reference_price * (1.0 + (quote.ask_spread_bps + self.offset_bps(level)) / BPS);
+                Quote { price, size: quote.amount_ask * self.size_multiplier(level) }
+            })
+            .collect()
+    }
+
+    fn offset_bps(&self, level: usize) -> f64 {
+        self.level_spacing_bps * level as f64
+    }
+
+    //capped to MAX_LEVELS so an out of range level count doesn't walk billions of levels
+    fn total_size_multiplier(&self) -> f64 {
+        (0..self.levels.min(MAX_LEVELS)).map(|level| self.size_multiplier(level)).sum()
+    }
+
+    fn size_multiplier(&self, level: usize) -> f64 {
+        match self.size_progression {
+            SizeProgression::Flat => 1.0,
+            SizeProgression::Linear => 1.0 + self.size_factor * level as f64,
+            SizeProgression::Geometric => self.size_factor.powi(level as i32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote_params() -> QuoteParams {
+        QuoteParams {
+            bid_spread_bps: 100.0,
+            ask_spread_bps: 50.0,
+            amount_bid: 990.0,
+            amount_ask: 2.0,
+            min_midpoint_change: 0.01,
+        }
+    }
+
+    fn ladder(levels: usize, size_progression: SizeProgression, size_factor: f64) -> LadderParams {
+        LadderParams { levels, level_spacing_bps: 10.0, size_progression, size_factor }
+    }
+
+    fn assert_quotes(actual: &[Quote], expected: &[(f64, f64)]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (quote, (price, size)) in actual.iter().zip(expected) {
+            assert!((quote.price - price).abs() < 1e-9 && (quote.size - size).abs() < 1e-9, "{:?} is not {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn a_single_level_is_the_plain_quote() {
+        let quote = quote_params();
+        let ladder = LadderParams::default();
+        assert_quotes(&ladder.bids(&quote, 100.0), &[(quote.bid_price(100.0), quote.bid_base_amount(quote.bid_price(100.0)))]);
+        assert_quotes(&ladder.asks(&quote, 100.0), &[(quote.ask_price(100.0), 2.0)]);
+    }
+
+    #[test]
+    fn flat_levels_step_away_at_the_same_size() {
+        let ladder = ladder(3, SizeProgression::Flat, 5.0);
+        assert_quotes(&ladder.bids(&quote_params(), 100.0), &[(99.0, 10.0), (98.9, 990.0 / 98.9), (98.8, 990.0 / 98.8)]);
+        assert_quotes(&ladder.asks(&quote_params(), 100.0), &[(100.5, 2.0), (100.6, 2.0), (100.7, 2.0)]);
+    }
+
+    #[test]
+    fn linear_levels_grow_by_the_factor_each_step() {
+        let ladder = ladder(3, SizeProgression::Linear, 0.5);
+        assert_quotes(&ladder.bids(&quote_params(), 100.0), &[(99.0, 10.0), (98.9, 990.0 / 98.9 * 1.5), (98.8, 990.0 / 98.8 * 2.0)]);
+        assert_quotes(&ladder.asks(&quote_params(), 100.0), &[(100.5, 2.0), (100.6, 3.0), (100.7, 4.0)]);
+    }
+
+    #[test]
+    fn geometric_levels_multiply_by_the_factor_each_step() {
+        let ladder = ladder(3, SizeProgression::Geometric, 2.0);
+        assert_quotes(&ladder.bids(&quote_params(), 100.0), &[(99.0, 10.0), (98.9, 990.0 / 98.9 * 2.0), (98.8, 990.0 / 98.8 * 4.0)]);
+        assert_quotes(&ladder.asks(&quote_params(), 100.0), &[(100.5, 2.0), (100.6, 4.0), (100.7, 8.0)]);
+    }
+
+    #[test]
+    fn validate_bounds_the_levels() {
+        let quote = quote_params();
+        assert!(ladder(1, SizeProgression::Flat, 1.0).validate(&quote).is_ok());
+        assert!(ladder(MAX_LEVELS, SizeProgression::Flat, 1.0).validate(&quote).is_ok());
+        for levels in [0, MAX_LEVELS + 1] {
+            let err = ladder(levels, SizeProgression::Flat, 1.0).validate(&quote).unwrap_err();
+            assert!(err.contains(&format!("levels must be between 1 and {}", MAX_LEVELS)), "{}", err);
+        }
+    }
+
+    #[test]
+    fn validate_keeps_the_furthest_bid_above_zero() {
+        let quote = QuoteParams { bid_spread_bps: 9900.0, ..quote_params() };
+        let mut ladder = ladder(2, SizeProgression::Flat, 1.0);
+        ladder.level_spacing_bps = 99.0;
+        assert!(ladder.validate(&quote).is_ok());
+
+        //exactly at 10000 bps the bid would be at 0
+        ladder.level_spacing_bps = 100.0;
+        let err = ladder.validate(&quote).unwrap_err();
+        assert_eq!(err, "the furthest bid is 10000 bps below the reference price, it must be below 10000");
+
+        ladder.levels = 3;
+        assert!(ladder.validate(&quote).unwrap_err().contains("the furthest bid is 10100 bps"));
+    }
+
+    #[test]
+    fn validate_refuses_non_positive_spacing_and_factors() {
+        let quote = quote_params();
+        let err = LadderParams { level_spacing_bps: 0.0, size_factor: -1.0, ..LadderParams::default() }.validate(&quote).unwrap_err();
+        assert_eq!(err, "level_spacing_bps must be above 0, size_factor must be above 0");
+    }
+
+    #[test]
+    fn validate_caps_the_total_size() {
+        let quote = quote_params();
+        //1 + 2 + ... + 64 = 127
+        assert!(ladder(7, SizeProgression::Geometric, 2.0).validate(&quote).is_err());
+        assert!(ladder(6, SizeProgression::Geometric, 2.0).validate(&quote).is_ok());
+        let err = ladder(32, SizeProgression::Geometric, 2.0).validate(&quote).unwrap_err();
+        assert_eq!(err, "the levels add up to 4294967295 times the first level's size, at most 100 are allowed");
+
+        //1 + 4 + 7 + ... + 28 = 145
+        assert!(ladder(10, SizeProgression::Linear, 3.0).validate(&quote).is_err());
+        assert!(ladder(MAX_LEVELS, SizeProgression::Flat, 1.0).validate(&quote).is_ok());
+        //a shrinking ladder never adds up to much
+        assert!(ladder(MAX_LEVELS, SizeProgression::Geometric, 0.5).validate(&quote).is_ok());
+    }
+}
@@ -1,500 +1,291 @@
-
-use std::num::NonZeroU64;
 use std::str::FromStr;
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
 
-use bytemuck::cast;
-use lazy_static::lazy_static;
-use serum_dex::instruction::{cancel_order_by_client_order_id, consume_events, NewOrderInstructionV3, SelfTradeBehavior, settle_funds};
-use serum_dex::matching::OrderType;
-use serum_dex::matching::Side;
-use serum_dex::state::{Market, MarketState};
-use solana_client::rpc_client::RpcClient;
-use solana_program::account_info::AccountInfo;
-use solana_program::instruction::{AccountMeta, Instruction};
-use solana_sdk::account::{Account, ReadableAccount};
-use solana_sdk::bs58::encode;
-use solana_sdk::commitment_config::CommitmentConfig;
-use solana_sdk::compute_budget::ComputeBudgetInstruction;
 use solana_sdk::pubkey::Pubkey;
-use solana_sdk::transaction::Transaction;
-use tokio::runtime::Runtime;
-use tokio::time::{interval, sleep};
+use tokio::sync::mpsc;
 use uuid::Uuid;
+use async_trait::async_trait;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::serum::serum_utils::pubkey_string;
+use crate::strategies::ladder::LadderParams;
+use crate::strategies::order_executor::{MarketAccounts, OrderExecutor};
+use crate::strategies::quote_params::QuoteParams;
+use crate::strategies::strategy::{DesiredQuotes, MarketSnapshot, Quote, Strategy};
+use crate::strategies::strategy_registry::{BuiltStrategy, StrategyContext, StrategyFuture, StrategyKind};
+use crate::arcana_web_config;
+
+//everything the strategy is created from, anything left out comes from the [bot] section of the config
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenBookSplUsdcConfig {
+    #[serde(with = "pubkey_string")]
+    pub market_id: Pubkey,
+    pub price_strategy: String,
+    //at the top level, the way the add bot form sends them
+    #[serde(flatten)]
+    pub quote: QuoteParams,
+    #[serde(flatten)]
+    pub ladder: LadderParams,
+    #[serde(with = "pubkey_string")]
+    pub ooa: Pubkey,
+    #[serde(with = "pubkey_string")]
+    pub base_wallet: Pubkey,
+    #[serde(with = "pubkey_string")]
+    pub quote_wallet: Pubkey,
+}
 
-use crate::pricing::jupiter_pricing_source::JupiterPricingSource;
-use crate::serum::market::MarketWrapper;
-use crate::serum::market_builder::MarketBuilder;
-use crate::serum::serum_manager::SerumManager;
-use crate::serum::serum_utils;
-use crate::serum::serum_utils::{SERUM_PROGRAM_ID_V3, SerumUtils};
-use crate::serum::serum_utils::pub_key;
-use crate::strategies::strategy::Strategy;
-
-const EVENT_LOOP_INITIAL_DELAY_MS: u64 = 0;
-const EVENT_LOOP_DURATION_MS: u64 = 5000;
-const SOL_QUOTE_SIZE: f64 = 0.1;
-const MIN_MIDPOINT_CHANGE:f64 = 0.0010;
-
-lazy_static!(
-    static ref TOKEN_PROGRAM_ID: Pubkey = Pubkey::from_str("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA").unwrap();
-    static ref SYSVAR_RENT_PUBKEY: Pubkey = Pubkey::from_str("SysvarRent111111111111111111111111111111111").unwrap();
-);
-
-static mut BID_CLIENT_ID: u64 = 0;
-static mut ASK_CLIENT_ID: u64 = 0;
-
-static mut FIRST_LOAD_COMPLETE: bool = false;
+impl Default for OpenBookSplUsdcConfig {
+    fn default() -> Self {
+        let defaults = arcana_web_config::bot_defaults();
+        Self {
+            //validated when the config was loaded
+            market_id: Pubkey::from_str(&defaults.market_id).unwrap(),
+            price_strategy: defaults.price_strategy,
+            quote: QuoteParams::default(),
+            ladder: LadderParams::default(),
+            ooa: Pubkey::from_str("7hM4pmTbyfAUoxU9p8KCqdFfdPTLXc5xFijXsbumaqAa").unwrap(),
+            base_wallet: Pubkey::from_str("3UrEoG5UeE214PYQUA487oJRN89bg6fmt3ejkavmvZ81").unwrap(),
+            quote_wallet: Pubkey::from_str("A6Jcj1XV6QqDpdimmL7jm1gQtSP62j8BWbyqkdhe4eLe").unwrap(),
+        }
+    }
+}
 
-pub struct OpenBookSplUsdc<'a> {
-    rpc_client: RpcClient,
-    //market_builder: MarketBuilder<'a>,
-    sol_usdc_market: MarketWrapper<'a>,
-    jupiter_pricing_source: JupiterPricingSource,
-    serum_manager: SerumManager,
-    mm_account: Account,
-    market_ooa: Pubkey,
-    base_wallet: Pubkey,
-    usdc_wallet: Pubkey,
-    last_bid_order: Option<NewOrderInstructionV3>,
-    last_ask_order: Option<NewOrderInstructionV3>,
+//quotes a ladder of bids and asks spread around a reference price, holding each level until it has moved by
+//min_midpoint_change
+pub struct OpenBookSplUsdc {
     uuid: Uuid,
     use_jupiter: bool,
-    best_bid_price: f64,
-    best_ask_price: f64,
-    base_ask_amount: f64,
-    usdc_bid_amount: f64,
-    ask_spread_multiplier: f64,
-    bid_spread_multiplier: f64,
-    last_placed_bid_price: f64,
-    last_placed_ask_price: f64,
+    //jupiter's price or the book's midpoint, both quotes are spread around it. A failed jupiter fetch keeps the last one
+    reference_price: f64,
+    quote: QuoteParams,
+    ladder: LadderParams,
+    param_updates: mpsc::UnboundedReceiver<QuoteParams>,
+    //set by a params update, the next update of each side replaces it whatever the price did
+    requote_bid: bool,
+    requote_ask: bool,
 }
 
-impl OpenBookSplUsdc<'_> {
-    pub fn new(
-        rpc_client: RpcClient,
-        rpc_client2: RpcClient,//have to do this for the borrow checker to not shout
-        market_id: Pubkey,
-        jupiter_pricing_source: JupiterPricingSource,
-        serum_manager: SerumManager,
-        pricing_strategy: &str,
-        market_ooa: Pubkey,
-        base_wallet: Pubkey,
-        usdc_wallet: Pubkey,
-        mm_account: Account,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut market_builder = MarketBuilder::new(rpc_client2, market_id.clone());
-
-        let market = market_builder.build();
-
-        let mut use_jupiter = false;
-        let mut best_bid_price = 0.;
-        let mut best_ask_price = 0.;
-        if pricing_strategy.eq_ignore_ascii_case("jupiter") {
-            use_jupiter = true;
-            let base_mint = encode(cast::<[u64;4], [u8;32]>(market.market.lock().unwrap().coin_mint)).into_string();
-            if let Some(price) = jupiter_pricing_source.get_usdc_price_for_symbol(&base_mint, 1000) {
-                best_bid_price = price;
-                best_ask_price = price;
-            }
-        }
-
-        unsafe {
-            BID_CLIENT_ID = rand::random();//todo check if this could/should be replaced
-            ASK_CLIENT_ID = rand::random();
-
-            println!("Bid clientId:{} , Ask: {}", BID_CLIENT_ID, ASK_CLIENT_ID);
-        }
-
-        let uuid = Uuid::new_v4();
-
-        Ok(Self {
-            rpc_client,
-            //market_builder,
-            sol_usdc_market: market,
-            jupiter_pricing_source,
-            serum_manager,
-            mm_account,
-            market_ooa,
-            base_wallet,
-            usdc_wallet,
-            last_bid_order: None,
-            last_ask_order: None,
+impl OpenBookSplUsdc {
+    pub fn new(uuid: Uuid, use_jupiter: bool, quote: QuoteParams, ladder: LadderParams, param_updates: mpsc::UnboundedReceiver<QuoteParams>) -> Self {
+        Self {
             uuid,
             use_jupiter,
-            best_bid_price,
-            best_ask_price,
-            base_ask_amount: SOL_QUOTE_SIZE,
-            usdc_bid_amount: SOL_QUOTE_SIZE,
-            ask_spread_multiplier: 1.0012,
-            bid_spread_multiplier: 0.9987,
-            last_placed_bid_price: 0.0,
-            last_placed_ask_price: 0.0,
-        })
+            reference_price: 0.,
+            quote,
+            ladder,
+            param_updates,
+            requote_bid: false,
+            requote_ask: false,
+        }
     }
 
-
-    fn place_sol_ask(
-            &mut self,
-            sol_amount: f64,
-            price: f64,
-            cancel: bool,
-        ) -> Result<(), Box<dyn std::error::Error>>
-    {
-        let sol_usdc_market = &self.sol_usdc_market;
-        let market_lock = self.sol_usdc_market.market.lock().unwrap();
-
-        let mut instructions = vec![
-            ComputeBudgetInstruction::set_compute_unit_price(151_420),
-            ComputeBudgetInstruction::set_compute_unit_limit(54_800),
-            {
-                let mut tmp = consume_events(
-                    &SERUM_PROGRAM_ID_V3,
-                    vec![&self.market_ooa],
-                    &pub_key(market_lock.own_address),
-                    &pub_key(market_lock.event_q),
-                    &self.base_wallet,
-                    &self.usdc_wallet,
-                    5,//this value was found inside the SerumProgram.java implementation
-                ).unwrap();
-                tmp.accounts.push(AccountMeta::new(self.mm_account.owner, true));
-                tmp
-            }
-        ];
-
-        let ask_order = {
-            let mut order = NewOrderInstructionV3 {
-                side: Side::Ask,
-                limit_price: NonZeroU64::new(1).unwrap(),
-                max_coin_qty: NonZeroU64::new(1).unwrap(),
-                order_type: OrderType::PostOnly,
-                client_order_id: unsafe { ASK_CLIENT_ID },
-                self_trade_behavior: SelfTradeBehavior::DecrementTake,
-                max_native_pc_qty_including_fees: NonZeroU64::new(1).unwrap(),
-                limit: 5,//todo what should limit's value be??????
-            };
-
-            self.serum_manager.set_order_prices(&mut order, &sol_usdc_market, price, sol_amount);
-
-            order
+    //only the latest update counts, they were validated when they were made
+    fn apply_param_updates(&mut self) {
+        let mut latest = None;
+        while let Ok(quote) = self.param_updates.try_recv() {
+            latest = Some(quote);
+        }
+        let quote = match latest {
+            Some(quote) => quote,
+            None => return,
         };
-
-        if cancel {
-            instructions.push(
-                cancel_order_by_client_order_id(
-                    &serum_utils::SERUM_PROGRAM_ID_V3,
-                    &pub_key(market_lock.own_address),
-                    &pub_key(market_lock.bids),
-                    &pub_key(market_lock.asks),
-                    &self.market_ooa,
-                    self.mm_account.owner(),
-                    &pub_key(market_lock.event_q),
-                    unsafe { ASK_CLIENT_ID },
-                ).unwrap()
-            );
+        println!("{} now quoting with {}", self.uuid, self.quote.changes(&quote).join(", "));
+        //replace both sides on this update rather than waiting for the price to move
+        if self.quote.requotes(&quote) {
+            self.requote_bid = true;
+            self.requote_ask = true;
         }
+        self.quote = quote;
+    }
 
-        instructions.push(settle_funds(
-            &SERUM_PROGRAM_ID_V3,
-            &pub_key(market_lock.own_address),
-            &TOKEN_PROGRAM_ID,
-            &self.market_ooa,
-            self.mm_account.owner(),
-            &pub_key(market_lock.coin_vault),
-            &self.base_wallet,
-            &pub_key(market_lock.pc_vault),
-            &self.usdc_wallet,
-            Some(&self.usdc_wallet),
-            &SerumUtils::get_vault_signer(&sol_usdc_market),
-        ).unwrap());
-
-        instructions.push(serum_dex::instruction::new_order(
-            &pub_key(market_lock.own_address),
-            &self.market_ooa,
-            &pub_key(market_lock.req_q),
-            &pub_key(market_lock.event_q),
-            &pub_key(market_lock.bids),
-            &pub_key(market_lock.asks),
-            &self.base_wallet,
-            &self.mm_account.owner,
-            &pub_key(market_lock.coin_vault),
-            &pub_key(market_lock.pc_vault),
-            &TOKEN_PROGRAM_ID,
-            &SYSVAR_RENT_PUBKEY,
-            None,
-            &SERUM_PROGRAM_ID_V3,
-            ask_order.side,
-            ask_order.limit_price,
-            ask_order.max_coin_qty,
-            ask_order.order_type,
-            ask_order.client_order_id,
-            ask_order.self_trade_behavior,
-            ask_order.limit,//todo check this out
-            ask_order.max_native_pc_qty_including_fees,
-        ).unwrap());
-
-        //found inside org.p2p.solanaj.programs.MemoProgram
-        let program_id = Pubkey::from_str("Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo").unwrap();
-        instructions.push(
-            Instruction::new_with_bytes(
-                program_id,
-                "Liquidity by Arcana".as_bytes(),
-                vec![AccountMeta::new(self.mm_account.owner, true)],
-            )
-        );
-        drop(program_id);
-
-        let mut place_tx = Transaction::new_with_payer(
-            &instructions,
-            Some(&self.mm_account.owner));//todo check if this value for payer is correct...
+    //each level keeps its resting quote until the new price is min_midpoint_change away from it
+    fn hold_or_replace(&self, resting: &[Quote], requote: bool, levels: Vec<Quote>) -> Vec<Quote> {
+        levels.into_iter()
+            .enumerate()
+            .map(|(level, quote)| match resting.get(level) {
+                Some(resting) if !requote && (1.0 - resting.price / quote.price).abs() < self.quote.min_midpoint_change => *resting,
+                _ => quote,
+            })
+            .collect()
+    }
+}
 
-        let result = self.rpc_client.send_and_confirm_transaction(&place_tx);
+impl StrategyKind for OpenBookSplUsdc {
+    const NAME: &'static str = "openbook_spl_usdc";
+    const DESCRIPTION: &'static str = "Quotes a bid and an ask, or a ladder of levels per side, around the jupiter price or the book's midpoint on an openbook market";
+    type Config = OpenBookSplUsdcConfig;
 
-        match result {
-            Ok(signature) => {
-                println!(
-                    "Base Ask: {} @ {}, Tx Signature: {:?}",
-                    price,
-                    sol_amount,
-                    signature
-                );
-                // Update lastAskOrder
-                // last_ask_order = ask_order.clone();
-                self.last_ask_order = Some(ask_order);
-            }
-            Err(err) => {
-                eprintln!("OrderTx Error: {}", err);
-            }
+    fn validate(config: &OpenBookSplUsdcConfig) -> Result<(), String> {
+        if !config.price_strategy.eq_ignore_ascii_case("jupiter") && !config.price_strategy.eq_ignore_ascii_case("orderbook") {
+            return Err(format!("unknown price strategy {}", config.price_strategy));
         }
-
-        Ok(())
+        config.quote.validate()?;
+        config.ladder.validate(&config.quote)
     }
 
-    fn place_usdc_bid(
-            &mut self,
-            amount: f64,
-            price: f64,
-            cancel: bool,
-        ) -> Result<(), Box<dyn std::error::Error>>
-    {
-        let sol_usdc_market = &self.sol_usdc_market;
-        let market_lock = self.sol_usdc_market.market.lock().unwrap();
-        let mut instructions = vec![
-            ComputeBudgetInstruction::set_compute_unit_price(151_420),
-            ComputeBudgetInstruction::set_compute_unit_limit(54_800),
-            {
-                let mut tmp = consume_events(
-                    &SERUM_PROGRAM_ID_V3,
-                    vec![&self.market_ooa],
-                    &pub_key(market_lock.own_address),
-                    &pub_key(market_lock.event_q),
-                    &self.base_wallet,
-                    &self.usdc_wallet,
-                    5,//this value was found inside the SerumProgram.java implementation
-                ).unwrap();
-                tmp.accounts.push(AccountMeta::new(self.mm_account.owner, true));
-                tmp
-            }
-        ];
-
-        let bid_order = {
-            let mut order = NewOrderInstructionV3 {
-                side: Side::Bid,
-                limit_price: NonZeroU64::new(1).unwrap(),
-                max_coin_qty: NonZeroU64::new(1).unwrap(),
-                order_type: OrderType::PostOnly,
-                client_order_id: unsafe { BID_CLIENT_ID },
-                self_trade_behavior: SelfTradeBehavior::DecrementTake,
-                max_native_pc_qty_including_fees: NonZeroU64::new(1).unwrap(),
-                limit: 5,//todo what should limit's value be??????
+    fn build(config: OpenBookSplUsdcConfig, context: StrategyContext) -> StrategyFuture {
+        Box::pin(async move {
+            let (quote_updates, param_updates) = mpsc::unbounded_channel();
+            let use_jupiter = config.price_strategy.eq_ignore_ascii_case("jupiter");
+            let strategy = OpenBookSplUsdc::new(context.order_state.uuid(), use_jupiter, config.quote, config.ladder, param_updates);
+            let accounts = MarketAccounts {
+                market_id: config.market_id,
+                open_orders: config.ooa,
+                base_wallet: config.base_wallet,
+                quote_wallet: config.quote_wallet,
             };
+            let executor = OrderExecutor::new(Box::new(strategy), accounts, use_jupiter, context).await.map_err(|e| e.to_string())?;
+            Ok(BuiltStrategy {
+                executor,
+                quote_updates: Some(quote_updates),
+            })
+        })
+    }
+}
 
-            self.serum_manager.set_order_prices(&mut order, &sol_usdc_market, price, amount);
-
-            order
+#[async_trait]
+impl Strategy for OpenBookSplUsdc {
+    async fn on_update(&mut self, snapshot: &MarketSnapshot) -> DesiredQuotes {
+        self.apply_param_updates();
+        if self.use_jupiter {
+            if let Some(price) = snapshot.jupiter_price {
+                self.reference_price = price;
+            }
+        }
+        else {
+            self.reference_price = snapshot.midpoint().unwrap_or(0.);
+        }
+        let mut desired = DesiredQuotes {
+            bids: snapshot.resting_bids.clone(),
+            asks: snapshot.resting_asks.clone(),
         };
-
-        if cancel {
-            instructions.push(
-                cancel_order_by_client_order_id(
-                    &SERUM_PROGRAM_ID_V3,
-                    &pub_key(market_lock.own_address),
-                    &pub_key(market_lock.bids),
-                                  &pub_key(market_lock.asks),
-                    &self.market_ooa,
-                    self.mm_account.owner(),
-                    &pub_key(market_lock.event_q),
-                    unsafe { BID_CLIENT_ID },
-                ).unwrap()
-            );
+        //no price to quote around yet, whatever rests stays
+        if self.reference_price <= 0. {
+            return desired;
         }
 
-        instructions.push(settle_funds(
-            &SERUM_PROGRAM_ID_V3,
-            &pub_key(market_lock.own_address),
-            &TOKEN_PROGRAM_ID,
-            &self.market_ooa,
-            self.mm_account.owner(),
-            &pub_key(market_lock.coin_vault),
-            &self.base_wallet,
-            &pub_key(market_lock.pc_vault),
-            &self.usdc_wallet,
-            Some(&self.usdc_wallet),
-            &SerumUtils::get_vault_signer(&sol_usdc_market),
-        ).unwrap());
-
-        instructions.push(serum_dex::instruction::new_order(
-            &pub_key(market_lock.own_address),
-            &self.market_ooa,
-            &pub_key(market_lock.req_q),
-            &pub_key(market_lock.event_q),
-            &pub_key(market_lock.bids),
-            &pub_key(market_lock.asks),
-            &self.usdc_wallet,
-            &self.mm_account.owner,
-            &pub_key(market_lock.coin_vault),
-            &pub_key(market_lock.pc_vault),
-            &TOKEN_PROGRAM_ID,
-            &SYSVAR_RENT_PUBKEY,
-            None,
-            &SERUM_PROGRAM_ID_V3,
-            bid_order.side,
-            bid_order.limit_price,
-            bid_order.max_coin_qty,
-            bid_order.order_type,
-            bid_order.client_order_id,
-            bid_order.self_trade_behavior,
-            bid_order.limit,//todo check this out
-            bid_order.max_native_pc_qty_including_fees,
-        ).unwrap());
-
-        //program_id key found inside org.p2p.solanaj.programs.MemoProgram
-        let program_id = Pubkey::from_str("Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo").unwrap();
-        instructions.push(
-            Instruction::new_with_bytes(
-                program_id,
-                "Liquidity by Arcana".as_bytes(),
-                vec![AccountMeta::new(self.mm_account.owner, true)],
-            )
-        );
-        drop(program_id);
-
-        let mut place_tx = Transaction::new_with_payer(
-            &instructions,
-            Some(&self.mm_account.owner));//todo check if this value for payer is correct...
-
-        let result = self.rpc_client.send_and_confirm_transaction(&place_tx);
-
-        match result {
-            Ok(signature) => {
-                println!(
-                    "Base Ask: {} @ {}, Tx Signature: {:?}",
-                    amount,
-                    price,
-                    signature
-                );
-                // Update lastAskOrder
-                self.last_ask_order = Some(bid_order);
-            }
-            Err(err) => {
-                eprintln!("OrderTx Error: {}", err);
-            }
+        //a side with a transaction in flight holds what it sent until the outcome is in
+        if !snapshot.bid_in_flight {
+            let bids = self.ladder.bids(&self.quote, self.reference_price);
+            desired.bids = self.hold_or_replace(&snapshot.resting_bids, self.requote_bid, bids);
+            self.requote_bid = false;
         }
-        Ok(())
+        if !snapshot.ask_in_flight {
+            let asks = self.ladder.asks(&self.quote, self.reference_price);
+            desired.asks = self.hold_or_replace(&snapshot.resting_asks, self.requote_ask, asks);
+            self.requote_ask = false;
+        }
+        desired
     }
 }
 
-impl Strategy for OpenBookSplUsdc<'_> {
-    fn uuid(&self) -> Uuid {
-        return self.uuid;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::ladder::SizeProgression;
+
+    fn quote_params() -> QuoteParams {
+        QuoteParams {
+            bid_spread_bps: 100.0,
+            ask_spread_bps: 50.0,
+            amount_bid: 990.0,
+            amount_ask: 2.0,
+            min_midpoint_change: 0.01,
+        }
     }
 
-    fn start(&mut self, executor: &Runtime) {
-        //let delay = Duration::from_millis(EVENT_LOOP_INITIAL_DELAY_MS);
-        let duration = Duration::from_millis(EVENT_LOOP_DURATION_MS);
-        let jupiter_pricing_source_clone = self.jupiter_pricing_source.clone();
-
-        executor.block_on(async move {
-            let mut interval = interval(duration);
-            loop {
-                interval.tick().await;
-                //let mut sol_usdc_market = &mut self.sol_usdc_market;
-                self.sol_usdc_market.reload();
-                let market_lock = self.sol_usdc_market.market.lock().unwrap();
-
-
-                if self.use_jupiter {
-                    if let Some(price) = jupiter_pricing_source_clone.get_usdc_price_for_symbol(&*pub_key(market_lock.coin_mint).to_string(), 1000){
-                        self.best_bid_price = price;
-                        self.best_ask_price = price;
-                    }
-                }
-                else {
-                    self.best_bid_price = self.sol_usdc_market.bid_order_book.get_best_bid_price();
-                    self.best_ask_price = self.sol_usdc_market.ask_order_book.get_best_ask_price();
-                }
+    fn strategy(use_jupiter: bool, levels: usize) -> (OpenBookSplUsdc, mpsc::UnboundedSender<QuoteParams>) {
+        let ladder = LadderParams { levels, level_spacing_bps: 10.0, size_progression: SizeProgression::Flat, size_factor: 1.0 };
+        let (quote_updates, param_updates) = mpsc::unbounded_channel();
+        (OpenBookSplUsdc::new(Uuid::nil(), use_jupiter, quote_params(), ladder, param_updates), quote_updates)
+    }
 
-                //dropping so that self can be borrowed as mutable later
-                drop(market_lock);
+    //a book whose midpoint is 100
+    fn snapshot() -> MarketSnapshot {
+        MarketSnapshot { best_bid: 99.0, best_ask: 101.0, ..Default::default() }
+    }
 
-                //todo this most likely does not work
-                // let bids_pubkey = pub_key(market_lock.bids);
-                // let bid_account_info = account_info(bids_pubkey, self.rpc_client.get_account_with_commitment(&bids_pubkey, CommitmentConfig::processed()).unwrap().value.unwrap());
-                // let orders = market_lock.load_orders_mut(//todo implement this
-                //     &bid_account_info,
-                //     Some(&bid_account_info),
-                //     &SERUM_PROGRAM_ID_V3,
-                //     None,
-                //     None
-                // );
-                // let is_cancel_bid = orders.is_ok();
-                let is_cancel_bid = false;
+    fn assert_quotes(actual: &[Quote], expected: &[(f64, f64)]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (quote, (price, size)) in actual.iter().zip(expected) {
+            assert!((quote.price - price).abs() < 1e-9 && (quote.size - size).abs() < 1e-9, "{:?} is not {:?}", actual, expected);
+        }
+    }
 
-                let percentage_change_from_last_bid =
-                1.0 - (self.last_placed_bid_price / (self.best_bid_price * self.bid_spread_multiplier));
+    #[tokio::test]
+    async fn quotes_a_ladder_around_the_midpoint() {
+        let (mut strategy, _updates) = strategy(false, 2);
+        let desired = strategy.on_update(&snapshot()).await;
+        assert_quotes(&desired.bids, &[(99.0, 10.0), (98.9, 990.0 / 98.9)]);
+        assert_quotes(&desired.asks, &[(100.5, 2.0), (100.6, 2.0)]);
+    }
 
-                // Only place bid if we haven't placed, or the change is >= 0.1% change
-                if self.last_placed_bid_price == 0. || percentage_change_from_last_bid.abs() >= MIN_MIDPOINT_CHANGE {
-                    self.place_usdc_bid(self.usdc_bid_amount, self.best_bid_price * self.bid_spread_multiplier, is_cancel_bid).unwrap();
-                    self.last_placed_bid_price = self.best_bid_price * self.bid_spread_multiplier;
-                }
+    #[tokio::test]
+    async fn quotes_nothing_without_a_reference_price() {
+        let (mut strategy, _updates) = strategy(false, 1);
+        let one_sided = MarketSnapshot { best_bid: 99.0, ..Default::default() };
+        assert_eq!(strategy.on_update(&one_sided).await, DesiredQuotes::default());
 
-                // //todo this most likely does not work
-                // let asks_pubkey = pub_key(market_lock.asks);
-                // let ask_account_info = serum_utils::account_info(asks_pubkey, self.rpc_client.get_account_with_commitment(&asks_pubkey, CommitmentConfig::processed()).unwrap().value.unwrap());
-                // let orders = market_lock.load_orders_mut(
-                //     &ask_account_info,
-                //     Some(&ask_account_info),
-                //     &SERUM_PROGRAM_ID_V3,
-                //     None,
-                //     None
-                // );
+        //jupiter hasn't answered yet, the book's midpoint isn't used instead
+        let (mut strategy, _updates) = self::strategy(true, 1);
+        assert_eq!(strategy.on_update(&snapshot()).await, DesiredQuotes::default());
+    }
 
-                //let is_cancel_ask = orders.is_ok();
-                let is_cancel_ask = false;
+    #[tokio::test]
+    async fn prices_off_jupiter_and_keeps_the_last_price_when_a_fetch_fails() {
+        let (mut strategy, _updates) = strategy(true, 1);
+        let desired = strategy.on_update(&MarketSnapshot { jupiter_price: Some(200.0), ..snapshot() }).await;
+        assert_quotes(&desired.bids, &[(198.0, 5.0)]);
+        assert_quotes(&desired.asks, &[(201.0, 2.0)]);
 
-                let percentage_change_from_last_ask =
-                    1.0 - (self.last_placed_ask_price / (self.best_ask_price * self.ask_spread_multiplier));
+        let desired = strategy.on_update(&MarketSnapshot { jupiter_price: None, ..snapshot() }).await;
+        assert_quotes(&desired.bids, &[(198.0, 5.0)]);
+        assert_quotes(&desired.asks, &[(201.0, 2.0)]);
+    }
 
-                if self.last_placed_ask_price == 0. || percentage_change_from_last_ask.abs() >= MIN_MIDPOINT_CHANGE {
-                    self.place_sol_ask(self.usdc_bid_amount, self.best_bid_price * self.bid_spread_multiplier, is_cancel_ask).unwrap();
-                    self.last_placed_bid_price = self.best_ask_price * self.ask_spread_multiplier;
-                }
+    #[tokio::test]
+    async fn holds_resting_quotes_until_the_price_has_moved_far_enough() {
+        let (mut strategy, _updates) = strategy(false, 1);
+        //the bid is 0.5% from where it would go, the ask 10%
+        let resting_bid = Quote { price: 99.5, size: 3.0 };
+        let resting_ask = Quote { price: 90.0, size: 3.0 };
+        let desired = strategy.on_update(&MarketSnapshot {
+            resting_bids: vec![resting_bid],
+            resting_asks: vec![resting_ask],
+            ..snapshot()
+        }).await;
+        assert_eq!(desired.bids, vec![resting_bid]);
+        assert_quotes(&desired.asks, &[(100.5, 2.0)]);
+    }
 
-                if unsafe { !FIRST_LOAD_COMPLETE } {
-                    println!("Sleeping 2000ms");
-                    sleep(Duration::from_millis(2000)).await;
-                    println!("First Load Complete");
+    #[tokio::test]
+    async fn a_side_in_flight_keeps_what_it_sent() {
+        let (mut strategy, _updates) = strategy(false, 1);
+        let sent = Quote { price: 50.0, size: 1.0 };
+        let desired = strategy.on_update(&MarketSnapshot {
+            resting_bids: vec![sent],
+            bid_in_flight: true,
+            ..snapshot()
+        }).await;
+        assert_eq!(desired.bids, vec![sent]);
+        assert_quotes(&desired.asks, &[(100.5, 2.0)]);
+    }
 
-                    unsafe { FIRST_LOAD_COMPLETE = true; }
-                }
-            }
-        });
+    #[tokio::test]
+    async fn a_params_update_replaces_quotes_the_price_would_have_held() {
+        let (mut strategy, updates) = strategy(false, 1);
+        let resting = MarketSnapshot {
+            resting_bids: vec![Quote { price: 99.0, size: 10.0 }],
+            resting_asks: vec![Quote { price: 100.5, size: 2.0 }],
+            ..snapshot()
+        };
+        updates.send(QuoteParams { amount_ask: 4.0, ..quote_params() }).unwrap();
+        let desired = strategy.on_update(&resting).await;
+        assert_quotes(&desired.bids, &[(99.0, 10.0)]);
+        assert_quotes(&desired.asks, &[(100.5, 4.0)]);
+
+        //only the update after the change is forced, then resting quotes are held again
+        let desired = strategy.on_update(&MarketSnapshot { resting_asks: vec![Quote { price: 100.6, size: 4.0 }], ..resting }).await;
+        assert_quotes(&desired.asks, &[(100.6, 4.0)]);
     }
 }
-
-
-//todo fix this (all of these Send+Sync errors are because of the MarketState struct)
-unsafe impl Send for OpenBookSplUsdc<'_> {}
-unsafe impl Sync for OpenBookSplUsdc<'_> {}
\ No newline at end of file
@@ -1,35 +1,125 @@
-use std::str::FromStr;
 use std::sync::{Arc, Mutex};
-use solana_sdk::pubkey::Pubkey;
-use tokio::runtime::Runtime;
-use crate::strategies::strategy::Strategy;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use crate::strategies::bot_lifecycle::{BotControl, BotState};
+use crate::strategies::ladder::LadderParams;
+use crate::strategies::quote_params::{QuoteParams, QuoteParamsUpdate};
+use crate::strategies::order_executor::OrderExecutor;
+use crate::strategies::strategy_registry::{BotConfig, BuiltStrategy};
+use crate::transactions::priority_fees::FeeTracker;
+
+#[derive(Serialize, Deserialize)]
+pub struct BotSummary {
+    pub uuid: String,
+    pub strategy: String,
+    pub state: BotState,
+    pub last_error: Option<String>,
+    pub restarts: u32,
+    pub params: BotConfig,
+    pub fees: FeeSpend,
+}
+
+//what the bot's transactions have cost since it was built, base and priority fees together
+#[derive(Serialize, Deserialize)]
+pub struct FeeSpend {
+    pub sol_spent: f64,
+    pub priority_sol_spent: f64,
+    pub transactions: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BotHealth {
+    pub last_error: Option<String>,
+    //restarts since the bot was last started by hand
+    pub restarts: u32,
+}
 
 pub struct OpenBookBot {
-    pub strategy: Option<Box<dyn Strategy>>,
-    pub strategy_executor: Option<Runtime>,
-    market_id: Pubkey,
-    bps_spread: f64,
-    amount_bid: f64,
-    amount_ask: f64,
-    ooa: Pubkey,
-    base_wallet: Pubkey,
-    quote_wallet: Pubkey,
-    price_strategy: String,
+    pub uuid: Uuid,
+    //runs the strategy, locked by the strategy task for as long as the loop runs so a restart waits for the previous
+    //loop to return
+    pub order_executor: Arc<tokio::sync::Mutex<OrderExecutor>>,
+    pub strategy_name: String,
+    pub strategy_task: Option<JoinHandle<()>>,
+    //only there while the bot is active, stopping cancels it
+    pub control: Option<BotControl>,
+    //the strategy task moves the bot to Running, Stopped or Failed itself
+    pub state: Arc<Mutex<BotState>>,
+    //kept up to date by the supervisor in the strategy task
+    pub health: Arc<Mutex<BotHealth>>,
+    pub fee_tracker: Arc<FeeTracker>,
+    config: BotConfig,
+    //the strategy picks these up on its next tick, or when it is next started. None for a strategy that doesn't quote
+    quote_updates: Option<mpsc::UnboundedSender<QuoteParams>>,
 }
 
 impl OpenBookBot {
-    pub fn new() -> Self {
+    //the strategy is built by the registry, see BotManager::prepare_bot
+    pub fn new(uuid: Uuid, config: BotConfig, built: BuiltStrategy, fee_tracker: Arc<FeeTracker>) -> Self {
         Self {
-            strategy: None,
-            strategy_executor: None,
-            market_id: Pubkey::from_str("9Lyhks5bQQxb9EyyX55NtgKQzpM4WK7JCmeaWuQ5MoXD").unwrap(),
-            bps_spread: 10.0,
-            amount_bid: 0.1,
-            amount_ask: 0.1,
-            ooa: Pubkey::from_str("7hM4pmTbyfAUoxU9p8KCqdFfdPTLXc5xFijXsbumaqAa").unwrap(),
-            base_wallet: Pubkey::from_str("3UrEoG5UeE214PYQUA487oJRN89bg6fmt3ejkavmvZ81").unwrap(),
-            quote_wallet: Pubkey::from_str("A6Jcj1XV6QqDpdimmL7jm1gQtSP62j8BWbyqkdhe4eLe").unwrap(),
-            price_strategy: "jupiter".to_string(),
+            uuid,
+            strategy_name: config.strategy.clone(),
+            order_executor: Arc::new(tokio::sync::Mutex::new(built.executor)),
+            strategy_task: None,
+            control: None,
+            state: Arc::new(Mutex::new(BotState::Stopped)),
+            health: Arc::new(Mutex::new(BotHealth::default())),
+            fee_tracker,
+            config,
+            quote_updates: built.quote_updates,
+        }
+    }
+
+    pub fn config(&self) -> &BotConfig {
+        &self.config
+    }
+
+    //validated here so a bad update is refused rather than dropped by the strategy. Returns what changed
+    pub fn update_quote_params(&mut self, update: &QuoteParamsUpdate) -> Result<Vec<String>, String> {
+        let quote_updates = self.quote_updates.as_ref()
+            .ok_or_else(|| format!("the {} strategy doesn't take quote params", self.config.strategy))?;
+        //a quoting strategy's config has the quote params at its top level
+        let current: QuoteParams = serde_json::from_value(Value::Object(self.config.config.clone()))
+            .map_err(|e| format!("bot {} has no quote params: {}", self.uuid, e))?;
+        let quote = update.apply(&current);
+        quote.validate()?;
+        //a wider bid spread can push the ladder's furthest bid to 0, the ladder sits at the top level too
+        let ladder: LadderParams = serde_json::from_value(Value::Object(self.config.config.clone()))
+            .map_err(|e| format!("bot {} has no ladder params: {}", self.uuid, e))?;
+        ladder.validate(&quote)?;
+        let changes = current.changes(&quote);
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+        //the receiver lives as long as the strategy, which lives as long as the bot
+        quote_updates.send(quote).map_err(|_| format!("bot {} is no longer taking updates", self.uuid))?;
+        if let Ok(Value::Object(fields)) = serde_json::to_value(quote) {
+            self.config.config.extend(fields);
+        }
+        Ok(changes)
+    }
+
+    pub fn state(&self) -> BotState {
+        *self.state.lock().unwrap()
+    }
+
+    pub fn summary(&self) -> BotSummary {
+        let health = self.health.lock().unwrap().clone();
+        BotSummary {
+            uuid: self.uuid.to_string(),
+            strategy: self.strategy_name.clone(),
+            state: self.state(),
+            last_error: health.last_error,
+            restarts: health.restarts,
+            params: self.config.clone(),
+            fees: FeeSpend {
+                sol_spent: self.fee_tracker.sol_spent(),
+                priority_sol_spent: self.fee_tracker.priority_sol_spent(),
+                transactions: self.fee_tracker.transaction_count(),
+            },
         }
     }
 }
@@ -38,5 +128,3 @@ impl OpenBookBot {
 //todo idk what to do about this, this is very unsafe and most probably requires restructuring the whole codebase
 // unsafe impl Send for OpenBookBot {}
 // unsafe impl Sync for OpenBookBot {}
-
-//strategy: Box<dyn Strategy>, market_id: Pubkey, bps_spread: f64, amount_bid: f64, amount_ask: f64
\ No newline at end of file
@@ -0,0 +1,801 @@
+
+use std::collections::HashSet;
+use std::num::NonZeroU64;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serum_dex::instruction::{cancel_order, consume_events, NewOrderInstructionV3, SelfTradeBehavior, settle_funds};
+use serum_dex::matching::OrderType;
+use serum_dex::matching::Side;
+use serum_dex::state::MarketState;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::Signer;
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
+use solana_sdk::message::{v0, VersionedMessage};
+use solana_sdk::transaction::VersionedTransaction;
+use tokio::time::interval;
+use uuid::Uuid;
+
+use crate::pricing::jupiter_pricing_source::JupiterPricingSource;
+use crate::rpc::chain_client::ChainClient;
+use crate::serum::market::MarketWrapper;
+use crate::serum::market_builder::MarketBuilder;
+use crate::serum::market_feed::{MarketFeed, MarketUpdate, OpenOrdersSnapshot};
+use crate::serum::open_orders::{cancel_all, cancel_all_instructions, settle_instruction, OPEN_ORDERS_SLOTS};
+use crate::serum::serum_manager::SerumManager;
+use crate::serum::serum_utils::{SERUM_PROGRAM_ID_V3, SerumUtils, SYSVAR_RENT_PUBKEY, TOKEN_PROGRAM_ID};
+use crate::serum::serum_utils::pub_key;
+use crate::strategies::bot_lifecycle::BotControl;
+use crate::strategies::bot_store::OrderStateRecorder;
+use crate::strategies::client_order_ids::ClientOrderIds;
+use crate::strategies::order_tracker::OrderTracker;
+use crate::strategies::reconciler::{reconcile, RestingOrder, SidePlan};
+use crate::strategies::strategy::{DesiredQuotes, MarketSnapshot, Quote, Strategy};
+use crate::strategies::strategy_registry::StrategyContext;
+use crate::transactions::direct_send::send_and_confirm;
+use crate::transactions::confirmation_tracker::{ConfirmationTracker, TransactionStatus};
+use crate::transactions::emergency_cancel::{create_nonce_account, nonce_blockhash, sign_with_nonce, EmergencyCancels, CANCELS_PER_NONCE_TRANSACTION};
+use crate::transactions::lookup_tables::{LookupTableManager, market_static_accounts};
+use crate::transactions::priority_fees::{FeeEstimate, FeeTracker, PriorityFeePolicy};
+use crate::arcana_web_config;
+
+//a tick whose orders couldn't be sent is retried on the next one, this many in a row and the loop gives up so the
+//bot manager can restart it
+const MAX_FAILED_TICKS: u32 = 5;
+
+//what a tracked transaction was for, handed back with its outcome
+struct SubmittedOrder {
+    side: Side,
+    price: f64,
+    fee_estimate: FeeEstimate,
+    signatures: usize,
+    //the orders it places
+    client_order_ids: Vec<u64>,
+    //order ids the transaction cancels
+    cancels: Vec<u128>,
+}
+
+//the accounts a bot trades through on its market
+pub struct MarketAccounts {
+    pub market_id: Pubkey,
+    pub open_orders: Pubkey,
+    pub base_wallet: Pubkey,
+    pub quote_wallet: Pubkey,
+}
+
+//runs a strategy against an openbook market: gathers a snapshot every tick, hands it to the strategy and turns the
+//quotes that come back into order transactions. Everything that talks to the chain lives here
+pub struct OrderExecutor {
+    strategy: Box<dyn Strategy>,
+    chain_client: Arc<dyn ChainClient>,
+    market: MarketWrapper,
+    jupiter_pricing_source: JupiterPricingSource,
+    //only fetched for strategies that price off jupiter, it is a http round trip every tick
+    fetch_jupiter_price: bool,
+    serum_manager: SerumManager,
+    //the trading account, pays for and signs every order transaction
+    owner: Pubkey,
+    signer: Arc<dyn Signer + Send + Sync>,
+    fee_policy: PriorityFeePolicy,
+    fee_tracker: Arc<FeeTracker>,
+    order_state: Arc<OrderStateRecorder>,
+    client_order_ids: ClientOrderIds,
+    //orders a cancel was sent for, so one that leaves the book is known to have been cancelled rather than filled
+    cancelling: HashSet<u128>,
+    emergency_cancels: Arc<EmergencyCancels>,
+    //the nonce accounts and the blockhash each currently holds, empty until the first run has set them up
+    nonces: Vec<(Pubkey, Hash)>,
+    emergency_cancel_stale: bool,
+    tx_tracker: ConfirmationTracker<SubmittedOrder>,
+    lookup_table: Option<AddressLookupTableAccount>,
+    market_feed: MarketFeed,
+    open_orders: Option<OpenOrdersSnapshot>,
+    market_ooa: Pubkey,
+    base_wallet: Pubkey,
+    usdc_wallet: Pubkey,
+    order_tracker: OrderTracker,
+    uuid: Uuid,
+    loop_interval: Duration,
+    resting_bids: Vec<Quote>,
+    resting_asks: Vec<Quote>,
+    //transactions per side still in flight, a side with any is left alone, the open orders account doesn't show
+    //their orders yet
+    bid_in_flight: usize,
+    ask_in_flight: usize,
+    //new orders of both sides in those transactions, they will take open orders slots the account doesn't show yet
+    orders_in_flight: usize,
+}
+
+impl OrderExecutor {
+    pub async fn new(
+        strategy: Box<dyn Strategy>,
+        accounts: MarketAccounts,
+        fetch_jupiter_price: bool,
+        context: StrategyContext,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let StrategyContext { order_state, emergency_cancels, chain_client, jupiter_pricing_source, signer, fee_tracker, settings } = context;
+        let market_id = accounts.market_id;
+        let market_ooa = accounts.open_orders;
+        let mut market_builder = MarketBuilder::new(chain_client.clone(), market_id);
+
+        let market = market_builder.build().await?;
+
+        let uuid = order_state.uuid();
+        let tx_tracker = ConfirmationTracker::new(chain_client.clone());
+        let market_feed = MarketFeed::new(settings.ws_url, chain_client.clone(), &market, market_ooa);
+
+        //without a table the order transactions still go out as v0, just with every account inline
+        let lookup_table = {
+            let vault_signer = SerumUtils::get_vault_signer(&market);
+            let static_accounts = market_static_accounts(&market.market.lock().unwrap(), &vault_signer);
+            match LookupTableManager::new(signer.clone()).market_lookup_table(chain_client.as_ref(), &market_id, &static_accounts).await {
+                Ok(lookup_table) => Some(lookup_table),
+                Err(err) => {
+                    eprintln!("Error preparing lookup table for market {}: {}", market_id, err);
+                    None
+                }
+            }
+        };
+
+        Ok(Self {
+            strategy,
+            chain_client,
+            market,
+            jupiter_pricing_source,
+            fetch_jupiter_price,
+            serum_manager: SerumManager,
+            owner: signer.pubkey(),
+            signer,
+            fee_policy: arcana_web_config::priority_fee_policy(),
+            fee_tracker,
+            client_order_ids: ClientOrderIds::new(order_state.clone()),
+            cancelling: HashSet::new(),
+            order_state,
+            emergency_cancels,
+            nonces: Vec::new(),
+            emergency_cancel_stale: false,
+            tx_tracker,
+            lookup_table,
+            market_feed,
+            open_orders: None,
+            market_ooa,
+            base_wallet: accounts.base_wallet,
+            usdc_wallet: accounts.quote_wallet,
+            order_tracker: OrderTracker::new(uuid),
+            uuid,
+            loop_interval: settings.loop_interval,
+            resting_bids: Vec::new(),
+            resting_asks: Vec::new(),
+            bid_in_flight: 0,
+            ask_in_flight: 0,
+            orders_in_flight: 0,
+        })
+    }
+
+    //runs the loop until control is cancelled, quoting nothing while it is paused. An error is the reason the
+    //loop gave up, the bot manager decides whether to run it again
+    pub async fn run(&mut self, control: BotControl) -> Result<(), String> {
+        self.tx_tracker.start();
+        self.market_feed.start();
+        //nonces that were advanced by a broadcast since the last run are picked up again here
+        if let Err(err) = self.prepare_emergency_cancel().await {
+            eprintln!("Error preparing emergency cancel for {}: {}", self.market_ooa, err);
+        }
+
+        let mut interval = interval(self.loop_interval);
+        let mut failed_ticks = 0;
+        loop {
+            //market updates wake the loop straight away, the interval keeps quotes fresh when the book is quiet
+            tokio::select! {
+                _ = control.cancelled() => break,
+                _ = interval.tick() => {}
+                Some(update) = self.market_feed.next_update() => self.handle_market_update(update),
+            }
+            //a burst of updates only needs one requote
+            while let Some(update) = self.market_feed.try_next_update() {
+                self.handle_market_update(update);
+            }
+            self.handle_transaction_outcomes();
+            if self.emergency_cancel_stale {
+                if let Err(err) = self.refresh_emergency_cancel().await {
+                    eprintln!("Error signing emergency cancel for {}: {}", self.market_ooa, err);
+                }
+            }
+            if control.is_paused() {
+                continue;
+            }
+            self.market.reload();
+            self.forget_vanished();
+
+            let snapshot = self.snapshot().await;
+            let desired = self.strategy.on_update(&snapshot).await;
+            match self.execute(desired).await {
+                Ok(()) => failed_ticks = 0,
+                Err(err) => {
+                    failed_ticks += 1;
+                    if failed_ticks >= MAX_FAILED_TICKS {
+                        return Err(format!("orders failed to go out {} ticks in a row: {}", failed_ticks, err));
+                    }
+                }
+            }
+        }
+        println!("{} stopped, {} transactions still in flight", self.uuid, self.tx_tracker.in_flight());
+        Ok(())
+    }
+
+    //pulls every resting order, returns how many there were
+    pub async fn cancel_orders(&mut self) -> Result<usize, String> {
+        let market_state = *self.market.market.lock().unwrap();
+        let cancelled = cancel_all(self.chain_client.as_ref(), &market_state, &self.market_ooa, self.signer.clone()).await
+            .map_err(|e| format!("Error cancelling orders on {}: {}", self.market_ooa, e))?;
+        //nothing is resting any more, the next run places both sides from scratch
+        self.resting_bids.clear();
+        self.resting_asks.clear();
+        self.order_state.update(|state| {
+            state.last_bid_price = 0.0;
+            state.last_ask_price = 0.0;
+            state.open_orders.clear();
+        });
+        self.emergency_cancels.set(self.market_ooa, Vec::new());
+        Ok(cancelled)
+    }
+
+    //cancels every order and brings the funds back to the wallets, waiting for confirmation. Called once the loop
+    //has returned, when the process is about to exit
+    pub async fn shutdown(&mut self) -> Result<usize, String> {
+        let cancelled = self.cancel_orders().await?;
+        let settle = settle_instruction(&self.market, &self.market_ooa, &self.owner, &self.base_wallet, &self.usdc_wallet)
+            .map_err(|e| format!("Error building settle for {}: {}", self.market_ooa, e))?;
+        let signature = send_and_confirm(self.chain_client.as_ref(), &[settle], vec![self.signer.clone()]).await
+            .map_err(|e| format!("Error settling {}: {}", self.market_ooa, e))?;
+        println!("Settled {}, Tx Signature: {}", self.market_ooa, signature);
+        Ok(cancelled)
+    }
+
+    async fn snapshot(&self) -> MarketSnapshot {
+        let jupiter_price = if self.fetch_jupiter_price {
+            let coin_mint = pub_key(self.market.market.lock().unwrap().coin_mint);
+            self.jupiter_pricing_source.get_usdc_price_for_symbol(&coin_mint.to_string(), 1000).await
+        } else {
+            None
+        };
+        MarketSnapshot {
+            best_bid: self.market.bid_order_book.get_best_bid_price(),
+            best_ask: self.market.ask_order_book.get_best_ask_price(),
+            jupiter_price,
+            resting_bids: self.resting_bids.clone(),
+            resting_asks: self.resting_asks.clone(),
+            bid_in_flight: self.bid_in_flight > 0,
+            ask_in_flight: self.ask_in_flight > 0,
+        }
+    }
+
+    //a side with none of its quotes left in the open orders account was filled or cancelled from elsewhere, the
+    //strategy quotes that side afresh
+    fn forget_vanished(&mut self) {
+        let snapshot = match &self.open_orders {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+        for side in [Side::Bid, Side::Ask] {
+            let (resting, in_flight) = match side {
+                Side::Bid => (&mut self.resting_bids, self.bid_in_flight),
+                Side::Ask => (&mut self.resting_asks, self.ask_in_flight),
+            };
+            if in_flight > 0 || resting.is_empty() || snapshot.orders.iter().any(|order| order.side == side) {
+                continue;
+            }
+            println!("{:?} quotes from {} are no longer resting, filled or cancelled", side, resting[0].price);
+            resting.clear();
+        }
+    }
+
+    //compares what the strategy wants with the orders actually resting and sends what it takes to get there. A side
+    //that fails is left for the next tick, the other side still goes out. The error is the last one either side hit
+    async fn execute(&mut self, desired: DesiredQuotes) -> Result<(), String> {
+        //nothing is known to rest until the feed has read the open orders account
+        let resting = match &self.open_orders {
+            Some(snapshot) => RestingOrder::from_open_orders(snapshot, &self.market),
+            None => return Ok(()),
+        };
+        let market = &self.market;
+        let plan = reconcile(&desired, &resting, |price| SerumUtils::price_number_to_lots_market(price, market));
+        //shared by both sides' plans
+        let mut free_slots = OPEN_ORDERS_SLOTS.saturating_sub(resting.len() + self.orders_in_flight);
+        let mut result = Ok(());
+
+        for (side, quotes, plan) in [(Side::Bid, desired.bids, plan.bid), (Side::Ask, desired.asks, plan.ask)] {
+            let in_flight = match side {
+                Side::Bid => self.bid_in_flight,
+                Side::Ask => self.ask_in_flight,
+            };
+            if in_flight > 0 {
+                continue;
+            }
+            //a cancel only transaction is logged against the first level that stays
+            let price = plan.place.first().map(|quote| quote.price)
+                .or(plan.keep.first().map(|keep| keep.price))
+                .unwrap_or(0.);
+            let mut failed = false;
+            for batch in plan.batches(&mut free_slots) {
+                if let Err(err) = self.submit_plan(side, &batch, price).await {
+                    eprintln!("OrderTx Error: {}", err);
+                    result = Err(format!("{:?} orders: {}", side, err));
+                    failed = true;
+                    break;
+                }
+            }
+            if failed {
+                continue;
+            }
+            //placed, already resting or left for a later tick, either way it is what the side quotes now
+            match side {
+                Side::Bid => self.resting_bids = quotes,
+                Side::Ask => self.resting_asks = quotes,
+            }
+        }
+        result
+    }
+
+    //signs and hands the transaction to the confirmation tracker, the outcome arrives on a later tick
+    async fn send_instructions(&self, instructions: Vec<Instruction>, writable_accounts: &[Pubkey], side: Side, price: f64, client_order_ids: Vec<u64>, cancels: Vec<u128>) -> Result<Signature, Box<dyn std::error::Error>> {
+        let payer = self.owner;
+        let lookup_tables: Vec<AddressLookupTableAccount> = self.lookup_table.iter().cloned().collect();
+        let fee_estimate = self.fee_policy.estimate(self.chain_client.as_ref(), &payer, &instructions, &lookup_tables, writable_accounts).await;
+
+        let mut budgeted_instructions = fee_estimate.budget_instructions();
+        budgeted_instructions.extend(instructions);
+
+        let (recent_blockhash, last_valid_block_height) =
+            self.chain_client.get_latest_blockhash(CommitmentConfig::confirmed()).await?;
+        let message = v0::Message::try_compile(&payer, &budgeted_instructions, &lookup_tables, recent_blockhash)?;
+        //signing can be a round trip to a remote signer over blocking io, so it gets its own thread
+        let signer = self.signer.clone();
+        let place_tx = tokio::task::spawn_blocking(move || {
+            VersionedTransaction::try_new(VersionedMessage::V0(message), &[signer.as_ref() as &dyn Signer])
+        }).await??;
+
+        let signatures = place_tx.signatures.len();
+        let signature = self.tx_tracker.submit(place_tx, last_valid_block_height, SubmittedOrder {
+            side,
+            price,
+            fee_estimate,
+            signatures,
+            client_order_ids,
+            cancels,
+        });
+
+        Ok(signature)
+    }
+
+    fn handle_transaction_outcomes(&mut self) {
+        while let Some(outcome) = self.tx_tracker.try_next_outcome() {
+            let order = outcome.tag;
+            match order.side {
+                Side::Bid => self.bid_in_flight = self.bid_in_flight.saturating_sub(1),
+                Side::Ask => self.ask_in_flight = self.ask_in_flight.saturating_sub(1),
+            }
+            self.orders_in_flight = self.orders_in_flight.saturating_sub(order.client_order_ids.len());
+            let reason = match outcome.status {
+                TransactionStatus::Confirmed { slot } => {
+                    self.fee_tracker.record(&order.fee_estimate, order.signatures);
+                    println!("{:?} @ {} confirmed in slot {}, Tx Signature: {}", order.side, order.price, slot, outcome.signature);
+                    for client_order_id in &order.client_order_ids {
+                        self.order_tracker.confirmed(*client_order_id);
+                    }
+                    continue;
+                }
+                TransactionStatus::Failed { slot, error } => {
+                    self.fee_tracker.record(&order.fee_estimate, order.signatures);
+                    eprintln!("{:?} @ {} failed in slot {}: {}, Tx Signature: {}", order.side, order.price, slot, error, outcome.signature);
+                    format!("failed in slot {}: {}", slot, error)
+                }
+                TransactionStatus::Rejected { error } => {
+                    eprintln!("{:?} @ {} rejected: {}, Tx Signature: {}", order.side, order.price, error, outcome.signature);
+                    format!("rejected: {}", error)
+                }
+                TransactionStatus::Expired => {
+                    eprintln!("{:?} @ {} expired before landing, Tx Signature: {}", order.side, order.price, outcome.signature);
+                    "expired before landing".to_string()
+                }
+            };
+            for client_order_id in &order.client_order_ids {
+                self.order_tracker.rejected(*client_order_id, &reason);
+            }
+
+            //the cancels didn't happen either, the reconciler sends them again
+            for order_id in &order.cancels {
+                self.cancelling.remove(order_id);
+            }
+            //the quotes never made it onto the book, forget the side so the strategy quotes it afresh
+            match order.side {
+                Side::Bid => self.resting_bids.clear(),
+                Side::Ask => self.resting_asks.clear(),
+            }
+            self.order_state.update(|state| match order.side {
+                Side::Bid => state.last_bid_price = 0.0,
+                Side::Ask => state.last_ask_price = 0.0,
+            });
+        }
+    }
+
+    fn handle_market_update(&mut self, update: MarketUpdate) {
+        match update {
+            MarketUpdate::Bids(order_book) => self.market.bid_order_book = order_book,
+            MarketUpdate::Asks(order_book) => self.market.ask_order_book = order_book,
+            MarketUpdate::EventQueue(snapshot) => self.order_tracker.fills(&snapshot.fills),
+            MarketUpdate::OpenOrders(snapshot) => {
+                self.order_tracker.open_orders(&snapshot, &self.cancelling);
+                if let Some(previous) = self.open_orders.take() {
+                    self.attribute_removed(&previous, &snapshot);
+                }
+                self.order_state.update(|state| state.record_open_orders(&snapshot));
+                self.open_orders = Some(snapshot);
+                self.emergency_cancel_stale = true;
+            }
+        }
+    }
+
+    //logs every order that left the open orders account with what it was placed for, the tracker already logs the
+    //ones it knows
+    fn attribute_removed(&mut self, previous: &OpenOrdersSnapshot, current: &OpenOrdersSnapshot) {
+        for order in &previous.orders {
+            if current.orders.iter().any(|current| current.order_id == order.order_id) {
+                continue;
+            }
+            let outcome = if self.cancelling.remove(&order.order_id) { "cancelled" } else { "filled or cancelled elsewhere" };
+            if self.order_tracker.tracks(order.client_order_id) {
+                continue;
+            }
+            println!("{} {} {}", self.uuid, self.client_order_ids.describe(order.client_order_id), outcome);
+        }
+    }
+
+    //finds the bot's nonce accounts or creates its first, without one the kill switch falls back to cancelling with a
+    //fresh blockhash
+    async fn prepare_emergency_cancel(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.nonces.clear();
+        for nonce_account in self.order_state.snapshot().nonce_accounts {
+            let nonce_account = Pubkey::from_str(&nonce_account)?;
+            let blockhash = nonce_blockhash(self.chain_client.as_ref(), &nonce_account).await?;
+            self.nonces.push((nonce_account, blockhash));
+        }
+        if self.nonces.is_empty() {
+            self.add_nonce_account().await?;
+        }
+        self.emergency_cancel_stale = true;
+        Ok(())
+    }
+
+    //recorded as soon as it exists, so a restart finds it again instead of paying for another
+    async fn add_nonce_account(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let nonce_account = create_nonce_account(self.chain_client.as_ref(), self.signer.clone()).await?;
+        self.order_state.update(|state| state.nonce_accounts.push(nonce_account.to_string()));
+        let blockhash = nonce_blockhash(self.chain_client.as_ref(), &nonce_account).await?;
+        self.nonces.push((nonce_account, blockhash));
+        Ok(())
+    }
+
+    //re-signs the cancels for whatever is resting now, signing against the nonces needs no rpc call. Every transaction
+    //advances its own nonce, so each chunk of cancels needs a nonce account of its own
+    async fn refresh_emergency_cancel(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.nonces.is_empty() {
+            return Ok(());
+        }
+        let snapshot = match &self.open_orders {
+            Some(snapshot) if !snapshot.orders.is_empty() => snapshot,
+            _ => {
+                self.emergency_cancels.set(self.market_ooa, Vec::new());
+                self.emergency_cancel_stale = false;
+                return Ok(());
+            }
+        };
+
+        let market_state = *self.market.market.lock().unwrap();
+        let instructions = cancel_all_instructions(&market_state, &self.market_ooa, snapshot, &self.owner)?;
+        let chunks: Vec<Vec<Instruction>> = instructions.chunks(CANCELS_PER_NONCE_TRANSACTION).map(|chunk| chunk.to_vec()).collect();
+        //more orders rest than the nonces cover, the ones that can't be added yet are left to the sweep that follows
+        //the broadcast and tried again on the next tick
+        let mut missing_nonce = None;
+        while self.nonces.len() < chunks.len() {
+            if let Err(err) = self.add_nonce_account().await {
+                missing_nonce = Some(err.to_string());
+                break;
+            }
+        }
+
+        //before the bot's first transaction there is no recent price to go on, bid the most the policy allows
+        let compute_unit_price = self.fee_tracker.high_compute_unit_price().unwrap_or(self.fee_policy.max_micro_lamports);
+        let mut transactions = Vec::with_capacity(chunks.len());
+        let chunk_count = chunks.len();
+        for (chunk, (nonce_account, blockhash)) in chunks.into_iter().zip(self.nonces.clone()) {
+            transactions.push(sign_with_nonce(&nonce_account, blockhash, self.signer.clone(), compute_unit_price, chunk).await?);
+        }
+        let signed = transactions.len();
+        self.emergency_cancels.set(self.market_ooa, transactions);
+        if let Some(err) = missing_nonce {
+            return Err(format!("only {} of {} cancel transactions signed, no nonce account for the rest: {}", signed, chunk_count, err).into());
+        }
+        self.emergency_cancel_stale = false;
+        Ok(())
+    }
+
+    //one batch of a side's plan in one transaction: the cancels, then the new orders. price is what the transaction is
+    //logged against
+    async fn submit_plan(
+            &mut self,
+            side: Side,
+            plan: &SidePlan,
+            price: f64,
+        ) -> Result<(), Box<dyn std::error::Error>>
+    {
+        //a copy rather than the guard, the helpers below lock the market themselves and nothing can be held across the send
+        let market_state = *self.market.market.lock().unwrap();
+
+        let orders: Vec<(Quote, NewOrderInstructionV3)> = plan.place.iter().map(|quote| {
+            let quote = *quote;
+            let mut order = NewOrderInstructionV3 {
+                side,
+                limit_price: NonZeroU64::new(1).unwrap(),
+                max_coin_qty: NonZeroU64::new(1).unwrap(),
+                order_type: OrderType::PostOnly,
+                client_order_id: self.client_order_ids.allocate(side, quote),
+                self_trade_behavior: SelfTradeBehavior::DecrementTake,
+                max_native_pc_qty_including_fees: NonZeroU64::new(1).unwrap(),
+                limit: 5,//todo what should limit's value be??????
+            };
+
+            self.serum_manager.set_order_prices(&mut order, &self.market, quote.price, quote.size);
+
+            (quote, order)
+        }).collect();
+
+        let accounts = OrderAccounts {
+            owner: self.owner,
+            open_orders: self.market_ooa,
+            base_wallet: self.base_wallet,
+            quote_wallet: self.usdc_wallet,
+            vault_signer: SerumUtils::get_vault_signer(&self.market),
+        };
+        let new_orders: Vec<NewOrderInstructionV3> = orders.iter().map(|(_, order)| order.clone()).collect();
+        let instructions = order_instructions(&market_state, &accounts, side, &plan.cancel, &new_orders)?;
+
+        let writable_accounts = market_writable_accounts(&market_state);
+
+        let client_order_ids: Vec<u64> = orders.iter().map(|(_, order)| order.client_order_id).collect();
+        for (quote, order) in &orders {
+            self.order_tracker.pending(order.client_order_id, side, *quote);
+        }
+        let cancels = plan.cancel.iter().map(|order| order.order_id).collect();
+        let result = self.send_instructions(instructions, &writable_accounts, side, price, client_order_ids.clone(), cancels).await;
+        let signature = match result {
+            Ok(signature) => signature,
+            Err(err) => {
+                for client_order_id in &client_order_ids {
+                    self.order_tracker.rejected(*client_order_id, &err.to_string());
+                }
+                return Err(err);
+            }
+        };
+        for client_order_id in &client_order_ids {
+            self.order_tracker.sent(*client_order_id);
+        }
+        match side {
+            Side::Bid => self.bid_in_flight += 1,
+            Side::Ask => self.ask_in_flight += 1,
+        }
+        self.orders_in_flight += orders.len();
+        let label = match side {
+            Side::Bid => "Bid",
+            Side::Ask => "Ask",
+        };
+        for cancel in &plan.cancel {
+            self.cancelling.insert(cancel.order_id);
+            println!("Cancelling {}, Tx Signature: {:?}", self.client_order_ids.describe(cancel.client_order_id), signature);
+        }
+        for (quote, _) in &orders {
+            println!(
+                "Base {}: {} @ {}, Tx Signature: {:?}",
+                label,
+                quote.size,
+                quote.price,
+                signature
+            );
+        }
+        //the level closest to the reference price
+        if let Some((quote, _)) = orders.first() {
+            self.order_state.update(|state| match side {
+                Side::Bid => state.last_bid_price = quote.price,
+                Side::Ask => state.last_ask_price = quote.price,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+//the accounts an order transaction moves funds between, next to the market's own
+struct OrderAccounts {
+    owner: Pubkey,
+    open_orders: Pubkey,
+    base_wallet: Pubkey,
+    quote_wallet: Pubkey,
+    vault_signer: Pubkey,
+}
+
+//one batch as instructions: consume events, the cancels, settle, the new orders and the memo. A bid pays from the
+//quote wallet, an ask from the base wallet
+fn order_instructions(
+    market_state: &MarketState,
+    accounts: &OrderAccounts,
+    side: Side,
+    cancels: &[RestingOrder],
+    orders: &[NewOrderInstructionV3],
+) -> Result<Vec<Instruction>, Box<dyn std::error::Error>> {
+    let payer_wallet = match side {
+        Side::Bid => &accounts.quote_wallet,
+        Side::Ask => &accounts.base_wallet,
+    };
+    let mut instructions = vec![
+        {
+            let mut tmp = consume_events(
+                &SERUM_PROGRAM_ID_V3,
+                vec![&accounts.open_orders],
+                &pub_key(market_state.own_address),
+                &pub_key(market_state.event_q),
+                &accounts.base_wallet,
+                &accounts.quote_wallet,
+                5,//this value was found inside the SerumProgram.java implementation
+            )?;
+            tmp.accounts.push(AccountMeta::new(accounts.owner, true));
+            tmp
+        }
+    ];
+
+    for resting in cancels {
+        instructions.push(cancel_order(
+            &SERUM_PROGRAM_ID_V3,
+            &pub_key(market_state.own_address),
+            &pub_key(market_state.bids),
+            &pub_key(market_state.asks),
+            &accounts.open_orders,
+            &accounts.owner,
+            &pub_key(market_state.event_q),
+            resting.side,
+            resting.order_id,
+        )?);
+    }
+
+    instructions.push(settle_funds(
+        &SERUM_PROGRAM_ID_V3,
+        &pub_key(market_state.own_address),
+        &TOKEN_PROGRAM_ID,
+        &accounts.open_orders,
+        &accounts.owner,
+        &pub_key(market_state.coin_vault),
+        &accounts.base_wallet,
+        &pub_key(market_state.pc_vault),
+        &accounts.quote_wallet,
+        Some(&accounts.quote_wallet),
+        &accounts.vault_signer,
+    )?);
+
+    for order in orders {
+        instructions.push(serum_dex::instruction::new_order(
+            &pub_key(market_state.own_address),
+            &accounts.open_orders,
+            &pub_key(market_state.req_q),
+            &pub_key(market_state.event_q),
+            &pub_key(market_state.bids),
+            &pub_key(market_state.asks),
+            payer_wallet,
+            &accounts.owner,
+            &pub_key(market_state.coin_vault),
+            &pub_key(market_state.pc_vault),
+            &TOKEN_PROGRAM_ID,
+            &SYSVAR_RENT_PUBKEY,
+            None,
+            &SERUM_PROGRAM_ID_V3,
+            order.side,
+            order.limit_price,
+            order.max_coin_qty,
+            order.order_type,
+            order.client_order_id,
+            order.self_trade_behavior,
+            order.limit,//todo check this out
+            order.max_native_pc_qty_including_fees,
+        )?);
+    }
+
+    //program_id key found inside org.p2p.solanaj.programs.MemoProgram
+    let program_id = Pubkey::from_str("Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo").unwrap();
+    instructions.push(
+        Instruction::new_with_bytes(
+            program_id,
+            "Liquidity by Arcana".as_bytes(),
+            vec![AccountMeta::new(accounts.owner, true)],
+        )
+    );
+
+    Ok(instructions)
+}
+
+//every account a consume/cancel/settle/new order sequence write locks, used to sample priority fees
+fn market_writable_accounts(market: &MarketState) -> Vec<Pubkey> {
+    vec![
+        pub_key(market.own_address),
+        pub_key(market.bids),
+        pub_key(market.asks),
+        pub_key(market.event_q),
+        pub_key(market.req_q),
+        pub_key(market.coin_vault),
+        pub_key(market.pc_vault),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::packet::PACKET_DATA_SIZE;
+    use solana_sdk::signature::Keypair;
+
+    use crate::serum::market_fixture::MarketFixture;
+    use crate::strategies::reconciler::{NEW_ORDER_COST, TRANSACTION_BUDGET};
+
+    use super::*;
+
+    #[test]
+    fn the_largest_batches_fit_a_packet() {
+        let fixture = MarketFixture::new();
+        let owner = Keypair::new();
+        let accounts = OrderAccounts {
+            owner: owner.pubkey(),
+            open_orders: Pubkey::new_unique(),
+            base_wallet: Pubkey::new_unique(),
+            quote_wallet: Pubkey::new_unique(),
+            vault_signer: Pubkey::new_unique(),
+        };
+        let lookup_table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: market_static_accounts(&fixture.state, &accounts.vault_signer),
+        };
+        let fee_estimate = FeeEstimate { compute_unit_price: u64::MAX, high_compute_unit_price: u64::MAX, compute_unit_limit: u32::MAX };
+
+        //every mix of cancels and new orders the reconciler can put in one transaction
+        for cancel_count in 0..=TRANSACTION_BUDGET {
+            let side = if cancel_count % 2 == 0 { Side::Bid } else { Side::Ask };
+            let cancels: Vec<RestingOrder> = (0..cancel_count).map(|i| RestingOrder {
+                order_id: u128::MAX - i as u128,
+                client_order_id: u64::MAX,
+                side,
+                price_lots: u64::MAX,
+                price: 1.0,
+                remaining: None,
+            }).collect();
+            let orders: Vec<NewOrderInstructionV3> = (0..(TRANSACTION_BUDGET - cancel_count) / NEW_ORDER_COST).map(|_| NewOrderInstructionV3 {
+                side,
+                limit_price: NonZeroU64::MAX,
+                max_coin_qty: NonZeroU64::MAX,
+                order_type: OrderType::PostOnly,
+                client_order_id: u64::MAX,
+                self_trade_behavior: SelfTradeBehavior::DecrementTake,
+                max_native_pc_qty_including_fees: NonZeroU64::MAX,
+                limit: u16::MAX,
+            }).collect();
+
+            let mut instructions = fee_estimate.budget_instructions();
+            instructions.extend(order_instructions(&fixture.state, &accounts, side, &cancels, &orders).unwrap());
+            //without the table when it couldn't be set up
+            for lookup_tables in [vec![lookup_table.clone()], Vec::new()] {
+                let message = v0::Message::try_compile(&owner.pubkey(), &instructions, &lookup_tables, Hash::new_unique()).unwrap();
+                let transaction = VersionedTransaction::try_new(VersionedMessage::V0(message), &[&owner]).unwrap();
+                let size = bincode::serialized_size(&transaction).unwrap() as usize;
+                assert!(size <= PACKET_DATA_SIZE, "{} cancels and {} orders take {} bytes", cancels.len(), orders.len(), size);
+            }
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::{BTreeMap, HashSet};
+use serde_derive::{Deserialize, Serialize};
+use serum_dex::matching::Side;
+use uuid::Uuid;
+use crate::serum::market_feed::{Fill, OpenOrdersSnapshot};
+use crate::strategies::client_order_ids::sequence;
+use crate::strategies::strategy::Quote;
+
+//this many finished orders are kept around, then they are dropped oldest first
+const KEPT_FINISHED: usize = 64;
+//lot rounding leaves the last fill a little short of the size the order was placed with
+const FILL_TOLERANCE: f64 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderStatus {
+    //built, the transaction isn't out yet
+    Pending,
+    //handed to the confirmation tracker
+    Sent,
+    //the transaction landed, the order hasn't shown up in the open orders account yet
+    Confirmed,
+    Resting,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    //the transaction failed, was refused or expired, the order never made it onto the book
+    Rejected,
+    //the transaction landed but the order never showed up in the open orders account and no fill has been seen for
+    //it. A post only order that would have crossed, or one filled and cranked before either update got here
+    Vanished,
+}
+
+impl OrderStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Rejected | OrderStatus::Vanished)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TrackedOrder {
+    pub client_order_id: u64,
+    pub side: Side,
+    pub price: f64,
+    pub size: f64,
+    pub filled: f64,
+    //known once the order shows up in the open orders account or in a fill
+    pub order_id: Option<u128>,
+    pub status: OrderStatus,
+}
+
+//every order a bot placed, keyed by client order id, moved along by transaction outcomes, open orders updates and
+//fills from the event queue
+pub struct OrderTracker {
+    uuid: Uuid,
+    orders: BTreeMap<u64, TrackedOrder>,
+    //fills stay in the event queue until they are cranked, each is only counted once
+    last_fill_seq_num: Option<u64>,
+}
+
+impl OrderTracker {
+    pub fn new(uuid: Uuid) -> Self {
+        Self {
+            uuid,
+            orders: BTreeMap::new(),
+            last_fill_seq_num: None,
+        }
+    }
+
+    pub fn tracks(&self, client_order_id: u64) -> bool {
+        self.orders.contains_key(&client_order_id)
+    }
+
+    pub fn pending(&mut self, client_order_id: u64, side: Side, quote: Quote) {
+        self.orders.insert(client_order_id, TrackedOrder {
+            client_order_id,
+            side,
+            price: quote.price,
+            size: quote.size,
+            filled: 0.,
+            order_id: None,
+            status: OrderStatus::Pending,
+        });
+        self.prune();
+    }
+
+    pub fn sent(&mut self, client_order_id: u64) {
+        self.transition(client_order_id, OrderStatus::Sent, None);
+    }
+
+    //the open orders update usually beats the confirmation, an order already seen resting stays as it is
+    pub fn confirmed(&mut self, client_order_id: u64) {
+        if self.status(client_order_id) == Some(OrderStatus::Sent) {
+            self.transition(client_order_id, OrderStatus::Confirmed, None);
+        }
+    }
+
+    pub fn rejected(&mut self, client_order_id: u64, reason: &str) {
+        if self.status(client_order_id).is_some_and(|status| !status.is_finished()) {
+            self.transition(client_order_id, OrderStatus::Rejected, Some(reason));
+        }
+    }
+
+    //orders that show up in the account are resting, the resting ones that are gone were filled, or cancelled when a
+    //cancel was sent for them. One that landed and is gone without ever resting can't be told apart, it vanished
+    pub fn open_orders(&mut self, snapshot: &OpenOrdersSnapshot, cancelling: &HashSet<u128>) {
+        let mut changes = Vec::new();
+        for order in self.orders.values_mut().filter(|order| !order.status.is_finished()) {
+            match snapshot.orders.iter().find(|resting| resting.client_order_id == order.client_order_id) {
+                Some(resting) => {
+                    order.order_id = Some(resting.order_id);
+                    if matches!(order.status, OrderStatus::Pending | OrderStatus::Sent | OrderStatus::Confirmed) {
+                        changes.push((order.client_order_id, OrderStatus::Resting, None));
+                    }
+                }
+                None => match order.status {
+                    OrderStatus::Confirmed => changes.push((order.client_order_id, OrderStatus::Vanished, Some("landed but never rested"))),
+                    OrderStatus::Resting | OrderStatus::PartiallyFilled => {
+                        if order.order_id.is_some_and(|order_id| cancelling.contains(&order_id)) {
+                            changes.push((order.client_order_id, OrderStatus::Cancelled, None));
+                        } else {
+                            //the fills may have been cranked before the event queue update got here
+                            order.filled = order.size;
+                            changes.push((order.client_order_id, OrderStatus::Filled, None));
+                        }
+                    }
+                    _ => {}
+                },
+            }
+        }
+        for (client_order_id, status, reason) in changes {
+            self.transition(client_order_id, status, reason);
+        }
+    }
+
+    pub fn fills(&mut self, fills: &[Fill]) {
+        for fill in fills {
+            if self.last_fill_seq_num.is_some_and(|last| fill.seq_num <= last) {
+                continue;
+            }
+            self.last_fill_seq_num = Some(fill.seq_num);
+            //a vanished order's fills can still turn up, they say what became of it
+            let order = match self.orders.get_mut(&fill.client_order_id) {
+                Some(order) if (!order.status.is_finished() || order.status == OrderStatus::Vanished) && order.side == fill.side => order,
+                _ => continue,
+            };
+            order.order_id = Some(fill.order_id);
+            order.filled += fill.quantity;
+            let status = if order.filled >= order.size * (1.0 - FILL_TOLERANCE) {
+                OrderStatus::Filled
+            } else if order.status == OrderStatus::Vanished {
+                OrderStatus::Vanished
+            } else {
+                OrderStatus::PartiallyFilled
+            };
+            let client_order_id = order.client_order_id;
+            self.transition(client_order_id, status, None);
+        }
+    }
+
+    fn status(&self, client_order_id: u64) -> Option<OrderStatus> {
+        self.orders.get(&client_order_id).map(|order| order.status)
+    }
+
+    fn transition(&mut self, client_order_id: u64, status: OrderStatus, reason: Option<&str>) {
+        let order = match self.orders.get_mut(&client_order_id) {
+            Some(order) => order,
+            None => return,
+        };
+        let previous = order.status;
+        order.status = status;
+        if previous == status && status != OrderStatus::PartiallyFilled {
+            return;
+        }
+        let reason = reason.map(|reason| format!(": {}", reason)).unwrap_or_default();
+        println!(
+            "{} {:?} {} @ {} (client id {}) {:?} -> {:?}, filled {}{}",
+            self.uuid, order.side, order.size, order.price, client_order_id, previous, status, order.filled, reason
+        );
+    }
+
+    //client order ids only go up, so the oldest finished orders have the lowest ids on each side
+    fn prune(&mut self) {
+        let mut finished: Vec<u64> = self.orders.values()
+            .filter(|order| order.status.is_finished())
+            .map(|order| order.client_order_id)
+            .collect();
+        if finished.len() <= KEPT_FINISHED {
+            return;
+        }
+        finished.sort_by_key(|client_order_id| sequence(*client_order_id));
+        for client_order_id in &finished[..finished.len() - KEPT_FINISHED] {
+            self.orders.remove(client_order_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::serum::market_feed::OpenOrder;
+
+    use super::*;
+
+    const BID: u64 = 1;
+    const ASK: u64 = 2;
+
+    fn tracker() -> OrderTracker {
+        let mut tracker = OrderTracker::new(Uuid::nil());
+        tracker.pending(BID, Side::Bid, Quote { price: 20.0, size: 1.0 });
+        tracker.pending(ASK, Side::Ask, Quote { price: 21.0, size: 2.0 });
+        tracker
+    }
+
+    //(client order id, order id, side)
+    fn open_orders(orders: &[(u64, u128, Side)]) -> OpenOrdersSnapshot {
+        OpenOrdersSnapshot {
+            base_free: 0,
+            base_total: 0,
+            quote_free: 0,
+            quote_total: 0,
+            orders: orders.iter()
+                .map(|(client_order_id, order_id, side)| OpenOrder { order_id: *order_id, client_order_id: *client_order_id, side: *side })
+                .collect(),
+        }
+    }
+
+    fn fill(seq_num: u64, client_order_id: u64, side: Side, quantity: f64) -> Fill {
+        Fill { seq_num, order_id: client_order_id as u128 + 100, client_order_id, side, quantity }
+    }
+
+    fn sent_and_confirmed(tracker: &mut OrderTracker) {
+        for client_order_id in [BID, ASK] {
+            tracker.sent(client_order_id);
+            tracker.confirmed(client_order_id);
+        }
+    }
+
+    #[test]
+    fn an_order_goes_from_pending_to_filled() {
+        let mut tracker = tracker();
+        assert_eq!(tracker.status(BID), Some(OrderStatus::Pending));
+        tracker.sent(BID);
+        assert_eq!(tracker.status(BID), Some(OrderStatus::Sent));
+        tracker.confirmed(BID);
+        assert_eq!(tracker.status(BID), Some(OrderStatus::Confirmed));
+
+        tracker.open_orders(&open_orders(&[(BID, 101, Side::Bid)]), &HashSet::new());
+        assert_eq!(tracker.status(BID), Some(OrderStatus::Resting));
+        assert_eq!(tracker.orders[&BID].order_id, Some(101));
+
+        tracker.fills(&[fill(1, BID, Side::Bid, 0.4)]);
+        assert_eq!(tracker.status(BID), Some(OrderStatus::PartiallyFilled));
+        //the same fill again, the event queue hasn't been cranked yet
+        tracker.fills(&[fill(1, BID, Side::Bid, 0.4)]);
+        assert_eq!(tracker.orders[&BID].filled, 0.4);
+
+        //a little short of the size, lot rounding
+        tracker.fills(&[fill(1, BID, Side::Bid, 0.4), fill(2, BID, Side::Bid, 0.595)]);
+        assert_eq!(tracker.status(BID), Some(OrderStatus::Filled));
+        assert!(tracker.status(BID).unwrap().is_finished());
+    }
+
+    #[test]
+    fn a_resting_order_that_is_gone_was_cancelled_or_filled() {
+        let mut tracker = tracker();
+        sent_and_confirmed(&mut tracker);
+        tracker.open_orders(&open_orders(&[(BID, 101, Side::Bid), (ASK, 102, Side::Ask)]), &HashSet::new());
+        tracker.fills(&[fill(1, ASK, Side::Ask, 0.5)]);
+        assert_eq!(tracker.status(ASK), Some(OrderStatus::PartiallyFilled));
+
+        //a cancel went out for the bid, the ask's remaining fills were cranked before the event queue update came in
+        tracker.open_orders(&open_orders(&[]), &HashSet::from([101]));
+        assert_eq!(tracker.status(BID), Some(OrderStatus::Cancelled));
+        assert_eq!(tracker.status(ASK), Some(OrderStatus::Filled));
+        assert_eq!(tracker.orders[&ASK].filled, 2.0);
+    }
+
+    #[test]
+    fn an_order_seen_resting_before_its_confirmation_stays_resting() {
+        let mut tracker = tracker();
+        tracker.sent(BID);
+        tracker.open_orders(&open_orders(&[(BID, 101, Side::Bid)]), &HashSet::new());
+        tracker.confirmed(BID);
+        assert_eq!(tracker.status(BID), Some(OrderStatus::Resting));
+    }
+
+    #[test]
+    fn orders_whose_transaction_has_not_landed_are_left_alone() {
+        let mut tracker = tracker();
+        tracker.sent(BID);
+        tracker.open_orders(&open_orders(&[]), &HashSet::new());
+        assert_eq!(tracker.status(BID), Some(OrderStatus::Sent));
+        assert_eq!(tracker.status(ASK), Some(OrderStatus::Pending));
+    }
+
+    #[test]
+    fn failed_and_expired_transactions_reject_their_orders() {
+        let mut tracker = tracker();
+        //signing or sending failed before the transaction went out
+        tracker.rejected(BID, "signing refused");
+        //the confirmation tracker gave up on the ask's transaction
+        tracker.sent(ASK);
+        tracker.rejected(ASK, "transaction expired");
+        assert_eq!(tracker.status(BID), Some(OrderStatus::Rejected));
+        assert_eq!(tracker.status(ASK), Some(OrderStatus::Rejected));
+
+        //a finished order isn't moved again
+        tracker.confirmed(ASK);
+        tracker.open_orders(&open_orders(&[]), &HashSet::new());
+        assert_eq!(tracker.status(ASK), Some(OrderStatus::Rejected));
+    }
+
+    #[test]
+    fn a_confirmed_order_missing_from_the_account_vanished_until_its_fills_turn_up() {
+        let mut tracker = tracker();
+        sent_and_confirmed(&mut tracker);
+        tracker.open_orders(&open_orders(&[]), &HashSet::new());
+        assert_eq!(tracker.status(BID), Some(OrderStatus::Vanished));
+        assert_eq!(tracker.status(ASK), Some(OrderStatus::Vanished));
+        assert!(tracker.status(BID).unwrap().is_finished());
+
+        tracker.fills(&[fill(1, BID, Side::Bid, 1.0), fill(2, ASK, Side::Ask, 0.5)]);
+        assert_eq!(tracker.status(BID), Some(OrderStatus::Filled));
+        assert_eq!(tracker.status(ASK), Some(OrderStatus::Vanished));
+        assert_eq!(tracker.orders[&ASK].filled, 0.5);
+    }
+}
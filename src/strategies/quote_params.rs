@@ -0,0 +1,238 @@
+use serde_derive::{Deserialize, Serialize};
+use crate::arcana_web_config;
+
+pub const BPS: f64 = 10_000.0;
+
+//what a bot quotes with, the part of its params that can change while it runs
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "QuoteParamsInput")]
+pub struct QuoteParams {
+    //below and above the reference price
+    pub bid_spread_bps: f64,
+    pub ask_spread_bps: f64,
+    //quote notional, the base quantity follows from the bid price
+    pub amount_bid: f64,
+    //base units
+    pub amount_ask: f64,
+    //fraction the quote has to move by before it is replaced
+    pub min_midpoint_change: f64,
+}
+
+//bps_spread is the symmetric shorthand the add bot form sends, a per side spread wins over it
+#[derive(Deserialize)]
+#[serde(default)]
+struct QuoteParamsInput {
+    bps_spread: Option<f64>,
+    bid_spread_bps: Option<f64>,
+    ask_spread_bps: Option<f64>,
+    amount_bid: f64,
+    amount_ask: f64,
+    min_midpoint_change: f64,
+}
+
+impl Default for QuoteParamsInput {
+    fn default() -> Self {
+        let defaults = arcana_web_config::bot_defaults();
+        Self {
+            bps_spread: None,
+            bid_spread_bps: None,
+            ask_spread_bps: None,
+            amount_bid: defaults.amount_bid,
+            amount_ask: defaults.amount_ask,
+            min_midpoint_change: defaults.min_midpoint_change,
+        }
+    }
+}
+
+impl From<QuoteParamsInput> for QuoteParams {
+    fn from(input: QuoteParamsInput) -> Self {
+        let defaults = arcana_web_config::bot_defaults();
+        Self {
+            bid_spread_bps: input.bid_spread_bps.or(input.bps_spread).or(defaults.bid_spread_bps).unwrap_or(defaults.bps_spread),
+            ask_spread_bps: input.ask_spread_bps.or(input.bps_spread).or(defaults.ask_spread_bps).unwrap_or(defaults.bps_spread),
+            amount_bid: input.amount_bid,
+            amount_ask: input.amount_ask,
+            min_midpoint_change: input.min_midpoint_change,
+        }
+    }
+}
+
+impl Default for QuoteParams {
+    fn default() -> Self {
+        QuoteParamsInput::default().into()
+    }
+}
+
+impl QuoteParams {
+    //same bounds as the [bot] section of the config
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+        if !(self.bid_spread_bps >= 0.0 && self.bid_spread_bps < BPS) {
+            problems.push("bid_spread_bps must be at least 0 and below 10000");
+        }
+        if !(self.ask_spread_bps >= 0.0 && self.ask_spread_bps < BPS) {
+            problems.push("ask_spread_bps must be at least 0 and below 10000");
+        }
+        if !(self.amount_bid > 0.0 && self.amount_bid.is_finite()) {
+            problems.push("amount_bid must be above 0");
+        }
+        if !(self.amount_ask > 0.0 && self.amount_ask.is_finite()) {
+            problems.push("amount_ask must be above 0");
+        }
+        if !(self.min_midpoint_change >= 0.0 && self.min_midpoint_change < 1.0) {
+            problems.push("min_midpoint_change must be at least 0 and below 1");
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join(", "))
+        }
+    }
+
+    pub fn bid_price(&self, reference_price: f64) -> f64 {
+        reference_price * (1.0 - self.bid_spread_bps / BPS)
+    }
+
+    pub fn ask_price(&self, reference_price: f64) -> f64 {
+        reference_price * (1.0 + self.ask_spread_bps / BPS)
+    }
+
+    //the bid is sized in quote, the order is placed in base
+    pub fn bid_base_amount(&self, bid_price: f64) -> f64 {
+        self.amount_bid / bid_price
+    }
+
+    //"name before -> after" for every value that differs, for the log
+    pub fn changes(&self, after: &QuoteParams) -> Vec<String> {
+        let fields = [
+            ("bid_spread_bps", self.bid_spread_bps, after.bid_spread_bps),
+            ("ask_spread_bps", self.ask_spread_bps, after.ask_spread_bps),
+            ("amount_bid", self.amount_bid, after.amount_bid),
+            ("amount_ask", self.amount_ask, after.amount_ask),
+            ("min_midpoint_change", self.min_midpoint_change, after.min_midpoint_change),
+        ];
+        fields.iter()
+            .filter(|(_, before, after)| before != after)
+            .map(|(name, before, after)| format!("{} {} -> {}", name, before, after))
+            .collect()
+    }
+
+    //whether the resting quotes are out of date under the new params, a new threshold alone doesn't move them
+    pub fn requotes(&self, after: &QuoteParams) -> bool {
+        self.bid_spread_bps != after.bid_spread_bps
+            || self.ask_spread_bps != after.ask_spread_bps
+            || self.amount_bid != after.amount_bid
+            || self.amount_ask != after.amount_ask
+    }
+}
+
+//a partial update, anything left out keeps its current value. bps_spread sets both sides unless one is given
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuoteParamsUpdate {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bps_spread: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bid_spread_bps: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ask_spread_bps: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_bid: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_ask: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_midpoint_change: Option<f64>,
+}
+
+impl QuoteParamsUpdate {
+    pub fn apply(&self, current: &QuoteParams) -> QuoteParams {
+        QuoteParams {
+            bid_spread_bps: self.bid_spread_bps.or(self.bps_spread).unwrap_or(current.bid_spread_bps),
+            ask_spread_bps: self.ask_spread_bps.or(self.bps_spread).unwrap_or(current.ask_spread_bps),
+            amount_bid: self.amount_bid.unwrap_or(current.amount_bid),
+            amount_ask: self.amount_ask.unwrap_or(current.amount_ask),
+            min_midpoint_change: self.min_midpoint_change.unwrap_or(current.min_midpoint_change),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote_params() -> QuoteParams {
+        QuoteParams {
+            bid_spread_bps: 100.0,
+            ask_spread_bps: 50.0,
+            amount_bid: 990.0,
+            amount_ask: 2.0,
+            min_midpoint_change: 0.01,
+        }
+    }
+
+    //every amount given, so the configured bot defaults don't come into it
+    fn parse(spreads: &str) -> QuoteParams {
+        let json = format!(r#"{{{}"amount_bid": 990.0, "amount_ask": 2.0, "min_midpoint_change": 0.01}}"#, spreads);
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn bps_spread_sets_both_sides_unless_one_is_given() {
+        let params = parse(r#""bps_spread": 20.0, "#);
+        assert_eq!((params.bid_spread_bps, params.ask_spread_bps), (20.0, 20.0));
+
+        let params = parse(r#""bps_spread": 20.0, "ask_spread_bps": 35.0, "#);
+        assert_eq!((params.bid_spread_bps, params.ask_spread_bps), (20.0, 35.0));
+
+        let params = parse(r#""bid_spread_bps": 15.0, "ask_spread_bps": 35.0, "#);
+        assert_eq!((params.bid_spread_bps, params.ask_spread_bps), (15.0, 35.0));
+    }
+
+    #[test]
+    fn an_update_only_changes_what_it_sets() {
+        let current = quote_params();
+        assert_eq!(QuoteParamsUpdate::default().apply(&current), current);
+
+        let update = QuoteParamsUpdate { amount_ask: Some(3.0), ..QuoteParamsUpdate::default() };
+        assert_eq!(update.apply(&current), QuoteParams { amount_ask: 3.0, ..current });
+
+        let update = QuoteParamsUpdate { bps_spread: Some(20.0), ..QuoteParamsUpdate::default() };
+        assert_eq!(update.apply(&current), QuoteParams { bid_spread_bps: 20.0, ask_spread_bps: 20.0, ..current });
+
+        let update = QuoteParamsUpdate { bps_spread: Some(20.0), bid_spread_bps: Some(70.0), ..QuoteParamsUpdate::default() };
+        assert_eq!(update.apply(&current), QuoteParams { bid_spread_bps: 70.0, ask_spread_bps: 20.0, ..current });
+    }
+
+    #[test]
+    fn validate_lists_every_problem() {
+        assert!(quote_params().validate().is_ok());
+        assert!(QuoteParams { bid_spread_bps: 0.0, min_midpoint_change: 0.0, ..quote_params() }.validate().is_ok());
+
+        let params = QuoteParams {
+            bid_spread_bps: 10_000.0,
+            ask_spread_bps: -1.0,
+            amount_bid: 0.0,
+            amount_ask: f64::NAN,
+            min_midpoint_change: 1.0,
+        };
+        assert_eq!(params.validate().unwrap_err(), "bid_spread_bps must be at least 0 and below 10000, \
+            ask_spread_bps must be at least 0 and below 10000, amount_bid must be above 0, amount_ask must be above 0, \
+            min_midpoint_change must be at least 0 and below 1");
+        assert!(QuoteParams { amount_bid: f64::INFINITY, ..quote_params() }.validate().is_err());
+    }
+
+    #[test]
+    fn a_new_threshold_alone_is_logged_but_doesnt_requote() {
+        let before = quote_params();
+        let after = QuoteParams { min_midpoint_change: 0.02, ..before };
+        assert_eq!(before.changes(&after), vec!["min_midpoint_change 0.01 -> 0.02"]);
+        assert!(!before.requotes(&after));
+
+        let after = QuoteParams { bid_spread_bps: 120.0, amount_ask: 2.5, ..before };
+        assert_eq!(before.changes(&after), vec!["bid_spread_bps 100 -> 120", "amount_ask 2 -> 2.5"]);
+        assert!(before.requotes(&after));
+
+        assert!(before.changes(&before).is_empty());
+        assert!(!before.requotes(&before));
+    }
+}
@@ -0,0 +1,297 @@
+use serum_dex::matching::Side;
+use crate::serum::market::MarketWrapper;
+use crate::serum::market_feed::OpenOrdersSnapshot;
+use crate::serum::serum_utils::SerumUtils;
+use crate::strategies::strategy::{DesiredQuotes, Quote};
+
+//a partially filled order keeps resting until less than this fraction of the desired size is left
+const MIN_REMAINING_FRACTION: f64 = 0.5;
+//lot rounding leaves a fresh order a little off the size it was placed with
+const SIZE_TOLERANCE: f64 = 0.01;
+//what one order transaction holds next to its compute budget, consume events, settle and memo, counted in cancels.
+//A new order takes about twice the room of a cancel. The fullest mixes come to about 720 bytes of a 1232 byte packet
+//with the market's lookup table and under 1000 with every account inline
+pub const TRANSACTION_BUDGET: usize = 8;
+pub const NEW_ORDER_COST: usize = 2;
+
+//one of the bot's orders as it actually rests: listed in its open orders account, with what is left of it from the book
+#[derive(Clone, Debug)]
+pub struct RestingOrder {
+    pub order_id: u128,
+    pub client_order_id: u64,
+    pub side: Side,
+    pub price_lots: u64,
+    pub price: f64,
+    //None while the book copy doesn't have the order yet, it counts as unfilled then
+    pub remaining: Option<f64>,
+}
+
+impl RestingOrder {
+    //the limit price sits in the top 64 bits of an order id on both sides, so an order the book copy hasn't caught
+    //up with still has one
+    pub fn from_open_orders(snapshot: &OpenOrdersSnapshot, market: &MarketWrapper) -> Vec<RestingOrder> {
+        let market_state = *market.market.lock().unwrap();
+        snapshot.orders.iter()
+            .map(|order| {
+                let book = match order.side {
+                    Side::Bid => &market.bid_order_book,
+                    Side::Ask => &market.ask_order_book,
+                };
+                let price_lots = (order.order_id >> 64) as u64;
+                RestingOrder {
+                    order_id: order.order_id,
+                    client_order_id: order.client_order_id,
+                    side: order.side,
+                    price_lots,
+                    price: SerumUtils::price_lots_to_number(price_lots as i64, market.base_decimals, market.quote_decimals, market_state.coin_lot_size, market_state.pc_lot_size),
+                    remaining: book.get_orders().iter()
+                        .find(|book_order| book_order.order_id == order.order_id)
+                        .map(|book_order| book_order.quantity),
+                }
+            })
+            .collect()
+    }
+}
+
+//what one side needs to end up holding the desired quotes
+#[derive(Clone, Debug, Default)]
+pub struct SidePlan {
+    pub cancel: Vec<RestingOrder>,
+    pub place: Vec<Quote>,
+    //the resting orders that already are one of the desired quotes
+    pub keep: Vec<RestingOrder>,
+}
+
+impl SidePlan {
+    pub fn is_empty(&self) -> bool {
+        self.cancel.is_empty() && self.place.is_empty()
+    }
+
+    //the plan cut into transactions that fit, cancels first. A new order only goes out when the open orders account
+    //has a slot for it: free_slots, or one its own transaction's cancels free up first, the others' may not have
+    //landed yet. What doesn't fit is left to a later tick
+    pub fn batches(&self, free_slots: &mut usize) -> Vec<SidePlan> {
+        let mut batches = Vec::new();
+        let mut cancel = self.cancel.iter();
+        let mut place = self.place.iter();
+        loop {
+            let mut batch = SidePlan::default();
+            let mut budget = TRANSACTION_BUDGET;
+            while budget > 0 {
+                match cancel.next() {
+                    Some(order) => batch.cancel.push(order.clone()),
+                    None => break,
+                }
+                budget -= 1;
+            }
+            while budget >= NEW_ORDER_COST && *free_slots + batch.cancel.len() > batch.place.len() {
+                match place.next() {
+                    Some(quote) => batch.place.push(*quote),
+                    None => break,
+                }
+                budget -= NEW_ORDER_COST;
+            }
+            if batch.is_empty() {
+                break;
+            }
+            *free_slots -= batch.place.len().saturating_sub(batch.cancel.len());
+            batches.push(batch);
+        }
+        batches
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OrderPlan {
+    pub bid: SidePlan,
+    pub ask: SidePlan,
+}
+
+//the fewest cancels and places that leave the book holding what the strategy wants: every desired quote keeps one
+//resting order that matches it, the orders left over on that side go. price_lots rounds a price the way the order
+//is placed
+pub fn reconcile(desired: &DesiredQuotes, resting: &[RestingOrder], price_lots: impl Fn(f64) -> u64) -> OrderPlan {
+    OrderPlan {
+        bid: reconcile_side(Side::Bid, &desired.bids, resting, &price_lots),
+        ask: reconcile_side(Side::Ask, &desired.asks, resting, &price_lots),
+    }
+}
+
+fn reconcile_side(side: Side, desired: &[Quote], resting: &[RestingOrder], price_lots: &impl Fn(f64) -> u64) -> SidePlan {
+    let mut plan = SidePlan::default();
+    let mut unmatched: Vec<&RestingOrder> = resting.iter().filter(|order| order.side == side).collect();
+    for quote in desired {
+        let target_lots = price_lots(quote.price);
+        match unmatched.iter().position(|order| order.price_lots == target_lots && fits(order, quote.size)) {
+            Some(index) => plan.keep.push(unmatched.remove(index).clone()),
+            None => plan.place.push(*quote),
+        }
+    }
+    plan.cancel = unmatched.into_iter().cloned().collect();
+    plan
+}
+
+//filled down to less than MIN_REMAINING_FRACTION, or bigger than wanted after a size change, is replaced
+fn fits(order: &RestingOrder, size: f64) -> bool {
+    match order.remaining {
+        Some(remaining) => remaining >= size * MIN_REMAINING_FRACTION && remaining <= size * (1.0 + SIZE_TOLERANCE),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //one lot is 0.01
+    fn price_lots(price: f64) -> u64 {
+        (price * 100.0).round() as u64
+    }
+
+    fn resting(order_id: u128, side: Side, price: f64, remaining: Option<f64>) -> RestingOrder {
+        RestingOrder { order_id, client_order_id: order_id as u64, side, price_lots: price_lots(price), price, remaining }
+    }
+
+    fn quote(price: f64, size: f64) -> Quote {
+        Quote { price, size }
+    }
+
+    fn ids(orders: &[RestingOrder]) -> Vec<u128> {
+        orders.iter().map(|order| order.order_id).collect()
+    }
+
+    #[test]
+    fn matching_orders_are_kept() {
+        let desired = DesiredQuotes { bids: vec![quote(10.0, 1.0), quote(9.9, 1.0)], asks: vec![quote(10.1, 1.0)] };
+        let resting = vec![
+            resting(1, Side::Bid, 10.0, Some(1.0)),
+            resting(2, Side::Bid, 9.9, Some(0.995)),
+            resting(3, Side::Ask, 10.1, Some(1.0)),
+        ];
+
+        let plan = reconcile(&desired, &resting, price_lots);
+        assert!(plan.bid.is_empty() && plan.ask.is_empty());
+        assert_eq!(ids(&plan.bid.keep), vec![1, 2]);
+        assert_eq!(ids(&plan.ask.keep), vec![3]);
+    }
+
+    #[test]
+    fn moved_quote_cancels_and_replaces() {
+        let desired = DesiredQuotes { bids: vec![quote(10.01, 1.0)], asks: vec![quote(10.1, 1.0)] };
+        let resting = vec![resting(1, Side::Bid, 10.0, Some(1.0)), resting(2, Side::Ask, 10.1, Some(1.0))];
+
+        let plan = reconcile(&desired, &resting, price_lots);
+        assert_eq!(ids(&plan.bid.cancel), vec![1]);
+        assert_eq!(plan.bid.place, vec![quote(10.01, 1.0)]);
+        assert!(plan.bid.keep.is_empty());
+        //the other side is left alone
+        assert!(plan.ask.is_empty());
+        assert_eq!(ids(&plan.ask.keep), vec![2]);
+    }
+
+    #[test]
+    fn partial_fill_is_kept_until_half_is_gone() {
+        let desired = DesiredQuotes { bids: vec![quote(10.0, 1.0), quote(9.9, 1.0)], asks: Vec::new() };
+        let resting = vec![resting(1, Side::Bid, 10.0, Some(0.5)), resting(2, Side::Bid, 9.9, Some(0.49))];
+
+        let plan = reconcile(&desired, &resting, price_lots);
+        assert_eq!(ids(&plan.bid.keep), vec![1]);
+        assert_eq!(ids(&plan.bid.cancel), vec![2]);
+        assert_eq!(plan.bid.place, vec![quote(9.9, 1.0)]);
+    }
+
+    #[test]
+    fn bigger_order_than_wanted_is_replaced() {
+        let desired = DesiredQuotes { bids: vec![quote(10.0, 1.0)], asks: Vec::new() };
+        let resting = vec![resting(1, Side::Bid, 10.0, Some(2.0))];
+
+        let plan = reconcile(&desired, &resting, price_lots);
+        assert_eq!(ids(&plan.bid.cancel), vec![1]);
+        assert_eq!(plan.bid.place, vec![quote(10.0, 1.0)]);
+    }
+
+    #[test]
+    fn order_missing_from_the_book_copy_counts_as_unfilled() {
+        let desired = DesiredQuotes { bids: vec![quote(10.0, 1.0)], asks: Vec::new() };
+        let resting = vec![resting(1, Side::Bid, 10.0, None)];
+
+        let plan = reconcile(&desired, &resting, price_lots);
+        assert!(plan.bid.is_empty());
+        assert_eq!(ids(&plan.bid.keep), vec![1]);
+    }
+
+    #[test]
+    fn vanished_order_is_placed_again_and_leftovers_cancelled() {
+        //the 10.0 bid filled and left the open orders account, the strategy dropped the 9.8 level
+        let desired = DesiredQuotes { bids: vec![quote(10.0, 1.0), quote(9.9, 1.0)], asks: Vec::new() };
+        let resting = vec![resting(2, Side::Bid, 9.9, Some(1.0)), resting(3, Side::Bid, 9.8, Some(1.0))];
+
+        let plan = reconcile(&desired, &resting, price_lots);
+        assert_eq!(plan.bid.place, vec![quote(10.0, 1.0)]);
+        assert_eq!(ids(&plan.bid.keep), vec![2]);
+        assert_eq!(ids(&plan.bid.cancel), vec![3]);
+    }
+
+    #[test]
+    fn each_resting_order_matches_one_quote_at_most() {
+        let desired = DesiredQuotes { bids: vec![quote(10.0, 1.0), quote(10.0, 1.0)], asks: Vec::new() };
+        let resting = vec![resting(1, Side::Bid, 10.0, Some(1.0))];
+
+        let plan = reconcile(&desired, &resting, price_lots);
+        assert_eq!(ids(&plan.bid.keep), vec![1]);
+        assert_eq!(plan.bid.place, vec![quote(10.0, 1.0)]);
+    }
+
+    #[test]
+    fn batches_split_over_the_transaction_budget() {
+        let plan = SidePlan {
+            cancel: (0..10).map(|id| resting(id, Side::Bid, 9.0, Some(1.0))).collect(),
+            place: (0..5).map(|level| quote(10.0 - level as f64 * 0.1, 1.0)).collect(),
+            keep: Vec::new(),
+        };
+        let mut free_slots = 100;
+
+        let batches = plan.batches(&mut free_slots);
+        let shape: Vec<(usize, usize)> = batches.iter().map(|batch| (batch.cancel.len(), batch.place.len())).collect();
+        //8 cancels fill the first, the last 2 leave room for 3 orders, the rest go alone
+        assert_eq!(shape, vec![(8, 0), (2, 3), (0, 2)]);
+        assert_eq!(ids(&batches.iter().flat_map(|batch| batch.cancel.clone()).collect::<Vec<_>>()), (0..10).collect::<Vec<u128>>());
+        assert_eq!(batches.iter().flat_map(|batch| batch.place.clone()).collect::<Vec<_>>(), plan.place);
+        //a batch's cancels only make room for its own orders, the 3 placed beyond that are taken
+        assert_eq!(free_slots, 97);
+    }
+
+    #[test]
+    fn batches_only_place_into_free_slots() {
+        let plan = SidePlan {
+            cancel: vec![resting(1, Side::Bid, 9.0, Some(1.0))],
+            place: (0..6).map(|level| quote(10.0 - level as f64 * 0.1, 1.0)).collect(),
+            keep: Vec::new(),
+        };
+        let mut free_slots = 2;
+
+        let batches = plan.batches(&mut free_slots);
+        let shape: Vec<(usize, usize)> = batches.iter().map(|batch| (batch.cancel.len(), batch.place.len())).collect();
+        //the cancel frees one slot for its own transaction, the rest wait for a later tick
+        assert_eq!(shape, vec![(1, 3)]);
+        assert_eq!(free_slots, 0);
+
+        let mut no_slots = 0;
+        assert!(SidePlan { cancel: Vec::new(), place: vec![quote(10.0, 1.0)], keep: Vec::new() }.batches(&mut no_slots).is_empty());
+    }
+
+    #[test]
+    fn both_sides_draw_on_the_same_free_slots() {
+        let bids = SidePlan { cancel: Vec::new(), place: (0..3).map(|level| quote(10.0 - level as f64 * 0.1, 1.0)).collect(), keep: Vec::new() };
+        let asks = SidePlan { cancel: Vec::new(), place: (0..3).map(|level| quote(11.0 + level as f64 * 0.1, 1.0)).collect(), keep: Vec::new() };
+        let mut free_slots = 4;
+
+        let placed: usize = [bids, asks].iter()
+            .flat_map(|plan| plan.batches(&mut free_slots))
+            .map(|batch| batch.place.len())
+            .sum();
+        //the bids take 3, one is left for the asks
+        assert_eq!(placed, 4);
+        assert_eq!(free_slots, 0);
+    }
+}
@@ -1,39 +1,50 @@
-use rocket::info;
-use tokio::runtime::Runtime;
-use uuid::Uuid;
+use async_trait::async_trait;
 
-pub trait Strategy: Send + Sync {
-    fn uuid(&self) -> Uuid;
-    fn start(&mut self, executor: &Runtime);
-    fn startup_complete(&self) {
-        info!("{} strategy instantiated.", std::any::type_name::<Self>());
-    }
-    fn get_strategy_name(&self) -> String {
-        std::any::type_name::<Self>().to_string()
+//one order a strategy wants resting, size in base units
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quote {
+    pub price: f64,
+    pub size: f64,
+}
+
+//what the strategy wants on the book after this update, closest to the reference price first. Anything else
+//resting on a side is cancelled, to leave a side as it is return its resting quotes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DesiredQuotes {
+    pub bids: Vec<Quote>,
+    pub asks: Vec<Quote>,
+}
+
+//everything a strategy decides from, gathered by the executor before every update. Prices are decimal adjusted
+#[derive(Clone, Debug, Default)]
+pub struct MarketSnapshot {
+    //0 when that side of the book is empty
+    pub best_bid: f64,
+    pub best_ask: f64,
+    //only fetched for strategies that price off jupiter, None when the fetch failed
+    pub jupiter_price: Option<f64>,
+    //the quotes the executor placed last and hasn't seen fail, returning them unchanged places nothing
+    pub resting_bids: Vec<Quote>,
+    pub resting_asks: Vec<Quote>,
+    //a transaction for that side hasn't come back yet, nothing more is sent for it until it has
+    pub bid_in_flight: bool,
+    pub ask_in_flight: bool,
+}
+
+impl MarketSnapshot {
+    //a one sided book has no midpoint
+    pub fn midpoint(&self) -> Option<f64> {
+        if self.best_bid > 0. && self.best_ask > 0. {
+            Some((self.best_bid + self.best_ask) / 2.)
+        } else {
+            None
+        }
     }
 }
 
-//
-// pub struct ConcreteStrategy {
-//     uuid: Uuid,
-// }
-//
-// impl ConcreteStrategy {
-//     pub fn new() -> Self {
-//         let strategy = Self {
-//             uuid: Uuid::new(),
-//         };
-//         strategy.startup_complete();
-//         strategy
-//     }
-// }
-//
-// impl Strategy for ConcreteStrategy {
-//     fn uuid(&self) -> Uuid {
-//         return self.uuid;
-//     }
-//
-//     fn start(&self) {
-//         // Implement the start method here
-//     }
-// }
+//the quoting logic of a bot, kept free of rpc and transactions so the same strategy runs live and in simulation.
+//The executor calls on_update on every tick and turns what comes back into orders
+#[async_trait]
+pub trait Strategy: Send + Sync {
+    async fn on_update(&mut self, snapshot: &MarketSnapshot) -> DesiredQuotes;
+}
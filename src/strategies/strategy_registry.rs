@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use solana_sdk::signer::Signer;
+use tokio::sync::mpsc;
+use crate::arcana_web_config::BotSettings;
+use crate::pricing::jupiter_pricing_source::JupiterPricingSource;
+use crate::rpc::chain_client::ChainClient;
+use crate::strategies::bot_store::OrderStateRecorder;
+use crate::strategies::open_book::open_book_spl_usdc::OpenBookSplUsdc;
+use crate::strategies::order_executor::OrderExecutor;
+use crate::strategies::quote_params::QuoteParams;
+use crate::transactions::emergency_cancel::EmergencyCancels;
+use crate::transactions::priority_fees::FeeTracker;
+
+//which strategy a bot runs and that strategy's own config, as the api takes it and the store keeps it.
+//Bots from before there was more than one kind have no strategy field
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BotConfig {
+    #[serde(default = "default_strategy")]
+    pub strategy: String,
+    #[serde(flatten)]
+    pub config: Map<String, Value>,
+}
+
+fn default_strategy() -> String {
+    OpenBookSplUsdc::NAME.to_string()
+}
+
+//what the bot manager hands every strategy, whatever its kind
+pub struct StrategyContext {
+    pub order_state: Arc<OrderStateRecorder>,
+    pub emergency_cancels: Arc<EmergencyCancels>,
+    pub chain_client: Arc<dyn ChainClient>,
+    pub jupiter_pricing_source: JupiterPricingSource,
+    pub signer: Arc<dyn Signer + Send + Sync>,
+    pub fee_tracker: Arc<FeeTracker>,
+    pub settings: BotSettings,
+}
+
+//the strategy, already handed to the executor that runs it. A strategy that quotes hands back a sender for live
+//quote params updates, the others don't take any
+pub struct BuiltStrategy {
+    pub executor: OrderExecutor,
+    pub quote_updates: Option<mpsc::UnboundedSender<QuoteParams>>,
+}
+
+pub type StrategyFuture = Pin<Box<dyn Future<Output = Result<BuiltStrategy, String>> + Send>>;
+
+//a kind of strategy a bot can run. The config is the kind's whole schema: the api and the store only ever see it as
+//json, anything left out of it comes from Default
+pub trait StrategyKind {
+    const NAME: &'static str;
+    const DESCRIPTION: &'static str;
+    type Config: serde::Serialize + DeserializeOwned + Default;
+
+    fn validate(config: &Self::Config) -> Result<(), String>;
+    //everything slow, like loading the market, belongs in the future
+    fn build(config: Self::Config, context: StrategyContext) -> StrategyFuture;
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StrategyKindInfo {
+    pub name: String,
+    pub description: String,
+    //the config a bot of this kind gets when nothing is given, from the [bot] section of the config
+    pub default_config: Value,
+}
+
+//a config checked against its kind and filled in with the kind's defaults, with the future that builds the strategy
+pub struct PreparedStrategy {
+    pub config: Map<String, Value>,
+    pub build: StrategyFuture,
+}
+
+struct RegisteredKind {
+    description: &'static str,
+    default_config: fn() -> Value,
+    prepare: fn(Map<String, Value>, StrategyContext) -> Result<PreparedStrategy, String>,
+}
+
+pub struct StrategyRegistry {
+    kinds: BTreeMap<&'static str, RegisteredKind>,
+}
+
+impl StrategyRegistry {
+    pub fn new() -> Self {
+        Self { kinds: BTreeMap::new() }
+    }
+
+    //every strategy arcana ships with
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register::<OpenBookSplUsdc>();
+        registry
+    }
+
+    pub fn register<K: StrategyKind>(&mut self) {
+        self.kinds.insert(K::NAME, RegisteredKind {
+            description: K::DESCRIPTION,
+            default_config: default_config::<K>,
+            prepare: prepare::<K>,
+        });
+    }
+
+    pub fn kinds(&self) -> Vec<StrategyKindInfo> {
+        self.kinds.iter()
+            .map(|(name, kind)| StrategyKindInfo {
+                name: name.to_string(),
+                description: kind.description.to_string(),
+                default_config: (kind.default_config)(),
+            })
+            .collect()
+    }
+
+    pub fn prepare(&self, config: &BotConfig, context: StrategyContext) -> Result<PreparedStrategy, String> {
+        let kind = self.kinds.get(config.strategy.as_str()).ok_or_else(|| format!(
+            "unknown strategy {}, expected one of {}",
+            config.strategy,
+            self.kinds.keys().cloned().collect::<Vec<_>>().join(", ")
+        ))?;
+        (kind.prepare)(config.config.clone(), context)
+    }
+}
+
+fn default_config<K: StrategyKind>() -> Value {
+    serde_json::to_value(K::Config::default()).unwrap_or(Value::Null)
+}
+
+fn prepare<K: StrategyKind>(config: Map<String, Value>, context: StrategyContext) -> Result<PreparedStrategy, String> {
+    let config: K::Config = serde_json::from_value(Value::Object(config))
+        .map_err(|e| format!("invalid {} config: {}", K::NAME, e))?;
+    K::validate(&config)?;
+    //stored the way the kind reads it, defaults and all, so a later change to the defaults doesn't change the bot
+    let normalized = match serde_json::to_value(&config).map_err(|e| e.to_string())? {
+        Value::Object(normalized) => normalized,
+        _ => return Err(format!("{} config isn't a json object", K::NAME)),
+    };
+    Ok(PreparedStrategy {
+        config: normalized,
+        build: K::build(config, context),
+    })
+}
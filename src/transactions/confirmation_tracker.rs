@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::interval;
+
+use crate::rpc::chain_client::ChainClient;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const REBROADCAST_INTERVAL: Duration = Duration::from_secs(2);
+//getSignatureStatuses rejects more than this many signatures per request
+const MAX_SIGNATURES_PER_STATUS_REQUEST: usize = 256;
+
+#[derive(Debug)]
+pub enum TransactionStatus {
+    Confirmed { slot: u64 },
+    //landed but the program returned an error, fees were still charged
+    Failed { slot: u64, error: String },
+    //refused by the node (usually preflight), it never landed
+    Rejected { error: String },
+    //the blockhash expired before the transaction landed, so it can never land
+    Expired,
+}
+
+pub struct TransactionOutcome<T> {
+    pub signature: Signature,
+    pub status: TransactionStatus,
+    pub tag: T,
+}
+
+struct Submission<T> {
+    transaction: VersionedTransaction,
+    last_valid_block_height: u64,
+    tag: T,
+}
+
+struct PendingTransaction<T> {
+    submission: Submission<T>,
+    last_sent: Instant,
+}
+
+//sends transactions without blocking the caller, rebroadcasts them until their blockhash expires
+//and hands the final outcome of each one back through try_next_outcome
+pub struct ConfirmationTracker<T> {
+    submissions: UnboundedSender<Submission<T>>,
+    outcomes: UnboundedReceiver<TransactionOutcome<T>>,
+    in_flight: Arc<AtomicUsize>,
+    worker: Option<ConfirmationWorker<T>>,
+}
+
+impl<T: Send + 'static> ConfirmationTracker<T> {
+    pub fn new(chain_client: Arc<dyn ChainClient>) -> Self {
+        let (submission_sender, submission_receiver) = unbounded_channel();
+        let (outcome_sender, outcome_receiver) = unbounded_channel();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+
+        Self {
+            submissions: submission_sender,
+            outcomes: outcome_receiver,
+            in_flight: in_flight.clone(),
+            worker: Some(ConfirmationWorker {
+                chain_client,
+                submissions: submission_receiver,
+                outcomes: outcome_sender,
+                in_flight,
+                pending: HashMap::new(),
+            }),
+        }
+    }
+
+    //spawns the worker onto the runtime the caller is running on
+    pub fn start(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            tokio::spawn(worker.run());
+        }
+    }
+
+    //the transaction must already be signed, its first signature is what gets tracked
+    pub fn submit(&self, transaction: VersionedTransaction, last_valid_block_height: u64, tag: T) -> Signature {
+        let signature = transaction.signatures[0];
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let submission = Submission { transaction, last_valid_block_height, tag };
+        if self.submissions.send(submission).is_err() {
+            eprintln!("Confirmation tracker stopped, dropping transaction {}", signature);
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        }
+        signature
+    }
+
+    pub fn try_next_outcome(&mut self) -> Option<TransactionOutcome<T>> {
+        self.outcomes.try_recv().ok()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+}
+
+struct ConfirmationWorker<T> {
+    chain_client: Arc<dyn ChainClient>,
+    submissions: UnboundedReceiver<Submission<T>>,
+    outcomes: UnboundedSender<TransactionOutcome<T>>,
+    in_flight: Arc<AtomicUsize>,
+    pending: HashMap<Signature, PendingTransaction<T>>,
+}
+
+impl<T: Send + 'static> ConfirmationWorker<T> {
+    async fn run(mut self) {
+        let mut poll = interval(POLL_INTERVAL);
+        loop {
+            tokio::select! {
+                submission = self.submissions.recv() => match submission {
+                    Some(submission) => self.send_first(submission).await,
+                    //the tracker was dropped, nobody is left to report outcomes to
+                    None => return,
+                },
+                _ = poll.tick() => self.poll_pending().await,
+            }
+        }
+    }
+
+    async fn send_first(&mut self, submission: Submission<T>) {
+        let signature = submission.transaction.signatures[0];
+
+        //preflight on the first send so obviously bad transactions are reported straight away
+        let config = RpcSendTransactionConfig {
+            skip_preflight: false,
+            preflight_commitment: Some(CommitmentConfig::processed().commitment),
+            max_retries: Some(0),
+            ..RpcSendTransactionConfig::default()
+        };
+        let sent = self.chain_client.send_transaction(&submission.transaction, config).await;
+        if let Err(err) = sent {
+            self.finish(signature, submission.tag, TransactionStatus::Rejected { error: err.to_string() });
+            return;
+        }
+
+        self.pending.insert(signature, PendingTransaction {
+            submission,
+            last_sent: Instant::now(),
+        });
+    }
+
+    async fn poll_pending(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let signatures: Vec<Signature> = self.pending.keys().cloned().collect();
+        for chunk in signatures.chunks(MAX_SIGNATURES_PER_STATUS_REQUEST) {
+            let statuses = match self.chain_client.get_signature_statuses(chunk).await {
+                Ok(statuses) => statuses,
+                Err(err) => {
+                    eprintln!("Error getting signature statuses: {}", err);
+                    return;
+                }
+            };
+
+            for (signature, status) in chunk.iter().zip(statuses) {
+                let status = match status {
+                    Some(status) if status.satisfies_commitment(CommitmentConfig::confirmed()) => status,
+                    _ => continue,
+                };
+                if let Some(pending) = self.pending.remove(signature) {
+                    let outcome = match status.err {
+                        None => TransactionStatus::Confirmed { slot: status.slot },
+                        Some(err) => TransactionStatus::Failed { slot: status.slot, error: err.to_string() },
+                    };
+                    self.finish(*signature, pending.submission.tag, outcome);
+                }
+            }
+        }
+
+        let block_height = match self.chain_client.get_block_height(CommitmentConfig::confirmed()).await {
+            Ok(block_height) => block_height,
+            Err(err) => {
+                eprintln!("Error getting block height: {}", err);
+                return;
+            }
+        };
+
+        let expired: Vec<Signature> = self.pending.iter()
+            .filter(|(_, pending)| block_height > pending.submission.last_valid_block_height)
+            .map(|(signature, _)| *signature)
+            .collect();
+        for signature in expired {
+            if let Some(pending) = self.pending.remove(&signature) {
+                self.finish(signature, pending.submission.tag, TransactionStatus::Expired);
+            }
+        }
+
+        self.rebroadcast().await;
+    }
+
+    async fn rebroadcast(&mut self) {
+        let config = RpcSendTransactionConfig {
+            skip_preflight: true,
+            max_retries: Some(0),
+            ..RpcSendTransactionConfig::default()
+        };
+
+        for pending in self.pending.values_mut() {
+            if pending.last_sent.elapsed() < REBROADCAST_INTERVAL {
+                continue;
+            }
+            let sent = self.chain_client.send_transaction(&pending.submission.transaction, config).await;
+            if let Err(err) = sent {
+                eprintln!("Error rebroadcasting {}: {}", pending.submission.transaction.signatures[0], err);
+            }
+            pending.last_sent = Instant::now();
+        }
+    }
+
+    fn finish(&self, signature: Signature, tag: T, status: TransactionStatus) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        let _ = self.outcomes.send(TransactionOutcome { signature, status, tag });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::message::{Message, VersionedMessage};
+    use solana_sdk::pubkey::Pubkey;
+    use solana_sdk::signature::Keypair;
+    use solana_sdk::signer::Signer;
+    use solana_sdk::system_instruction;
+    use solana_sdk::transaction::TransactionError;
+    use tokio::time::sleep;
+
+    use crate::rpc::mock_chain_client::MockChainClient;
+
+    use super::*;
+
+    const OUTCOME_TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn signed_transfer() -> VersionedTransaction {
+        let payer = Keypair::new();
+        let instruction = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        let message = VersionedMessage::Legacy(Message::new(&[instruction], Some(&payer.pubkey())));
+        VersionedTransaction::try_new(message, &[&payer]).unwrap()
+    }
+
+    fn started_tracker(chain_client: Arc<MockChainClient>) -> ConfirmationTracker<u32> {
+        let mut tracker = ConfirmationTracker::new(chain_client);
+        tracker.start();
+        tracker
+    }
+
+    async fn next_outcome(tracker: &mut ConfirmationTracker<u32>) -> TransactionOutcome<u32> {
+        let started = Instant::now();
+        loop {
+            if let Some(outcome) = tracker.try_next_outcome() {
+                return outcome;
+            }
+            assert!(started.elapsed() < OUTCOME_TIMEOUT, "no outcome after {:?}", OUTCOME_TIMEOUT);
+            sleep(Duration::from_millis(20)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn landed_transaction_is_confirmed() {
+        let chain_client = Arc::new(MockChainClient::new());
+        let mut tracker = started_tracker(chain_client.clone());
+
+        let signature = tracker.submit(signed_transfer(), 150, 7);
+        assert_eq!(tracker.in_flight(), 1);
+
+        let outcome = next_outcome(&mut tracker).await;
+        assert_eq!(outcome.signature, signature);
+        assert_eq!(outcome.tag, 7);
+        assert!(matches!(outcome.status, TransactionStatus::Confirmed { slot: 1 }));
+        assert_eq!(tracker.in_flight(), 0);
+        assert_eq!(chain_client.sent_transactions().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn refused_send_is_rejected() {
+        let chain_client = Arc::new(MockChainClient::new());
+        let mut tracker = started_tracker(chain_client.clone());
+        chain_client.fail_next_send("preflight failed");
+
+        tracker.submit(signed_transfer(), 150, 1);
+
+        let outcome = next_outcome(&mut tracker).await;
+        match outcome.status {
+            TransactionStatus::Rejected { error } => assert!(error.contains("preflight failed")),
+            status => panic!("expected rejected, got {:?}", status),
+        }
+        assert_eq!(tracker.in_flight(), 0);
+        assert!(chain_client.sent_transactions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn program_error_is_failed() {
+        let chain_client = Arc::new(MockChainClient::new());
+        let mut tracker = started_tracker(chain_client.clone());
+        chain_client.fail_next_execution(TransactionError::InsufficientFundsForFee);
+
+        tracker.submit(signed_transfer(), 150, 1);
+
+        let outcome = next_outcome(&mut tracker).await;
+        assert!(matches!(outcome.status, TransactionStatus::Failed { slot: 1, .. }), "{:?}", outcome.status);
+        assert_eq!(tracker.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn transaction_that_never_lands_expires_with_its_blockhash() {
+        let chain_client = Arc::new(MockChainClient::new());
+        let mut tracker = started_tracker(chain_client.clone());
+        chain_client.drop_sends(true);
+
+        tracker.submit(signed_transfer(), 3, 1);
+        //still valid, nothing may come back yet
+        sleep(POLL_INTERVAL * 2).await;
+        assert!(tracker.try_next_outcome().is_none());
+        assert_eq!(tracker.in_flight(), 1);
+
+        chain_client.advance_slot(3);
+        let outcome = next_outcome(&mut tracker).await;
+        assert!(matches!(outcome.status, TransactionStatus::Expired), "{:?}", outcome.status);
+        assert_eq!(tracker.in_flight(), 0);
+    }
+}
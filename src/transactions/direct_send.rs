@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::Signature;
+use solana_sdk::signer::Signer;
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+
+use crate::rpc::chain_client::ChainClient;
+
+//for one-off transactions (setup, settling, cancelling everything) where the caller wants to wait for
+//confirmation rather than go through the confirmation tracker. The first signer pays
+pub async fn send_and_confirm(
+    chain_client: &dyn ChainClient,
+    instructions: &[Instruction],
+    signers: Vec<Arc<dyn Signer + Send + Sync>>,
+) -> Result<Signature, Box<dyn std::error::Error>> {
+    let payer = signers.first().ok_or("no signer for transaction")?.pubkey();
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&payer));
+    let (recent_blockhash, _) = chain_client.get_latest_blockhash(CommitmentConfig::confirmed()).await?;
+
+    //any of them may be a remote signer doing blocking io
+    let transaction = tokio::task::spawn_blocking(move || {
+        let signers: Vec<&dyn Signer> = signers.iter().map(|signer| signer.as_ref() as &dyn Signer).collect();
+        transaction.try_sign(&signers, recent_blockhash).map(|_| transaction)
+    }).await??;
+
+    Ok(chain_client.send_and_confirm_transaction(&VersionedTransaction::from(transaction)).await?)
+}
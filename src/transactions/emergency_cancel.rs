@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::nonce::state::{State, Versions};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::Signer;
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+
+use crate::rpc::chain_client::ChainClient;
+use crate::serum::open_orders::CANCELS_PER_TRANSACTION;
+use crate::transactions::direct_send::send_and_confirm;
+
+//the nonce advance and the two compute budget instructions take three slots of each transaction
+pub const CANCELS_PER_NONCE_TRANSACTION: usize = CANCELS_PER_TRANSACTION - 3;
+//a cancel walks the book's slab, this leaves room for a deep one. The nonce advance is a few hundred units
+const COMPUTE_UNITS_PER_CANCEL: u32 = 30_000;
+const NONCE_ADVANCE_COMPUTE_UNITS: u32 = 1_000;
+
+//cancel-all transactions signed ahead of time against durable nonces, as many per open orders account as its
+//resting orders take, so the kill switch can broadcast them without first fetching a blockhash from a congested rpc.
+//The order executors keep them current, re-signing whenever their resting orders change
+#[derive(Default)]
+pub struct EmergencyCancels {
+    transactions: Mutex<HashMap<Pubkey, Vec<VersionedTransaction>>>,
+}
+
+impl EmergencyCancels {
+    //empty when nothing is resting, there is nothing to cancel then
+    pub fn set(&self, open_orders: Pubkey, cancels: Vec<VersionedTransaction>) {
+        let mut transactions = self.transactions.lock().unwrap();
+        if cancels.is_empty() {
+            transactions.remove(&open_orders);
+        } else {
+            transactions.insert(open_orders, cancels);
+        }
+    }
+
+    //each transaction advances its nonce, so it can only ever be sent once
+    pub fn take_all(&self) -> Vec<(Pubkey, VersionedTransaction)> {
+        self.transactions.lock().unwrap()
+            .drain()
+            .flat_map(|(open_orders, cancels)| cancels.into_iter().map(move |transaction| (open_orders, transaction)))
+            .collect()
+    }
+}
+
+//sends every pre-signed cancel straight away without preflight, a simulation is one more rpc round trip and
+//a cancel of an order that already filled fails either way
+pub async fn broadcast(chain_client: &dyn ChainClient, transactions: Vec<(Pubkey, VersionedTransaction)>) -> Vec<(Pubkey, Result<Signature, String>)> {
+    let config = RpcSendTransactionConfig {
+        skip_preflight: true,
+        ..RpcSendTransactionConfig::default()
+    };
+    let mut results = Vec::with_capacity(transactions.len());
+    for (open_orders, transaction) in transactions {
+        let result = chain_client.send_transaction(&transaction, config).await.map_err(|e| e.to_string());
+        results.push((open_orders, result));
+    }
+    results
+}
+
+//a nonce account the authority controls, paid for by the authority
+pub async fn create_nonce_account(
+    chain_client: &dyn ChainClient,
+    authority: Arc<dyn Signer + Send + Sync>,
+) -> Result<Pubkey, Box<dyn std::error::Error>> {
+    let nonce_account = Arc::new(Keypair::new());
+    let authority_pubkey = authority.pubkey();
+    let instructions = system_instruction::create_nonce_account(
+        &authority_pubkey,
+        &nonce_account.pubkey(),
+        &authority_pubkey,
+        Rent::default().minimum_balance(State::size()),
+    );
+
+    send_and_confirm(chain_client, &instructions, vec![authority, nonce_account.clone()]).await?;
+    println!("Created nonce account {}", nonce_account.pubkey());
+    Ok(nonce_account.pubkey())
+}
+
+//the blockhash a transaction against this nonce has to be signed with, it only changes when the nonce is advanced
+pub async fn nonce_blockhash(chain_client: &dyn ChainClient, nonce_account: &Pubkey) -> Result<Hash, Box<dyn std::error::Error>> {
+    let account = chain_client.get_account(nonce_account, CommitmentConfig::confirmed()).await?
+        .ok_or_else(|| format!("nonce account {} not found", nonce_account))?;
+    let versions: Versions = bincode::deserialize(&account.data)?;
+    match versions.state() {
+        State::Initialized(data) => Ok(data.blockhash()),
+        State::Uninitialized => Err(format!("nonce account {} is not initialized", nonce_account).into()),
+    }
+}
+
+//advance_nonce has to be the first instruction for the runtime to accept the nonce in place of a recent blockhash.
+//The cancels go out when the market is congested, so they bid compute_unit_price for a limit sized to them
+pub async fn sign_with_nonce(
+    nonce_account: &Pubkey,
+    nonce_blockhash: Hash,
+    authority: Arc<dyn Signer + Send + Sync>,
+    compute_unit_price: u64,
+    cancels: Vec<Instruction>,
+) -> Result<VersionedTransaction, Box<dyn std::error::Error>> {
+    let authority_pubkey = authority.pubkey();
+    let compute_unit_limit = NONCE_ADVANCE_COMPUTE_UNITS + COMPUTE_UNITS_PER_CANCEL * cancels.len() as u32;
+    let mut nonce_instructions = vec![
+        system_instruction::advance_nonce_account(nonce_account, &authority_pubkey),
+        ComputeBudgetInstruction::set_compute_unit_limit(compute_unit_limit),
+        ComputeBudgetInstruction::set_compute_unit_price(compute_unit_price),
+    ];
+    nonce_instructions.extend(cancels);
+    let mut transaction = Transaction::new_with_payer(&nonce_instructions, Some(&authority_pubkey));
+
+    //the authority may be a remote signer doing blocking io
+    let transaction = tokio::task::spawn_blocking(move || {
+        transaction.try_sign(&[authority.as_ref() as &dyn Signer], nonce_blockhash).map(|_| transaction)
+    }).await??;
+    Ok(VersionedTransaction::from(transaction))
+}
+
+#[cfg(test)]
+mod tests {
+    use serum_dex::matching::Side;
+    use solana_sdk::compute_budget;
+    use solana_sdk::system_program;
+    use solana_sdk::packet::PACKET_DATA_SIZE;
+
+    use crate::serum::market_feed::OpenOrdersSnapshot;
+    use crate::serum::market_fixture::MarketFixture;
+    use crate::serum::open_orders::cancel_all_instructions;
+
+    use super::*;
+
+    #[test]
+    fn every_cancel_of_an_account_is_taken_once() {
+        let emergency_cancels = EmergencyCancels::default();
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        emergency_cancels.set(first, vec![VersionedTransaction::default(); 3]);
+        emergency_cancels.set(second, vec![VersionedTransaction::default()]);
+        //re-signed for fewer orders, then nothing left resting
+        emergency_cancels.set(first, vec![VersionedTransaction::default(); 2]);
+        emergency_cancels.set(second, Vec::new());
+
+        let taken = emergency_cancels.take_all();
+        assert_eq!(taken.len(), 2);
+        assert!(taken.iter().all(|(open_orders, _)| *open_orders == first));
+        assert!(emergency_cancels.take_all().is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_full_chunk_of_priced_cancels_fits_a_packet() {
+        let fixture = MarketFixture::new();
+        let authority = Arc::new(Keypair::new());
+        let open_orders = Pubkey::new_unique();
+        let orders: Vec<(Side, u128, u64)> = (0..CANCELS_PER_NONCE_TRANSACTION)
+            .map(|i| (if i % 2 == 0 { Side::Bid } else { Side::Ask }, u128::MAX - i as u128, i as u64 + 1))
+            .collect();
+        let snapshot = OpenOrdersSnapshot::from_account_data(&fixture.open_orders_account(&authority.pubkey(), &orders).data).unwrap();
+        let cancels = cancel_all_instructions(&fixture.state, &open_orders, &snapshot, &authority.pubkey()).unwrap();
+        assert_eq!(cancels.len(), CANCELS_PER_NONCE_TRANSACTION);
+
+        let transaction = sign_with_nonce(&Pubkey::new_unique(), Hash::new_unique(), authority, 500_000, cancels).await.unwrap();
+        let instructions = transaction.message.instructions();
+        let keys = transaction.message.static_account_keys();
+        assert!(system_program::check_id(&keys[instructions[0].program_id_index as usize]));
+        assert!(instructions[1..3].iter().all(|instruction| compute_budget::check_id(&keys[instruction.program_id_index as usize])));
+        assert!(bincode::serialized_size(&transaction).unwrap() as usize <= PACKET_DATA_SIZE);
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use solana_address_lookup_table_program::instruction::{create_lookup_table, extend_lookup_table};
+use solana_address_lookup_table_program::state::AddressLookupTable;
+use solana_client::rpc_config::RpcProgramAccountsConfig;
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signer::Signer;
+use serum_dex::state::MarketState;
+
+use crate::rpc::chain_client::ChainClient;
+use crate::serum::serum_utils::{pub_key, SYSVAR_RENT_PUBKEY, TOKEN_PROGRAM_ID};
+use crate::transactions::direct_send::send_and_confirm;
+
+//ProgramState tag (4) + deactivation_slot (8) + last_extended_slot (8) + start index (1) + Option tag (1)
+const LOOKUP_TABLE_AUTHORITY_OFFSET: usize = 22;
+//keeps each extend transaction well under the legacy size limit
+const MAX_ADDRESSES_PER_EXTEND: usize = 20;
+//a slot is ~400ms, the table is normally usable after one or two
+const WARM_UP_POLL_INTERVAL: Duration = Duration::from_millis(400);
+const MAX_WARM_UP_POLLS: u32 = 50;
+
+//accounts every order transaction on a market touches regardless of which bot sends it
+pub fn market_static_accounts(market: &MarketState, vault_signer: &Pubkey) -> Vec<Pubkey> {
+    vec![
+        pub_key(market.own_address),
+        pub_key(market.bids),
+        pub_key(market.asks),
+        pub_key(market.event_q),
+        pub_key(market.req_q),
+        pub_key(market.coin_vault),
+        pub_key(market.pc_vault),
+        *vault_signer,
+        *TOKEN_PROGRAM_ID,
+        *SYSVAR_RENT_PUBKEY,
+    ]
+}
+
+//one lookup table per market, owned by the trading account. Tables are found again on chain
+//by authority after a restart, so nothing about them needs to be stored locally
+pub struct LookupTableManager {
+    authority: Arc<dyn Signer + Send + Sync>,
+    tables: Mutex<HashMap<Pubkey, AddressLookupTableAccount>>,
+}
+
+impl LookupTableManager {
+    pub fn new(authority: Arc<dyn Signer + Send + Sync>) -> Self {
+        Self {
+            authority,
+            tables: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn market_lookup_table(
+        &self,
+        chain_client: &dyn ChainClient,
+        market_id: &Pubkey,
+        addresses: &[Pubkey],
+    ) -> Result<AddressLookupTableAccount, Box<dyn std::error::Error>> {
+        if let Some(table) = self.tables.lock().unwrap().get(market_id) {
+            if addresses.iter().all(|address| table.addresses.contains(address)) {
+                return Ok(table.clone());
+            }
+        }
+
+        let existing = self.find_table(chain_client, market_id).await?;
+        let (table, last_extended_slot) = match existing {
+            Some(found) => found,
+            None => (self.create_table(chain_client).await?, 0),
+        };
+
+        let missing: Vec<Pubkey> = addresses.iter()
+            .filter(|address| !table.addresses.contains(address))
+            .cloned()
+            .collect();
+        let (table, last_extended_slot) = if missing.is_empty() {
+            (table, last_extended_slot)
+        } else {
+            self.extend_table(chain_client, &table.key, missing).await?;
+            fetch_table(chain_client, &table.key).await?
+        };
+        //addresses can only be looked up from the slot after the one that added them, a transaction using the table
+        //any sooner fails
+        wait_for_slot_after(chain_client, last_extended_slot).await?;
+
+        println!("Using lookup table {} ({} addresses) for market {}", table.key, table.addresses.len(), market_id);
+        self.tables.lock().unwrap().insert(*market_id, table.clone());
+        Ok(table)
+    }
+
+    //the table and the slot it was last extended in
+    async fn find_table(&self, chain_client: &dyn ChainClient, market_id: &Pubkey) -> Result<Option<(AddressLookupTableAccount, u64)>, Box<dyn std::error::Error>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                LOOKUP_TABLE_AUTHORITY_OFFSET,
+                self.authority.pubkey().as_ref(),
+            ))]),
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = chain_client.get_program_accounts(&solana_address_lookup_table_program::id(), config).await?;
+
+        for (key, account) in accounts {
+            let table = match AddressLookupTable::deserialize(&account.data) {
+                Ok(table) => table,
+                Err(_) => continue,
+            };
+            //deactivated tables can't be extended or used for much longer
+            if table.meta.deactivation_slot != u64::MAX {
+                continue;
+            }
+            if table.addresses.contains(market_id) {
+                return Ok(Some((AddressLookupTableAccount {
+                    key,
+                    addresses: table.addresses.to_vec(),
+                }, table.meta.last_extended_slot)));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn create_table(&self, chain_client: &dyn ChainClient) -> Result<AddressLookupTableAccount, Box<dyn std::error::Error>> {
+        let authority = self.authority.pubkey();
+        //the slot has to still be in the SlotHashes sysvar, a finalized one always is
+        let recent_slot = chain_client.get_slot(CommitmentConfig::finalized()).await?;
+        let (instruction, table_key) = create_lookup_table(authority, authority, recent_slot);
+
+        self.send_setup_transaction(chain_client, &[instruction]).await?;
+        println!("Created lookup table {}", table_key);
+
+        Ok(AddressLookupTableAccount {
+            key: table_key,
+            addresses: Vec::new(),
+        })
+    }
+
+    async fn extend_table(&self, chain_client: &dyn ChainClient, table_key: &Pubkey, addresses: Vec<Pubkey>) -> Result<(), Box<dyn std::error::Error>> {
+        let authority = self.authority.pubkey();
+        for chunk in addresses.chunks(MAX_ADDRESSES_PER_EXTEND) {
+            let instruction = extend_lookup_table(*table_key, authority, Some(authority), chunk.to_vec());
+            self.send_setup_transaction(chain_client, &[instruction]).await?;
+        }
+        Ok(())
+    }
+
+    //one-off setup, so waiting for confirmation here is fine
+    async fn send_setup_transaction(&self, chain_client: &dyn ChainClient, instructions: &[Instruction]) -> Result<(), Box<dyn std::error::Error>> {
+        send_and_confirm(chain_client, instructions, vec![self.authority.clone()]).await?;
+        Ok(())
+    }
+}
+
+async fn fetch_table(chain_client: &dyn ChainClient, table_key: &Pubkey) -> Result<(AddressLookupTableAccount, u64), Box<dyn std::error::Error>> {
+    let account = chain_client.get_account(table_key, CommitmentConfig::confirmed()).await?
+        .ok_or_else(|| format!("lookup table {} not found", table_key))?;
+    let table = AddressLookupTable::deserialize(&account.data)?;
+    Ok((AddressLookupTableAccount {
+        key: *table_key,
+        addresses: table.addresses.to_vec(),
+    }, table.meta.last_extended_slot))
+}
+
+//once the cluster has confirmed a later slot every bank a transaction can land in is past it
+async fn wait_for_slot_after(chain_client: &dyn ChainClient, slot: u64) -> Result<(), Box<dyn std::error::Error>> {
+    for _ in 0..MAX_WARM_UP_POLLS {
+        if chain_client.get_slot(CommitmentConfig::confirmed()).await? > slot {
+            return Ok(());
+        }
+        tokio::time::sleep(WARM_UP_POLL_INTERVAL).await;
+    }
+    Err(format!("the cluster is still not past slot {}, the lookup table can't be used yet", slot).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_address_lookup_table_program::state::LookupTableMeta;
+    use solana_sdk::account::Account;
+    use solana_sdk::signature::Keypair;
+
+    use crate::rpc::mock_chain_client::MockChainClient;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn a_table_is_only_handed_out_once_its_last_extension_is_usable() {
+        let chain_client = Arc::new(MockChainClient::new());
+        let authority = Arc::new(Keypair::new());
+        let market_id = Pubkey::new_unique();
+        let addresses = vec![market_id, Pubkey::new_unique(), Pubkey::new_unique()];
+        let table = AddressLookupTable {
+            meta: LookupTableMeta {
+                authority: Some(authority.pubkey()),
+                //extended in the slot the chain is at now
+                last_extended_slot: chain_client.get_slot(CommitmentConfig::confirmed()).await.unwrap(),
+                ..LookupTableMeta::default()
+            },
+            addresses: addresses.clone().into(),
+        };
+        let table_key = Pubkey::new_unique();
+        chain_client.set_account(table_key, Account {
+            lamports: 1,
+            data: table.serialize_for_tests().unwrap(),
+            owner: solana_address_lookup_table_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        });
+
+        let manager = Arc::new(LookupTableManager::new(authority));
+        let lookup = tokio::spawn({
+            let (chain_client, manager, addresses) = (chain_client.clone(), manager.clone(), addresses.clone());
+            async move { manager.market_lookup_table(chain_client.as_ref(), &market_id, &addresses).await.map_err(|e| e.to_string()) }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(manager.tables.lock().unwrap().is_empty());
+
+        chain_client.advance_slot(1);
+        let found = lookup.await.unwrap().unwrap();
+        assert_eq!(found.key, table_key);
+        assert_eq!(found.addresses, addresses);
+        //nothing was sent, the table already held every address
+        assert!(chain_client.sent_transactions().is_empty());
+        assert!(manager.tables.lock().unwrap().contains_key(&market_id));
+    }
+}
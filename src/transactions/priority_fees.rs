@@ -0,0 +1,301 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde_derive::{Deserialize, Serialize};
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::address_lookup_table_account::AddressLookupTableAccount;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::{v0, VersionedMessage};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+
+use crate::rpc::chain_client::ChainClient;
+
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u64 = 1_000_000;
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+//what the busiest recent slots paid, for transactions that have to land when everyone else is trying to as well
+const HIGH_PERCENTILE: f64 = 95.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriorityFeePolicy {
+    //percentile of the recent per-slot fees paid to write lock the same accounts, 0-100
+    pub percentile: f64,
+    pub min_micro_lamports: u64,
+    pub max_micro_lamports: u64,
+    //simulated compute units are scaled by this so a slightly busier book doesn't run out of CU
+    pub compute_unit_margin: f64,
+    //used whenever the pre-flight simulation fails or doesn't report units consumed
+    pub fallback_compute_unit_limit: u32,
+}
+
+impl Default for PriorityFeePolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PriorityFeePolicy {
+    pub fn new() -> Self {
+        Self {
+            percentile: 75.0,
+            min_micro_lamports: 10_000,
+            max_micro_lamports: 500_000,
+            compute_unit_margin: 1.2,
+            fallback_compute_unit_limit: 54_800,
+        }
+    }
+
+    pub async fn compute_unit_price(&self, chain_client: &dyn ChainClient, writable_accounts: &[Pubkey]) -> u64 {
+        self.price_at(self.recent_fees(chain_client, writable_accounts).await, self.percentile)
+    }
+
+    async fn recent_fees(&self, chain_client: &dyn ChainClient, writable_accounts: &[Pubkey]) -> Vec<u64> {
+        match chain_client.get_recent_prioritization_fees(writable_accounts).await {
+            Ok(fees) => fees,
+            Err(err) => {
+                eprintln!("Error getting recent prioritization fees: {}", err);
+                Vec::new()
+            }
+        }
+    }
+
+    fn price_at(&self, fees: Vec<u64>, fee_percentile: f64) -> u64 {
+        percentile(fees, fee_percentile)
+            .unwrap_or(self.min_micro_lamports)
+            .clamp(self.min_micro_lamports, self.max_micro_lamports)
+    }
+
+    pub async fn compute_unit_limit(&self, chain_client: &dyn ChainClient, payer: &Pubkey, instructions: &[Instruction], lookup_tables: &[AddressLookupTableAccount]) -> u32 {
+        let mut simulation_instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT)];
+        simulation_instructions.extend_from_slice(instructions);
+        let message = match v0::Message::try_compile(payer, &simulation_instructions, lookup_tables, Hash::default()) {
+            Ok(message) => VersionedMessage::V0(message),
+            Err(err) => {
+                eprintln!("Error compiling simulation message: {}", err);
+                return self.fallback_compute_unit_limit;
+            }
+        };
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default(); message.header().num_required_signatures as usize],
+            message,
+        };
+
+        //unsigned on purpose, the node swaps in a fresh blockhash and skips signature checks
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(CommitmentConfig::processed()),
+            ..RpcSimulateTransactionConfig::default()
+        };
+
+        match chain_client.simulate_transaction(&transaction, config).await {
+            Ok(result) => match (result.err, result.units_consumed) {
+                (None, Some(units_consumed)) => {
+                    ((units_consumed as f64 * self.compute_unit_margin).ceil() as u32).min(MAX_COMPUTE_UNIT_LIMIT)
+                }
+                (Some(err), _) => {
+                    eprintln!("Pre-flight simulation failed: {}", err);
+                    self.fallback_compute_unit_limit
+                }
+                (None, None) => self.fallback_compute_unit_limit,
+            },
+            Err(err) => {
+                eprintln!("Error simulating transaction: {}", err);
+                self.fallback_compute_unit_limit
+            }
+        }
+    }
+
+    pub async fn estimate(
+        &self,
+        chain_client: &dyn ChainClient,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+        lookup_tables: &[AddressLookupTableAccount],
+        writable_accounts: &[Pubkey],
+    ) -> FeeEstimate {
+        let fees = self.recent_fees(chain_client, writable_accounts).await;
+        FeeEstimate {
+            compute_unit_price: self.price_at(fees.clone(), self.percentile),
+            high_compute_unit_price: self.price_at(fees, HIGH_PERCENTILE.max(self.percentile)),
+            compute_unit_limit: self.compute_unit_limit(chain_client, payer, instructions, lookup_tables).await,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FeeEstimate {
+    //micro-lamports per compute unit
+    pub compute_unit_price: u64,
+    //the same recent fees at HIGH_PERCENTILE, not what this transaction pays
+    pub high_compute_unit_price: u64,
+    pub compute_unit_limit: u32,
+}
+
+impl FeeEstimate {
+    pub fn budget_instructions(&self) -> Vec<Instruction> {
+        vec![
+            ComputeBudgetInstruction::set_compute_unit_price(self.compute_unit_price),
+            ComputeBudgetInstruction::set_compute_unit_limit(self.compute_unit_limit),
+        ]
+    }
+
+    //the priority fee is charged on the requested limit, not on what the transaction ends up using
+    pub fn priority_fee_lamports(&self) -> u64 {
+        let micro_lamports = self.compute_unit_price as u128 * self.compute_unit_limit as u128;
+        micro_lamports.div_ceil(MICRO_LAMPORTS_PER_LAMPORT as u128) as u64
+    }
+
+    pub fn total_fee_lamports(&self, signatures: usize) -> u64 {
+        LAMPORTS_PER_SIGNATURE * signatures as u64 + self.priority_fee_lamports()
+    }
+}
+
+//shared between a bot and its strategy so the ui can show what each bot has spent
+#[derive(Default)]
+pub struct FeeTracker {
+    lamports: AtomicU64,
+    priority_lamports: AtomicU64,
+    transactions: AtomicU64,
+    high_compute_unit_price: AtomicU64,
+}
+
+impl FeeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, estimate: &FeeEstimate, signatures: usize) {
+        self.lamports.fetch_add(estimate.total_fee_lamports(signatures), Ordering::Relaxed);
+        self.priority_lamports.fetch_add(estimate.priority_fee_lamports(), Ordering::Relaxed);
+        self.transactions.fetch_add(1, Ordering::Relaxed);
+        self.high_compute_unit_price.store(estimate.high_compute_unit_price, Ordering::Relaxed);
+    }
+
+    //from the latest recorded transaction, None until the bot has sent one
+    pub fn high_compute_unit_price(&self) -> Option<u64> {
+        Some(self.high_compute_unit_price.load(Ordering::Relaxed)).filter(|price| *price > 0)
+    }
+
+    pub fn sol_spent(&self) -> f64 {
+        self.lamports.load(Ordering::Relaxed) as f64 / LAMPORTS_PER_SOL as f64
+    }
+
+    pub fn priority_sol_spent(&self) -> f64 {
+        self.priority_lamports.load(Ordering::Relaxed) as f64 / LAMPORTS_PER_SOL as f64
+    }
+
+    pub fn transaction_count(&self) -> u64 {
+        self.transactions.load(Ordering::Relaxed)
+    }
+}
+
+fn percentile(mut values: Vec<u64>, percentile: f64) -> Option<u64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let rank = (percentile.clamp(0.0, 100.0) / 100.0 * (values.len() - 1) as f64).round() as usize;
+    Some(values[rank])
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::system_instruction;
+
+    use crate::rpc::mock_chain_client::MockChainClient;
+
+    use super::*;
+
+    #[test]
+    fn percentile_picks_the_nearest_rank() {
+        let fees = vec![40, 10, 30, 20, 50];
+        assert_eq!(percentile(fees.clone(), 0.0), Some(10));
+        assert_eq!(percentile(fees.clone(), 50.0), Some(30));
+        assert_eq!(percentile(fees.clone(), 75.0), Some(40));
+        assert_eq!(percentile(fees.clone(), 100.0), Some(50));
+        assert_eq!(percentile(fees, 250.0), Some(50));
+        assert_eq!(percentile(Vec::new(), 75.0), None);
+    }
+
+    #[tokio::test]
+    async fn compute_unit_price_is_clamped_to_the_policy() {
+        let chain_client = MockChainClient::new();
+        let policy = PriorityFeePolicy::new();
+
+        //nothing recent to go on
+        assert_eq!(policy.compute_unit_price(&chain_client, &[]).await, policy.min_micro_lamports);
+
+        chain_client.set_prioritization_fees(vec![0, 20_000, 40_000, 60_000, 80_000]);
+        assert_eq!(policy.compute_unit_price(&chain_client, &[]).await, 60_000);
+
+        chain_client.set_prioritization_fees(vec![1, 2, 3]);
+        assert_eq!(policy.compute_unit_price(&chain_client, &[]).await, policy.min_micro_lamports);
+
+        chain_client.set_prioritization_fees(vec![5_000_000; 4]);
+        assert_eq!(policy.compute_unit_price(&chain_client, &[]).await, policy.max_micro_lamports);
+    }
+
+    #[tokio::test]
+    async fn compute_unit_limit_adds_the_margin_to_the_simulation() {
+        let chain_client = MockChainClient::new();
+        let policy = PriorityFeePolicy::new();
+        let payer = Pubkey::new_unique();
+        let instructions = [system_instruction::transfer(&payer, &Pubkey::new_unique(), 1)];
+
+        chain_client.set_units_consumed(100_000);
+        assert_eq!(policy.compute_unit_limit(&chain_client, &payer, &instructions, &[]).await, 120_000);
+
+        chain_client.set_units_consumed(100_001);
+        assert_eq!(policy.compute_unit_limit(&chain_client, &payer, &instructions, &[]).await, 120_002);
+
+        chain_client.set_units_consumed(1_300_000);
+        assert_eq!(policy.compute_unit_limit(&chain_client, &payer, &instructions, &[]).await, MAX_COMPUTE_UNIT_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn estimate_prices_the_requested_limit() {
+        let chain_client = MockChainClient::new();
+        chain_client.set_prioritization_fees(vec![100_000]);
+        chain_client.set_units_consumed(50_000);
+        let payer = Pubkey::new_unique();
+        let instructions = [system_instruction::transfer(&payer, &Pubkey::new_unique(), 1)];
+
+        let estimate = PriorityFeePolicy::new().estimate(&chain_client, &payer, &instructions, &[], &[]).await;
+        assert_eq!(estimate.compute_unit_price, 100_000);
+        assert_eq!(estimate.high_compute_unit_price, 100_000);
+        assert_eq!(estimate.compute_unit_limit, 60_000);
+        //100_000 micro-lamports * 60_000 CU = 6_000 lamports
+        assert_eq!(estimate.priority_fee_lamports(), 6_000);
+        assert_eq!(estimate.total_fee_lamports(2), 16_000);
+        assert_eq!(estimate.budget_instructions().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn tracker_keeps_the_latest_high_price() {
+        let chain_client = MockChainClient::new();
+        let payer = Pubkey::new_unique();
+        let instructions = [system_instruction::transfer(&payer, &Pubkey::new_unique(), 1)];
+        let tracker = FeeTracker::new();
+        assert_eq!(tracker.high_compute_unit_price(), None);
+
+        //21 slots, the 75th percentile is the 16th and the 95th the 20th
+        chain_client.set_prioritization_fees((0..=20).map(|i| i * 10_000).collect());
+        let estimate = PriorityFeePolicy::new().estimate(&chain_client, &payer, &instructions, &[], &[]).await;
+        assert_eq!(estimate.compute_unit_price, 150_000);
+        assert_eq!(estimate.high_compute_unit_price, 190_000);
+        tracker.record(&estimate, 1);
+        assert_eq!(tracker.high_compute_unit_price(), Some(190_000));
+
+        chain_client.set_prioritization_fees(vec![5_000_000; 4]);
+        let estimate = PriorityFeePolicy::new().estimate(&chain_client, &payer, &instructions, &[], &[]).await;
+        tracker.record(&estimate, 1);
+        assert_eq!(tracker.high_compute_unit_price(), Some(PriorityFeePolicy::new().max_micro_lamports));
+    }
+}